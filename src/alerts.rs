@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use crate::language::{Language, TextId, get_text};
+use crate::provider::QuoteSeries;
+
+// A fired rule re-arms only after the price (or day change) moved back this far, in percent of the threshold
+const HYSTERESIS_PERCENT: f64 = 0.5;
+// Number of fired alerts kept in the in-app history
+pub const MAX_HISTORY: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind")]
+pub enum AlertCondition {
+    CrossAbove { price: f64 },
+    CrossBelow { price: f64 },
+    PercentChange { percent: f64 }, // Absolute change of the current day in either direction
+    NewHigh52w,
+}
+
+/// Condition type without its threshold, used to pick the condition in the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    CrossAbove,
+    CrossBelow,
+    PercentChange,
+    NewHigh52w,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 4] = [AlertKind::CrossAbove, AlertKind::CrossBelow, AlertKind::PercentChange, AlertKind::NewHigh52w];
+
+    pub fn condition(self, value: f64) -> AlertCondition {
+        match self {
+            AlertKind::CrossAbove => AlertCondition::CrossAbove { price: value },
+            AlertKind::CrossBelow => AlertCondition::CrossBelow { price: value },
+            AlertKind::PercentChange => AlertCondition::PercentChange { percent: value },
+            AlertKind::NewHigh52w => AlertCondition::NewHigh52w,
+        }
+    }
+
+    pub fn label(self, lang: Language) -> &'static str {
+        let id = match self {
+            AlertKind::CrossAbove => TextId::AlertKindCrossAbove,
+            AlertKind::CrossBelow => TextId::AlertKindCrossBelow,
+            AlertKind::PercentChange => TextId::AlertKindPercentChange,
+            AlertKind::NewHigh52w => TextId::AlertKindNewHigh,
+        };
+        get_text(lang, id)
+    }
+}
+
+impl AlertCondition {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            AlertCondition::CrossAbove { .. } => AlertKind::CrossAbove,
+            AlertCondition::CrossBelow { .. } => AlertKind::CrossBelow,
+            AlertCondition::PercentChange { .. } => AlertKind::PercentChange,
+            AlertCondition::NewHigh52w => AlertKind::NewHigh52w,
+        }
+    }
+
+    /// Threshold of the condition, None if it has none.
+    pub fn value(&self) -> Option<f64> {
+        match *self {
+            AlertCondition::CrossAbove { price } | AlertCondition::CrossBelow { price } => Some(price),
+            AlertCondition::PercentChange { percent } => Some(percent),
+            AlertCondition::NewHigh52w => None,
+        }
+    }
+
+    /// Timeframe whose data the condition is evaluated on, None = any loaded timeframe (only the last price is used).
+    pub fn timeframe(&self) -> Option<&'static str> {
+        match self {
+            AlertCondition::CrossAbove { .. } | AlertCondition::CrossBelow { .. } => None,
+            AlertCondition::PercentChange { .. } => Some("1D"),
+            AlertCondition::NewHigh52w => Some("1Y"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub id: u64,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_cooldown")]
+    pub cooldown_minutes: u64,
+    #[serde(default = "default_true")]
+    pub armed: bool, // Cleared when the rule fires, set again once the condition is no longer met (with hysteresis)
+    #[serde(default)]
+    pub last_fired: Option<i64>, // Unix timestamp
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cooldown() -> u64 {
+    60
+}
+
+impl AlertRule {
+    pub fn new(id: u64, symbol: String, condition: AlertCondition) -> Self {
+        Self {
+            id,
+            symbol,
+            condition,
+            enabled: true,
+            cooldown_minutes: default_cooldown(),
+            armed: true,
+            last_fired: None,
+        }
+    }
+
+    /// Updates the rule state with a freshly loaded series and returns the alert if it fires.
+    fn evaluate(&mut self, timeframe: &str, series: &QuoteSeries, now: i64) -> Option<AlertEvent> {
+        if !self.enabled {
+            return None;
+        }
+        if self.condition.timeframe().is_some_and(|tf| tf != timeframe) {
+            return None;
+        }
+        let last = series.candles.last()?;
+        let h = HYSTERESIS_PERCENT / 100.0;
+
+        // (condition met, far enough on the other side to re-arm)
+        let (active, rearm) = match self.condition {
+            AlertCondition::CrossAbove { price } => (last.close >= price, last.close < price * (1.0 - h)),
+            AlertCondition::CrossBelow { price } => (last.close <= price, last.close > price * (1.0 + h)),
+            AlertCondition::PercentChange { percent } => {
+                let first = series.candles.first()?.open;
+                if first == 0.0 {
+                    return None;
+                }
+                let change = ((last.close - first) / first * 100.0).abs();
+                (change >= percent, change < percent * (1.0 - h))
+            },
+            AlertCondition::NewHigh52w => {
+                let (_, previous) = series.candles.split_last()?;
+                let previous_high = previous.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+                let high = previous_high.max(last.high);
+                (last.high > previous_high, last.close < high * (1.0 - h))
+            },
+        };
+
+        if rearm {
+            self.armed = true;
+        }
+        if !active || !self.armed {
+            return None;
+        }
+        let cooldown = self.cooldown_minutes as i64 * 60;
+        if self.last_fired.is_some_and(|t| now - t < cooldown) {
+            return None;
+        }
+
+        self.armed = false;
+        self.last_fired = Some(now);
+        Some(AlertEvent {
+            rule_id: self.id,
+            symbol: self.symbol.clone(),
+            condition: self.condition,
+            price: last.close,
+            fired_at: now,
+        })
+    }
+}
+
+/// A fired alert, kept in the in-app history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub rule_id: u64,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub price: f64,
+    pub fired_at: i64,
+}
+
+impl AlertEvent {
+    pub fn message(&self, lang: Language) -> String {
+        let template = match self.condition {
+            AlertCondition::CrossAbove { price } => get_text(lang, TextId::AlertCrossAbove).replacen("{}", &format!("{:.2}", price), 1),
+            AlertCondition::CrossBelow { price } => get_text(lang, TextId::AlertCrossBelow).replacen("{}", &format!("{:.2}", price), 1),
+            AlertCondition::PercentChange { percent } => get_text(lang, TextId::AlertPercentChange).replacen("{}", &format!("{:.1}", percent), 1),
+            AlertCondition::NewHigh52w => get_text(lang, TextId::AlertNewHigh).to_string(),
+        };
+        format!("{}: {} ({:.2})", self.symbol, template, self.price)
+    }
+}
+
+/// Evaluates all rules for `symbol` against a freshly loaded series and returns the alerts that fired.
+pub fn evaluate(rules: &mut [AlertRule], symbol: &str, timeframe: &str, series: &QuoteSeries, now: i64) -> Vec<AlertEvent> {
+    rules
+        .iter_mut()
+        .filter(|r| r.symbol == symbol)
+        .filter_map(|r| r.evaluate(timeframe, series, now))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Candle;
+
+    const HOUR: i64 = 60 * 60;
+
+    fn candle(open: f64, close: f64) -> Candle {
+        Candle { timestamp: 0, open, high: open.max(close), low: open.min(close), close, volume: 0 }
+    }
+
+    // A day that opened at `open` and trades at `close` now
+    fn series(open: f64, close: f64) -> QuoteSeries {
+        QuoteSeries { candles: vec![candle(open, open), candle(open, close)], currency: "USD".to_string() }
+    }
+
+    fn rule(condition: AlertCondition) -> AlertRule {
+        AlertRule::new(1, "AAPL".to_string(), condition)
+    }
+
+    #[test]
+    fn cross_above_fires_once_until_rearmed() {
+        let mut rule = rule(AlertCondition::CrossAbove { price: 100.0 });
+        assert!(rule.evaluate("1D", &series(90.0, 99.0), 0).is_none());
+
+        let event = rule.evaluate("1D", &series(90.0, 101.0), 0).unwrap();
+        assert_eq!((event.rule_id, event.price), (1, 101.0));
+        assert!(!rule.armed);
+        assert_eq!(rule.last_fired, Some(0));
+
+        // Still above, or only just below the threshold: no new alert
+        assert!(rule.evaluate("1D", &series(90.0, 102.0), 2 * HOUR).is_none());
+        assert!(rule.evaluate("1D", &series(90.0, 99.8), 2 * HOUR).is_none());
+        assert!(!rule.armed);
+
+        // Far enough below re-arms, the next cross fires again
+        assert!(rule.evaluate("1D", &series(90.0, 99.0), 2 * HOUR).is_none());
+        assert!(rule.armed);
+        assert!(rule.evaluate("1D", &series(90.0, 100.5), 2 * HOUR).is_some());
+    }
+
+    #[test]
+    fn cooldown_holds_back_a_rearmed_rule() {
+        let mut rule = rule(AlertCondition::CrossBelow { price: 100.0 });
+        assert!(rule.evaluate("1D", &series(110.0, 99.0), 0).is_some());
+        assert!(rule.evaluate("1D", &series(110.0, 105.0), 10 * 60).is_none());
+        assert!(rule.armed);
+
+        assert!(rule.evaluate("1D", &series(110.0, 98.0), 30 * 60).is_none());
+        assert!(rule.armed);
+        assert!(rule.evaluate("1D", &series(110.0, 98.0), HOUR).is_some());
+    }
+
+    #[test]
+    fn percent_change_rearms_for_small_thresholds() {
+        let mut rule = rule(AlertCondition::PercentChange { percent: 0.3 });
+        // Only evaluated on the day's data
+        assert!(rule.evaluate("1W", &series(100.0, 101.0), 0).is_none());
+
+        assert!(rule.evaluate("1D", &series(100.0, 99.6), 0).is_some());
+        assert!(rule.evaluate("1D", &series(100.0, 100.1), HOUR).is_none());
+        assert!(rule.armed);
+        assert!(rule.evaluate("1D", &series(100.0, 100.4), 2 * HOUR).is_some());
+    }
+
+    #[test]
+    fn new_52w_high_compares_with_the_earlier_candles() {
+        let mut rule = rule(AlertCondition::NewHigh52w);
+        let year = |last: f64| QuoteSeries { candles: vec![candle(100.0, 120.0), candle(120.0, 110.0), candle(110.0, last)], currency: "USD".to_string() };
+
+        assert!(rule.evaluate("1D", &year(125.0), 0).is_none());
+        assert!(rule.evaluate("1Y", &year(115.0), 0).is_none());
+        let event = rule.evaluate("1Y", &year(125.0), 0).unwrap();
+        assert_eq!(event.condition, AlertCondition::NewHigh52w);
+
+        // Back below the high re-arms it
+        assert!(rule.evaluate("1Y", &year(110.0), 2 * HOUR).is_none());
+        assert!(rule.armed);
+    }
+
+    #[test]
+    fn disabled_rules_and_other_symbols_are_skipped() {
+        let mut rules = [rule(AlertCondition::CrossAbove { price: 100.0 }), rule(AlertCondition::CrossAbove { price: 100.0 })];
+        rules[1].id = 2;
+        rules[1].enabled = false;
+
+        assert!(evaluate(&mut rules, "MSFT", "1D", &series(90.0, 101.0), 0).is_empty());
+        let fired = evaluate(&mut rules, "AAPL", "1D", &series(90.0, 101.0), 0);
+        assert_eq!(fired.iter().map(|e| e.rule_id).collect::<Vec<_>>(), vec![1]);
+    }
+}
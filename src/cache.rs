@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use chrono::{DateTime, Local};
+use crate::config::{self, AppConfig};
+use crate::provider::QuoteSeries;
+
+const CACHE_FILE: &str = "quote_cache.json";
+// Entries older than this are dropped on load/insert
+const MAX_AGE_DAYS: i64 = 7;
+// Oldest entries are evicted beyond this
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub series: QuoteSeries,
+    pub fetched_at: i64, // Unix seconds
+}
+
+impl CacheEntry {
+    pub fn fetched_at(&self) -> DateTime<Local> {
+        DateTime::from_timestamp(self.fetched_at, 0).unwrap_or_default().with_timezone(&Local)
+    }
+}
+
+/// Quote cache persisted next to config.json, so charts can show the last known data right after startup.
+/// Keyed by symbol/timeframe/interval.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct QuoteCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+fn cache_key(symbol: &str, timeframe: &str, interval: &str) -> String {
+    format!("{}|{}|{}", symbol, timeframe, interval)
+}
+
+impl QuoteCache {
+    fn path() -> PathBuf {
+        AppConfig::path().with_file_name(CACHE_FILE)
+    }
+
+    pub fn load() -> Self {
+        let path = Self::path();
+        let mut cache = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Failed to parse quote cache {:?}: {}", path, e);
+                QuoteCache::default()
+            }),
+            Err(_) => QuoteCache::default(),
+        };
+        cache.prune();
+        log::info!("Loaded {} cached quote series from {:?}", cache.entries.len(), path);
+        cache
+    }
+
+    /// Saves a cache shared with the fetch workers. Its lock is only held while serializing,
+    /// not during the disk write.
+    pub fn save(cache: &Mutex<QuoteCache>) {
+        // One save at a time, so an older snapshot can't overwrite a newer one
+        static SAVING: Mutex<()> = Mutex::new(());
+        let _saving = SAVING.lock().unwrap();
+
+        let content = match serde_json::to_string(&*cache.lock().unwrap()) {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Failed to serialize quote cache: {}", e);
+                return;
+            }
+        };
+        let path = Self::path();
+        if let Err(e) = config::write_atomic(&path, &content) {
+            log::error!("Failed to save quote cache to {:?}: {}", path, e);
+        }
+    }
+
+    pub fn get(&self, symbol: &str, timeframe: &str, interval: &str) -> Option<&CacheEntry> {
+        self.entries.get(&cache_key(symbol, timeframe, interval))
+    }
+
+    pub fn insert(&mut self, symbol: &str, timeframe: &str, interval: &str, series: QuoteSeries) {
+        self.entries.insert(cache_key(symbol, timeframe, interval), CacheEntry {
+            series,
+            fetched_at: Local::now().timestamp(),
+        });
+        self.prune();
+    }
+
+    fn prune(&mut self) {
+        let cutoff = Local::now().timestamp() - MAX_AGE_DAYS * 24 * 60 * 60;
+        self.entries.retain(|_, e| e.fetched_at >= cutoff);
+
+        if self.entries.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(String, i64)> = self.entries.iter().map(|(k, e)| (k.clone(), e.fetched_at)).collect();
+            by_age.sort_by_key(|(_, ts)| *ts);
+            let excess = self.entries.len() - MAX_ENTRIES;
+            for (key, _) in by_age.into_iter().take(excess) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Candle;
+
+    fn series(close: f64) -> QuoteSeries {
+        QuoteSeries { candles: vec![Candle { timestamp: 0, open: close, high: close, low: close, close, volume: 0 }], currency: "USD".to_string() }
+    }
+
+    fn entry(close: f64, age_seconds: i64) -> CacheEntry {
+        CacheEntry { series: series(close), fetched_at: Local::now().timestamp() - age_seconds }
+    }
+
+    #[test]
+    fn entries_are_keyed_by_interval() {
+        let mut cache = QuoteCache::default();
+        cache.insert("AAPL", "1D", "5m", series(1.0));
+        cache.insert("AAPL", "1D", "15m", series(2.0));
+
+        assert_eq!(cache.get("AAPL", "1D", "5m").unwrap().series.candles[0].close, 1.0);
+        assert_eq!(cache.get("AAPL", "1D", "15m").unwrap().series.candles[0].close, 2.0);
+        assert!(cache.get("AAPL", "1W", "5m").is_none());
+    }
+
+    #[test]
+    fn prune_drops_expired_entries() {
+        let day = 24 * 60 * 60;
+        let mut cache = QuoteCache::default();
+        cache.entries.insert(cache_key("OLD", "1D", "5m"), entry(1.0, MAX_AGE_DAYS * day + 60));
+        cache.entries.insert(cache_key("RECENT", "1D", "5m"), entry(2.0, MAX_AGE_DAYS * day - 60));
+
+        cache.prune();
+
+        assert!(cache.get("OLD", "1D", "5m").is_none());
+        assert!(cache.get("RECENT", "1D", "5m").is_some());
+    }
+
+    #[test]
+    fn prune_evicts_the_oldest_beyond_the_limit() {
+        let mut cache = QuoteCache::default();
+        for i in 0..MAX_ENTRIES + 5 {
+            // Symbol 0 is the oldest
+            cache.entries.insert(cache_key(&i.to_string(), "1D", "5m"), entry(i as f64, (MAX_ENTRIES + 5 - i) as i64));
+        }
+
+        cache.prune();
+
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert!((0..5).all(|i| cache.get(&i.to_string(), "1D", "5m").is_none()));
+        assert!(cache.get("5", "1D", "5m").is_some());
+    }
+}
@@ -1,208 +1,150 @@
 use winit::window::{Window, WindowLevel};
 use winit::window::WindowId;
-use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::event_loop::ActiveEventLoop;
 use winit::event::{WindowEvent, ElementState, MouseButton};
+use winit::dpi::PhysicalPosition;
 use std::rc::Rc;
 use softbuffer::{Context, Surface};
 use std::num::NonZeroU32;
-use plotters::prelude::*;
-use plotters::backend::BitMapBackend;
-use crate::common::{WindowHandler, UserEvent};
-use chrono::{DateTime, Local, Utc};
-use time::OffsetDateTime;
+use crate::common::WindowHandler;
+use crate::fetcher::{CachedSeries, FetchService};
+use crate::market_hours::{self, Session};
+use chrono::{DateTime, Local};
 use std::collections::HashMap;
 use std::time::Instant;
-use winit::platform::windows::WindowAttributesExtWindows;
 #[cfg(target_os = "macos")]
 use window_vibrancy::{apply_vibrancy, NSVisualEffectMaterial};
-use yahoo_finance_api as yahoo;
-use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
-use std::ffi::c_void;
-use crate::language::{Language, AppError};
-
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::Graphics::Dwm::DwmExtendFrameIntoClientArea;
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::UI::Controls::MARGINS;
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::Foundation::{HWND, WPARAM, LPARAM, LRESULT, RECT, FARPROC, BOOL};
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::UI::Shell::{SetWindowSubclass, DefSubclassProc};
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowRect, SetWindowPos, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTLEFT, HTRIGHT, HTTOP, HTTOPLEFT, HTTOPRIGHT, SWP_NOMOVE, SWP_NOSIZE, SWP_NOACTIVATE};
-#[cfg(target_os = "windows")]
-use windows_sys::Win32::System::LibraryLoader::{LoadLibraryA, GetProcAddress};
-
-#[cfg(target_os = "windows")]
-#[repr(C)]
-#[allow(non_snake_case)]
-struct ACCENT_POLICY {
-    AccentState: u32,
-    AccentFlags: u32,
-    GradientColor: u32,
-    AnimationId: u32,
-}
-
-#[cfg(target_os = "windows")]
-#[repr(C)]
-#[allow(non_snake_case)]
-struct WINDOWCOMPOSITIONATTRIBDATA {
-    Attrib: u32,
-    pvData: *mut c_void,
-    cbData: usize,
-}
-
-#[cfg(target_os = "windows")]
-#[derive(PartialEq)]
-#[repr(C)]
-#[allow(non_camel_case_types)]
-// #[allow(dead_code)]
-enum ACCENT_STATE {
-    // ACCENT_DISABLED = 0,
-    // ACCENT_ENABLE_GRADIENT = 1,
-    // ACCENT_ENABLE_TRANSPARENTGRADIENT = 2,
-    // ACCENT_ENABLE_BLURBEHIND = 3,
-    ACCENT_ENABLE_ACRYLICBLURBEHIND = 4,
-    // ACCENT_INVALID_STATE = 5,
-}
-
-#[cfg(target_os = "windows")]
-unsafe extern "system" fn subclass_proc(
-    hwnd: HWND,
-    msg: u32,
-    wparam: WPARAM,
-    lparam: LPARAM,
-    _uid_subclass: usize,
-    _dw_ref_data: usize,
-) -> LRESULT {
-    const WM_NCHITTEST: u32 = 0x0084;
-    const WM_MOUSEACTIVATE: u32 = 0x0021;
-    const MA_NOACTIVATE: LRESULT = 3;
-
-    // Prevent activation on click
-    if msg == WM_MOUSEACTIVATE {
-        return MA_NOACTIVATE;
-    }
-
-    // Only handle Resize if Unlocked (ref_data == 0)
-    let locked = _dw_ref_data == 1;
-
-    if msg == WM_NCHITTEST && !locked {
-        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
-        // SAFETY: GetWindowRect is called with a valid HWND and pointer to RECT.
-        unsafe { GetWindowRect(hwnd, &mut rect) };
+use crate::provider::{Candle, QuoteSeries};
+use crate::language::Language;
+use crate::indicators::Indicator;
+use crate::render::{ChartView, render_chart};
+use crate::currency::{FxRates, display_prices};
+use crate::platform;
+
+/// Creates a borderless, transparent desktop widget window pinned to the desktop layer.
+/// `placement` is the saved (x, y, width, height); windows saved on a monitor that is gone are moved onto the primary one.
+pub(crate) fn create_widget_window(event_loop: &ActiveEventLoop, title: &str, placement: Option<(i32, i32, u32, u32)>) -> Rc<Window> {
+    let window_attributes = Window::default_attributes()
+        .with_title(title)
+        .with_transparent(true)
+        .with_decorations(false)
+        .with_window_level(WindowLevel::AlwaysOnBottom)
+        .with_visible(false);
+    let mut window_attributes = platform::widget_attributes(window_attributes);
+
+    if let Some((mut x, mut y, width, height)) = placement {
+        let mut is_visible = false;
         
-        let x = (lparam & 0xFFFF) as i16 as i32;
-        let y = ((lparam >> 16) & 0xFFFF) as i16 as i32;
+        // Check visibility: at least a 10x10 corner must be visible or center visible
+        // Let's ensure the top-left corner + 20px is visible, or center.
+        // Strict: Top-Left must be within bounds.
+        for monitor in event_loop.available_monitors() {
+            let m_pos = monitor.position();
+            let m_size = monitor.size();
+            let m_width = m_size.width as i32;
+            let m_height = m_size.height as i32;
+            
+            // Check if Top-Left is inside
+            if x >= m_pos.x && x < m_pos.x + m_width &&
+               y >= m_pos.y && y < m_pos.y + m_height {
+                is_visible = true;
+                break;
+            }
+        }
         
-        let border_width = 8;
-        let bottom_border_height = 8; 
-
-        let left = x < rect.left + border_width;
-        let right = x >= rect.right - border_width;
-        let top = y < rect.top + border_width;
-        let bottom = y >= rect.bottom - bottom_border_height;
-
-        if top && left { return HTTOPLEFT as LRESULT; }
-        if top && right { return HTTOPRIGHT as LRESULT; }
-        if bottom && left { return HTBOTTOMLEFT as LRESULT; }
-        if bottom && right { return HTBOTTOMRIGHT as LRESULT; }
-        if left { return HTLEFT as LRESULT; }
-        if right { return HTRIGHT as LRESULT; }
-        if top { return HTTOP as LRESULT; }
-        if bottom { return HTBOTTOM as LRESULT; }
-    }
-
-    // SAFETY: DefSubclassProc is safe to call with valid HWND.
-    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
-}
+        if !is_visible {
+            // Find primary or first
+            let target_monitor = event_loop.primary_monitor().or_else(|| event_loop.available_monitors().next());
+            if let Some(monitor) = target_monitor {
+                let m_pos = monitor.position();
+                // Place at 50, 50 relative to monitor
+                x = m_pos.x + 50;
+                y = m_pos.y + 50;
+            } else {
+                x = 50;
+                y = 50;
+            }
+        }
 
-#[cfg(target_os = "windows")]
-fn get_function_impl(library: &str, function: &str) -> Option<FARPROC> {
-    let module = unsafe { LoadLibraryA(library.as_ptr()) };
-    if module == 0 {
-        return None;
+        window_attributes = window_attributes
+            .with_position(winit::dpi::PhysicalPosition::new(x, y))
+            .with_inner_size(winit::dpi::PhysicalSize::new(width, height));
     }
-    Some(unsafe { GetProcAddress(module, function.as_ptr()) })
-}
 
-#[cfg(target_os = "windows")]
-unsafe fn set_window_composition_attribute(hwnd: HWND, accent_state: ACCENT_STATE, color: Option<(u8, u8, u8, u8)>) {
-    type SetWindowCompositionAttributeFn = unsafe extern "system" fn(HWND, *mut WINDOWCOMPOSITIONATTRIBDATA) -> BOOL;
+    let window = Rc::new(event_loop.create_window(window_attributes).unwrap());
 
-    let library = "user32.dll\0";
-    let function = "SetWindowCompositionAttribute\0";
+    #[cfg(target_os = "macos")]
+    apply_vibrancy(&*window, NSVisualEffectMaterial::HudWindow, None, None).expect("Unsupported platform!");
 
-    if let Some(proc) = get_function_impl(library, function) {
-        // SAFETY: Casting FARPROC to function pointer signature we expect.
-        let set_window_composition_attribute: SetWindowCompositionAttributeFn = unsafe { std::mem::transmute(proc) };
-        
-        let mut color = color.unwrap_or((0, 0, 0, 0));
-        let is_acrylic = accent_state == ACCENT_STATE::ACCENT_ENABLE_ACRYLICBLURBEHIND;
-        if is_acrylic && color.3 == 0 {
-             color.3 = 1;
-        }
-
-        let mut policy = ACCENT_POLICY {
-            AccentState: accent_state as u32,
-            AccentFlags: if is_acrylic { 0 } else { 2 },
-            GradientColor: (color.0 as u32)
-                | ((color.1 as u32) << 8)
-                | ((color.2 as u32) << 16)
-                | ((color.3 as u32) << 24),
-            AnimationId: 0,
-        };
+    platform::init_widget_window(&window);
 
-        let mut data = WINDOWCOMPOSITIONATTRIBDATA {
-            Attrib: 0x13, // WCA_ACCENT_POLICY
-            pvData: &mut policy as *mut _ as *mut c_void,
-            cbData: std::mem::size_of_val(&policy),
-        };
+    window
+}
 
-        // SAFETY: Calling loaded function pointer.
-        unsafe { set_window_composition_attribute(hwnd, &mut data) };
-    }
+/// Shows a widget once it has something to draw, below all other windows.
+pub(crate) fn show_widget(window: &Window) {
+    window.set_visible(true);
+    platform::force_to_bottom(window);
+    window.request_redraw();
 }
 
-fn apply_shadow(window: &Window) {
-    #[cfg(target_os = "windows")]
-    {
-        if let Ok(handle) = window.window_handle() {
-            if let RawWindowHandle::Win32(handle) = handle.as_raw() {
-                let hwnd = handle.hwnd.get() as HWND;
-                let margins = MARGINS {
-                    cxLeftWidth: 1,
-                    cxRightWidth: 1,
-                    cyTopHeight: 1,
-                    cyBottomHeight: 1,
-                };
-                unsafe {
-                    DwmExtendFrameIntoClientArea(hwnd, &margins);
-                    // Use a separate function or logic to manage subclass based on lock state
-                    // SetWindowSubclass(hwnd, Some(subclass_proc), 1, 0); // Moved to set_locked logic
-                    
-                    // Manual Acrylic Application
-                    set_window_composition_attribute(
-                        hwnd, 
-                        ACCENT_STATE::ACCENT_ENABLE_ACRYLICBLURBEHIND, 
-                        Some((18, 18, 18, 125))
-                    );
-                }
-            }
+/// Copies the opaque pixels of a rendered RGBA image into a softbuffer (0RGB), transparent ones are skipped.
+pub(crate) fn blit_rgba(buffer: &mut [u32], rgba: &[u8]) {
+    for (target, pixel) in buffer.iter_mut().zip(rgba.chunks(4)) {
+        if pixel[3] != 0 {
+            *target = ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | pixel[2] as u32;
         }
     }
 }
 
-fn update_subclass(window: &Window, locked: bool) {
-    #[cfg(target_os = "windows")]
-    {
-        if let Ok(handle) = window.window_handle() {
-            if let RawWindowHandle::Win32(handle) = handle.as_raw() {
-                let hwnd = handle.hwnd.get() as HWND;
-                let ref_data = if locked { 1 } else { 0 };
-                unsafe {
-                     SetWindowSubclass(hwnd, Some(subclass_proc), 1, ref_data);
-                }
+/// Yellow rounded frame shown while a widget is unlocked and can be moved/resized.
+pub(crate) fn draw_unlocked_frame(buffer: &mut [u32], width: i32, height: i32) {
+    let frame_color = 0xFFFF00; // Yellow
+    let thickness = 3; 
+    let radius = 12; // Radius for rounded corners
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut in_border = false;
+
+            // Check corners
+            if x < radius && y < radius { // Top-Left
+                let d = ((x - radius).pow(2) + (y - radius).pow(2)) as f64;
+                let r_out = (radius as f64).powi(2);
+                let r_in = ((radius - thickness) as f64).powi(2);
+                if d <= r_out && d >= r_in { in_border = true; }
+            } else if x >= width - radius && y < radius { // Top-Right
+                let d = ((x - (width - radius)).pow(2) + (y - radius).pow(2)) as f64;
+                let r_out = (radius as f64).powi(2);
+                let r_in = ((radius - thickness) as f64).powi(2);
+                if d <= r_out && d >= r_in { in_border = true; }
+            } else if x < radius && y >= height - radius { // Bottom-Left
+                 let d = ((x - radius).pow(2) + (y - (height - radius)).pow(2)) as f64;
+                 let r_out = (radius as f64).powi(2);
+                 let r_in = ((radius - thickness) as f64).powi(2);
+                 if d <= r_out && d >= r_in { in_border = true; }
+            } else if x >= width - radius && y >= height - radius { // Bottom-Right
+                 let d = ((x - (width - radius)).pow(2) + (y - (height - radius)).pow(2)) as f64;
+                 let r_out = (radius as f64).powi(2);
+                 let r_in = ((radius - thickness) as f64).powi(2);
+                 if d <= r_out && d >= r_in { in_border = true; }
+            } else {
+                // Straight Edges
+                // Top Edge (between rounded corners)
+                if y < thickness && x >= radius && x < width - radius { in_border = true; }
+                // Bottom Edge
+                if y >= height - thickness && x >= radius && x < width - radius { in_border = true; }
+                // Left Edge
+                if x < thickness && y >= radius && y < height - radius { in_border = true; }
+                // Right Edge
+                if x >= width - thickness && y >= radius && y < height - radius { in_border = true; }
+            }
+
+            if in_border {
+                 let idx = (y * width + x) as usize;
+                 if idx < buffer.len() {
+                     buffer[idx] = frame_color;
+                 }
             }
         }
     }
@@ -217,85 +159,36 @@ pub struct ChartWindow {
     _context: Context<Rc<Window>>, 
     symbol: String,
     currency: String,
-    quotes: Option<Vec<yahoo::Quote>>,
+    quotes: Option<Vec<Candle>>,
     locked: bool,
-    proxy: EventLoopProxy<UserEvent>,
+    cursor_position: PhysicalPosition<f64>, // Last cursor position, decides between moving and resizing on Linux
+    fetcher: FetchService,
     last_fetch_time: Option<DateTime<Local>>,
     timeframe: String,
     
-    // Cache: Timeframe -> (Quotes, Currency, FetchTime)
-    cache: HashMap<String, (Vec<yahoo::Quote>, String, DateTime<Local>)>,
+    // Cache: Timeframe -> Series
+    cache: HashMap<String, CachedSeries>,
+    // Currently shown data was restored from the on-disk cache and has not been refreshed yet
+    stale: bool,
     pending_timeframe: Option<String>,
     last_timeframe_change: Option<Instant>,
     language: Language,
+    provider: Option<String>,
+    style: ChartStyle,
+    volume_pane_height: Option<u32>,
+    indicators: Vec<Indicator>,
+    compare_symbols: Vec<String>,
+    // (Symbol, Timeframe) -> Series of the compared symbols
+    compare_cache: HashMap<(String, String), CachedSeries>,
+    fx: Option<FxRates>, // Set while prices are shown in the base currency
+    session: Option<Session>, // None for symbols without a known exchange calendar
 }
 
-use crate::config::ChartConfig;
+use crate::config::{ChartConfig, ChartStyle, WidgetConfig};
 
 impl ChartWindow {
-    pub fn new(event_loop: &ActiveEventLoop, proxy: EventLoopProxy<UserEvent>, symbol: String, mut config: Option<ChartConfig>, language: Language) -> Self {
-        // ... (attributes setup)
-        let mut window_attributes = Window::default_attributes()
-            .with_title(&format!("Stock Chart - {}", symbol))
-            .with_transparent(true)
-            .with_decorations(false)
-            .with_window_level(WindowLevel::AlwaysOnBottom)
-            .with_skip_taskbar(true)
-            .with_visible(false); 
-
-        if let Some(cfg) = &mut config {
-            let mut is_visible = false;
-            let window_x = cfg.x;
-            let window_y = cfg.y;
-            
-            // Check visibility: at least a 10x10 corner must be visible or center visible
-            // Let's ensure the top-left corner + 20px is visible, or center.
-            // Strict: Top-Left must be within bounds.
-            for monitor in event_loop.available_monitors() {
-                let m_pos = monitor.position();
-                let m_size = monitor.size();
-                let m_width = m_size.width as i32;
-                let m_height = m_size.height as i32;
-                
-                // Simple AABB check for intersection
-                // Rect 1: window_x, window_y, ...
-                // Rect 2: m_pos.x, m_pos.y, width, height
-                
-                // Check if Top-Left is inside
-                if window_x >= m_pos.x && window_x < m_pos.x + m_width &&
-                   window_y >= m_pos.y && window_y < m_pos.y + m_height {
-                    is_visible = true;
-                    break;
-                }
-            }
-            
-            if !is_visible {
-                // Find primary or first
-                let target_monitor = event_loop.primary_monitor().or_else(|| event_loop.available_monitors().next());
-                if let Some(monitor) = target_monitor {
-                    let m_pos = monitor.position();
-                    // Place at 50, 50 relative to monitor
-                    cfg.x = m_pos.x + 50;
-                    cfg.y = m_pos.y + 50;
-                } else {
-                    cfg.x = 50;
-                    cfg.y = 50;
-                }
-            }
-
-
-            window_attributes = window_attributes
-                .with_position(winit::dpi::PhysicalPosition::new(cfg.x, cfg.y))
-                .with_inner_size(winit::dpi::PhysicalSize::new(cfg.width, cfg.height));
-        }
-
-        let window = Rc::new(event_loop.create_window(window_attributes).unwrap());
-
-        #[cfg(target_os = "macos")]
-        apply_vibrancy(&*window, NSVisualEffectMaterial::HudWindow, None, None).expect("Unsupported platform!");
-
-        // Since the window-shadows crate is deprecated and incompatible with the version of winit used in this project (causing the build failures you saw), I implemented the shadow logic manually using the windows-sys crate.
-        apply_shadow(&window);
+    pub fn new(event_loop: &ActiveEventLoop, fetcher: FetchService, symbol: String, config: Option<ChartConfig>, language: Language) -> Self {
+        let window = create_widget_window(event_loop, &format!("Stock Chart - {}", symbol), config.as_ref().map(|c| (c.x, c.y, c.width, c.height)));
 
         let context = Context::new(window.clone()).unwrap();
         let mut surface = Surface::new(&context, window.clone()).unwrap();
@@ -315,17 +208,36 @@ impl ChartWindow {
             currency: "USD".to_string(),
             quotes: None,
             locked: true,
-            proxy,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            fetcher,
             last_fetch_time: None,
             timeframe: config.as_ref().and_then(|c| c.timeframe.clone()).unwrap_or("1M".to_string()),
             cache: HashMap::new(),
+            stale: false,
             pending_timeframe: None,
             last_timeframe_change: None,
             language,
+            provider: config.as_ref().and_then(|c| c.provider.clone()),
+            style: config.as_ref().map(|c| c.style).unwrap_or_default(),
+            volume_pane_height: config.as_ref().and_then(|c| c.volume_pane_height),
+            indicators: config.as_ref().map(|c| c.indicators.clone()).unwrap_or_default(),
+            compare_symbols: config.as_ref().map(|c| c.compare_symbols.clone()).unwrap_or_default(),
+            compare_cache: HashMap::new(),
+            fx: None,
+            session: market_hours::session(&symbol),
         };
         
         // Initialize subclass
-        update_subclass(&chart.window, true);
+        platform::set_locked(&chart.window, true);
+
+        // Show the last persisted data right away (marked as stale) until the network answers
+        if let Some((series, fetched_at)) = chart.fetcher.cached(&chart.symbol, &chart.timeframe, chart.provider.as_deref()) {
+            chart.cache.insert(chart.timeframe.clone(), CachedSeries { series, fetched_at, from_disk: true });
+            chart.load_from_cache();
+            chart.window.set_visible(true);
+            platform::force_to_bottom(&chart.window);
+        }
+        chart.load_compare_from_disk();
         
         // Initial Fetch
         chart.refresh();
@@ -334,109 +246,48 @@ impl ChartWindow {
     }
 
     fn load_from_cache(&mut self) {
-         if let Some((quotes, currency, ts)) = self.cache.get(&self.timeframe) {
-             self.quotes = Some(quotes.clone());
-             self.currency = currency.clone();
-             self.last_fetch_time = Some(*ts);
+         if let Some(cached) = self.cache.get(&self.timeframe) {
+             self.quotes = Some(cached.series.candles.clone());
+             self.currency = cached.series.currency.clone();
+             self.last_fetch_time = Some(cached.fetched_at);
+             self.stale = cached.from_disk;
              self.window.request_redraw();
          }
     }
 
     fn fetch_data(&self) {
-        let proxy = self.proxy.clone();
-        let symbol = self.symbol.clone();
-        
-        let timeframe = self.timeframe.clone();
-        
-        log::info!("Fetching data for {} ({})", symbol, timeframe);
-        
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                let provider = yahoo::YahooConnector::new().unwrap();
-                
-                if timeframe == "1W" {
-                    // Stitching 7 days of 5m data
-                    let mut all_quotes = Vec::new();
-                    let now = Utc::now();
-                    let start = now - chrono::Duration::days(7);
-                    
-                    let mut currency = "USD".to_string();       
-                    // Fetch day by day to allow high resolution "5m"
-                    for i in 0..8 {
-                         let chunk_start = start + chrono::Duration::days(i as i64);
-                         let chunk_end = chunk_start + chrono::Duration::days(1);
-                         
-                         if chunk_start > now { break; }
-
-                         let sys_start: std::time::SystemTime = chunk_start.into();
-                         let sys_end: std::time::SystemTime = chunk_end.into();
-                         let odt_start = OffsetDateTime::from(sys_start);
-                         let odt_end = OffsetDateTime::from(sys_end);
-
-                         if let Ok(response) = provider.get_quote_history_interval(&symbol, odt_start, odt_end, "5m").await {
-                             if let Ok(meta) = response.metadata() {
-                                 currency = meta.currency.clone().unwrap_or("USD".to_string());
-                             }
-                             if let Ok(quotes) = response.quotes() {
-                                 all_quotes.extend(quotes);
-                             }
-                         }
-                    }
-                    
-                    // Dedup and sort
-                    all_quotes.sort_by_key(|q| q.timestamp);
-                    all_quotes.dedup_by_key(|q| q.timestamp);
-                    
-                     if !all_quotes.is_empty() {
-                          let _ = proxy.send_event(UserEvent::DataLoaded(symbol, all_quotes, currency));
-                     } else {
-                          let _ = proxy.send_event(UserEvent::Error(symbol, AppError::WeekDataError));
-                     }
-
-                } else {
-                    // Standard fetching
-                    let (interval, range) = match timeframe.as_str() {
-                        "1D" => ("2m", "1d"),
-                        "1M" => ("1d", "1mo"),
-                        "3M" => ("1d", "3mo"),
-                        "6M" => ("1d", "6mo"),
-                        "1Y" => ("1d", "1y"),
-                        "YTD" => ("1d", "ytd"),
-                        _ => ("1d", "1mo"),
-                    };
-                    match provider.get_quote_range(&symbol, interval, range).await {
-                        Ok(response) => {
-                             let currency = response.metadata().ok().and_then(|m| m.currency.clone()).unwrap_or("USD".to_string());
-                              if let Ok(quotes) = response.quotes() {
-                                  let _ = proxy.send_event(UserEvent::DataLoaded(symbol, quotes, currency));
-                              } else {
-                                  let _ = proxy.send_event(UserEvent::Error(symbol, AppError::NoQuotesFound));
-                              }
-                         },
-                         Err(e) => {
-                             log::error!("Yahoo API Error for {}: {:?}", symbol, e);
-                             let _ = proxy.send_event(UserEvent::Error(symbol, AppError::FetchError(e.to_string())));
-                         }
-                    }
-                }
-            });
-        });
+        self.fetcher.request(&self.symbol, &self.timeframe, self.provider.as_deref());
+        for symbol in &self.compare_symbols {
+            self.fetcher.request(symbol, &self.timeframe, self.provider.as_deref());
+        }
     }
 
-    fn force_to_bottom(&self) {
-        #[cfg(target_os = "windows")]
-        {
-            if let Ok(handle) = self.window.window_handle() {
-                if let RawWindowHandle::Win32(handle) = handle.as_raw() {
-                    let hwnd = handle.hwnd.get() as HWND;
-                    unsafe {
-                        SetWindowPos(hwnd, 1 as HWND, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE);
-                    }
-                }
+    // Persisted data of compared symbols for the current timeframe, stale until the network answers
+    fn load_compare_from_disk(&mut self) {
+        for symbol in &self.compare_symbols {
+            let key = (symbol.clone(), self.timeframe.clone());
+            if self.compare_cache.contains_key(&key) {
+                continue;
+            }
+            if let Some((series, fetched_at)) = self.fetcher.cached(symbol, &self.timeframe, self.provider.as_deref()) {
+                self.compare_cache.insert(key, CachedSeries { series, fetched_at, from_disk: true });
             }
         }
     }
+
+    // Requests compared symbols that have no fresh data for the current timeframe
+    fn fetch_compare(&self) {
+        for symbol in &self.compare_symbols {
+            let outdated = match self.compare_cache.get(&(symbol.clone(), self.timeframe.clone())) {
+                Some(cached) => market_hours::is_outdated(symbol, cached.fetched_at, cached.from_disk),
+                None => true,
+            };
+            if outdated {
+                self.fetcher.request(symbol, &self.timeframe, self.provider.as_deref());
+            }
+        }
+    }
+
 }
 
 impl WindowHandler for ChartWindow {
@@ -447,7 +298,7 @@ impl WindowHandler for ChartWindow {
 
     fn set_locked(&mut self, locked: bool) {
         self.locked = locked;
-        update_subclass(&self.window, locked);
+        platform::set_locked(&self.window, locked);
         self.window.request_redraw();
     }
 
@@ -457,24 +308,33 @@ impl WindowHandler for ChartWindow {
     }
 
     fn refresh(&mut self) {
-        // If data is older than 30 mins, fetch new
+        // If data is older than 30 mins (or only restored from disk) and the market may have moved since, fetch new
         if let Some(last) = self.last_fetch_time {
-             if (Local::now() - last).num_minutes() >= 30 {
+             if market_hours::is_outdated(&self.symbol, last, self.stale) {
                  self.fetch_data();
              }
         } else {
              self.fetch_data();
         }
+        self.fetch_compare();
+    }
+
+    fn set_style(&mut self, style: ChartStyle) {
+        self.style = style;
+        self.window.request_redraw();
+    }
+
+    fn set_indicators(&mut self, indicators: Vec<Indicator>) {
+        self.indicators = indicators;
+        self.window.request_redraw();
     }
 
     fn set_timeframe(&mut self, timeframe: String) {
         // Check cache first - if valid, apply immediately (no debounce needed)
         let mut cache_hit = false;
-        if let Some((_, _, ts)) = self.cache.get(&timeframe) {
-            // Check if outdated (30 mins)
-            if (Local::now() - *ts).num_minutes() < 30 {
-                cache_hit = true;
-            }
+        if let Some(cached) = self.cache.get(&timeframe)
+            && !market_hours::is_outdated(&self.symbol, cached.fetched_at, cached.from_disk) {
+            cache_hit = true;
         }
 
         if cache_hit {
@@ -482,6 +342,8 @@ impl WindowHandler for ChartWindow {
             self.timeframe = timeframe;
             self.pending_timeframe = None;
             self.load_from_cache();
+            self.load_compare_from_disk();
+            self.fetch_compare();
         } else {
             self.pending_timeframe = Some(timeframe.clone());
             self.last_timeframe_change = Some(Instant::now());
@@ -489,33 +351,79 @@ impl WindowHandler for ChartWindow {
     }
 
     fn tick(&mut self) {
-        if let Some(pending) = &self.pending_timeframe {
-            if let Some(last_change) = self.last_timeframe_change {
-                 if last_change.elapsed().as_millis() > 500 {
-                     // Commit
-                     self.timeframe = pending.clone();
-                     self.pending_timeframe = None;
-                     self.fetch_data();
-                 }
-            }
+        // The header badge follows the market opening and closing
+        let session = market_hours::session(&self.symbol);
+        if session != self.session {
+            self.session = session;
+            self.window.request_redraw();
+        }
+
+        if let Some(pending) = &self.pending_timeframe
+            && let Some(last_change) = self.last_timeframe_change
+            && last_change.elapsed().as_millis() > 500 {
+            // Commit
+            self.timeframe = pending.clone();
+            self.pending_timeframe = None;
+            self.load_compare_from_disk();
+            self.fetch_data();
         }
     }
 
     fn has_data(&self) -> bool {
         self.last_fetch_time.is_some()
     }
+
+    fn latest_quote(&self) -> Option<crate::http_api::Quote> {
+        // The most recently fetched timeframe has the freshest last price
+        let (timeframe, cached) = self.cache.iter().max_by_key(|(_, cached)| cached.fetched_at)?;
+        let last = cached.series.candles.last()?;
+        let first = cached.series.candles.first()?.open;
+        Some(crate::http_api::Quote {
+            chart_id: format!("{:?}", self.window.id()),
+            symbol: self.symbol.clone(),
+            currency: cached.series.currency.clone(),
+            price: last.close,
+            change_percent: if first != 0.0 { (last.close - first) / first * 100.0 } else { 0.0 },
+            timeframe: timeframe.clone(),
+            timestamp: last.timestamp,
+            fetched_at: cached.fetched_at.timestamp(),
+            stale: cached.from_disk,
+        })
+    }
     
-    fn get_config(&self) -> Option<ChartConfig> {
+    fn symbols(&self) -> Vec<String> {
+        std::iter::once(self.symbol.clone()).chain(self.compare_symbols.iter().cloned()).collect()
+    }
+
+    fn set_fx_rates(&mut self, rates: Option<&FxRates>, convert_prices: bool) {
+        self.fx = rates.filter(|_| convert_prices).cloned();
+        self.window.request_redraw();
+    }
+
+    fn set_compare_symbols(&mut self, symbols: Vec<String>) {
+        self.compare_symbols = symbols.into_iter().filter(|s| *s != self.symbol).collect();
+        self.compare_cache.retain(|(symbol, _), _| self.compare_symbols.contains(symbol));
+        self.load_compare_from_disk();
+        self.fetch_compare();
+        self.window.request_redraw();
+    }
+
+    fn get_config(&self) -> Option<WidgetConfig> {
         let size = self.window.inner_size();
         let pos = self.window.outer_position().unwrap_or(winit::dpi::PhysicalPosition::new(0, 0));
-        Some(ChartConfig {
+        Some(WidgetConfig::Chart(ChartConfig {
             symbol: self.symbol.clone(),
             x: pos.x,
             y: pos.y,
             width: size.width,
             height: size.height,
             timeframe: self.pending_timeframe.clone().or_else(|| Some(self.timeframe.clone())),
-        })
+            provider: self.provider.clone(),
+            style: self.style,
+            volume_pane_height: self.volume_pane_height,
+            indicators: self.indicators.clone(),
+            compare_symbols: self.compare_symbols.clone(),
+        }))
     }
 
     fn handle_event(&mut self, event: WindowEvent, _event_loop: &ActiveEventLoop) {
@@ -527,10 +435,12 @@ impl WindowHandler for ChartWindow {
             WindowEvent::Resized(size) => {
                 self.resize(size);
             },
-            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
-                if !self.locked {
-                    let _ = self.window.drag_window();
-                }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = position;
+                platform::update_cursor(&self.window, position, self.locked);
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if !self.locked => {
+                platform::drag(&self.window, self.cursor_position);
             },
             WindowEvent::RedrawRequested => {
                 self.redraw();
@@ -546,18 +456,28 @@ impl WindowHandler for ChartWindow {
         }
     }
 
-    fn update_data(&mut self, quotes: Vec<yahoo::Quote>, currency: String) {
-        self.quotes = Some(quotes.clone());
-        self.currency = currency.clone();
+    fn update_data(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries) {
         let now = Local::now();
-        self.last_fetch_time = Some(now);
-        
+        if symbol != self.symbol {
+            if self.compare_symbols.iter().any(|s| s == symbol) {
+                self.compare_cache.insert((symbol.to_string(), timeframe.to_string()), CachedSeries { series, fetched_at: now, from_disk: false });
+                if timeframe == self.timeframe {
+                    self.window.request_redraw();
+                }
+            }
+            return;
+        }
+
         // Update Cache
-        self.cache.insert(self.timeframe.clone(), (quotes, currency, now));
+        self.cache.insert(timeframe.to_string(), CachedSeries { series, fetched_at: now, from_disk: false });
 
-        self.window.set_visible(true);
-        self.force_to_bottom();
-        self.window.request_redraw();
+        // Data for another timeframe of the same symbol (fetched for a different window) is only cached
+        if timeframe != self.timeframe {
+            return;
+        }
+        self.load_from_cache();
+
+        show_widget(&self.window);
     }
 
     fn redraw(&mut self) {
@@ -570,210 +490,42 @@ impl WindowHandler for ChartWindow {
             // (Border drawing moved to end)
 
             if let Some(quotes) = &self.quotes {
-                // let width = buffer.width().get(); // Already defined
-                // let height = buffer.height().get();
-                
-                let first_quote = quotes.first().unwrap();
-                let last_quote = quotes.last().unwrap();
-                let first_price = first_quote.close;
-                let last_price = last_quote.close;
-                let diff = last_price - first_price;
-                let percent_change = (diff / first_price) * 100.0;
-                
-                let color = if diff >= 0.0 { &GREEN } else { &RED };
-                let sign = if diff >= 0.0 { "+" } else { "" };
-
-                let symbol_txt = match self.currency.as_str() {
-                    "USD" => "$",
-                    "EUR" => "€",
-                    "GBP" => "£",
-                    "JPY" => "¥",
-                    _ => &self.currency,
+                // Compared symbols without data yet show up in the legend only
+                let compare: Vec<(&str, &[Candle])> = self.compare_symbols.iter()
+                    .map(|symbol| {
+                        let quotes = self.compare_cache.get(&(symbol.clone(), self.timeframe.clone())).map(|cached| cached.series.candles.as_slice()).unwrap_or(&[]);
+                        (symbol.as_str(), quotes)
+                    })
+                    .collect();
+                let (quotes, currency) = display_prices(self.fx.as_ref(), quotes, &self.currency);
+                let view = ChartView {
+                    symbol: &self.symbol,
+                    currency,
+                    quotes: &quotes,
+                    timeframe: &self.timeframe,
+                    style: self.style,
+                    indicators: &self.indicators,
+                    volume_pane_height: self.volume_pane_height,
+                    language: self.language,
+                    fetched_at: self.last_fetch_time.map(|t| (t, self.stale)),
+                    compare: &compare,
+                    session: self.session,
+                    session_range: quotes.last().and_then(|q| market_hours::session_range(&self.symbol, q.timestamp)),
                 };
+                let rendered = render_chart(&view, width, height);
 
-                let mut pixel_buffer = vec![0u8; (width * height * 3) as usize];
-                {
-                    let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
-                    root.fill(&TRANSPARENT).unwrap(); 
-                    
-                    let font = ("sans-serif", 30).into_font();
-                    let padding = 20;
-                    let mut current_x = 20;
-
-                    // Symbol
-                    root.draw_text(&self.symbol, &font.clone().color(&WHITE), (current_x, 20)).unwrap();
-                    let (w, _) = font.box_size(&self.symbol).unwrap();
-                    current_x += w as i32 + padding;
-                    
-                    // Price
-                    let price_text = format!("{}{:.2}", symbol_txt, last_price);
-                    root.draw_text(&price_text, &font.clone().color(&WHITE), (current_x, 20)).unwrap();
-                    let (w, _) = font.box_size(&price_text).unwrap();
-                    current_x += w as i32 + padding;
-
-                    // Change
-                    let change_text = format!("{}{:.2} ({}{:.2}%)", sign, diff, sign, percent_change);
-                    root.draw_text(&change_text, &font.clone().color(color), (current_x, 20)).unwrap();
-                    let (w, _) = font.box_size(&change_text).unwrap();
-                    current_x += w as i32 + padding;
-
-                    // Update Window Min Size
-                    let min_width = current_x as u32;
-                    let min_height = 300; 
-                    self.window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_width as f64, min_height as f64)));
-                    
-                    // Chart
-                    let start_date = DateTime::from_timestamp(quotes.first().unwrap().timestamp as i64, 0).unwrap();
-                    let end_date = DateTime::from_timestamp(quotes.last().unwrap().timestamp as i64, 0).unwrap();
-                    
-                    let min_price = quotes.iter().map(|q| q.low).fold(f64::INFINITY, f64::min);
-                    let max_price = quotes.iter().map(|q| q.high).fold(f64::NEG_INFINITY, f64::max);
-                    
-                    let range = max_price - min_price;
-                    let use_decimals = range < 1.0 || max_price < 2.0;
-                    
-                    let x_labels = (width / 120).max(2) as usize;
-                    let y_labels = (height / 60).max(2) as usize;
-
-                    let mut chart = ChartBuilder::on(&root)
-                        .margin(10)
-                        .margin_top(60) 
-                        .set_label_area_size(LabelAreaPosition::Left, 40)
-                        .set_label_area_size(LabelAreaPosition::Bottom, 40)
-                        .build_cartesian_2d(start_date..end_date, min_price..max_price)
-                        .unwrap();
-
-                    chart.configure_mesh()
-                        .axis_style(WHITE)
-                        .bold_line_style(WHITE.mix(0.3))
-                        .light_line_style(TRANSPARENT)
-                        .label_style(("sans-serif", 15).into_font().color(&WHITE))
-                        .x_labels(x_labels)
-                        .y_labels(y_labels)
-                        .x_label_formatter(&|d| {
-                            let date = DateTime::from_timestamp(d.timestamp(), 0).unwrap().with_timezone(&Local);
-                            let lang = self.language;
-
-                            if self.timeframe == "1D" {
-                                crate::language::format_time(date)
-                            } else if self.timeframe == "1W" {
-                                let duration = end_date.signed_duration_since(start_date);
-                                if duration.num_days() <= 2 {
-                                    crate::language::format_weekday_time(lang, date)
-                                } else {
-                                    crate::language::format_month_day(lang, date)
-                                }
-                            } else {
-                                crate::language::format_month_day(lang, date)
-                            }
-                        })
-                        .y_label_formatter(&|y| {
-                            if use_decimals {
-                                format!("{:.2}", y)
-                            } else {
-                                format!("{:.0}", y)
-                            }
-                        })
-                        .draw().unwrap();
-
-                    chart.draw_series(
-                        AreaSeries::new(
-                            quotes.iter().map(|q| (
-                                DateTime::from_timestamp(q.timestamp as i64, 0).unwrap(),
-                                q.close
-                            )),
-                            min_price,
-                            color.mix(0.15).filled(),
-                        )
-                    ).unwrap();
-
-                    chart.draw_series(
-                        LineSeries::new(
-                            quotes.iter().map(|q| (
-                                DateTime::from_timestamp(q.timestamp as i64, 0).unwrap(),
-                                q.close
-                            )),
-                            color,
-                        )
-                    ).unwrap();
-                    
-                    // Draw Timestamp
-                    if let Some(ts) = self.last_fetch_time {
-                        let time_str = format!("{}", ts.format("%Y-%m-%d %H:%M:%S"));
-                        let ts_font = ("sans-serif", 14).into_font();
-                        let (tw, th) = ts_font.box_size(&time_str).unwrap();
-                        // Bottom Right
-                        let tx = (width as i32) - (tw as i32) - 10;
-                        let ty = (height as i32) - (th as i32) - 5;
-                        root.draw_text(&time_str, &ts_font.color(&WHITE.mix(0.5)), (tx, ty)).unwrap();
-                    }
-                }
-                
-                for (i, chunk) in pixel_buffer.chunks(3).enumerate() {
-                    if i < buffer.len() {
-                        let r = chunk[0] as u32;
-                        let g = chunk[1] as u32;
-                        let b = chunk[2] as u32;
-                        if r != 0 || g != 0 || b != 0 {
-                            buffer[i] = (r << 16) | (g << 8) | b;
-                        }
-                    }
-                }
+                // Update Window Min Size
+                let (min_width, min_height) = rendered.min_size;
+                self.window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_width as f64, min_height as f64)));
+
+                blit_rgba(&mut buffer, &rendered.rgba);
             }
             
             // Draw yellow frame if unlocked
             if !self.locked {
                 let width = buffer.width().get() as i32;
                 let height = buffer.height().get() as i32;
-                let frame_color = 0xFFFF00; // Yellow
-                let thickness = 3; 
-                let radius = 12; // Radius for rounded corners
-
-                for y in 0..height {
-                    for x in 0..width {
-                        let mut in_border = false;
-
-                        // Check corners
-                        if x < radius && y < radius { // Top-Left
-                            let d = ((x - radius).pow(2) + (y - radius).pow(2)) as f64;
-                            let r_out = (radius as f64).powi(2);
-                            let r_in = ((radius - thickness) as f64).powi(2);
-                            if d <= r_out && d >= r_in { in_border = true; }
-                        } else if x >= width - radius && y < radius { // Top-Right
-                            let d = ((x - (width - radius)).pow(2) + (y - radius).pow(2)) as f64;
-                            let r_out = (radius as f64).powi(2);
-                            let r_in = ((radius - thickness) as f64).powi(2);
-                            if d <= r_out && d >= r_in { in_border = true; }
-                        } else if x < radius && y >= height - radius { // Bottom-Left
-                             let d = ((x - radius).pow(2) + (y - (height - radius)).pow(2)) as f64;
-                             let r_out = (radius as f64).powi(2);
-                             let r_in = ((radius - thickness) as f64).powi(2);
-                             if d <= r_out && d >= r_in { in_border = true; }
-                        } else if x >= width - radius && y >= height - radius { // Bottom-Right
-                             let d = ((x - (width - radius)).pow(2) + (y - (height - radius)).pow(2)) as f64;
-                             let r_out = (radius as f64).powi(2);
-                             let r_in = ((radius - thickness) as f64).powi(2);
-                             if d <= r_out && d >= r_in { in_border = true; }
-                        } else {
-                            // Straight Edges
-                            // Top Edge (between rounded corners)
-                            if y < thickness && x >= radius && x < width - radius { in_border = true; }
-                            // Bottom Edge
-                            if y >= height - thickness && x >= radius && x < width - radius { in_border = true; }
-                            // Left Edge
-                            if x < thickness && y >= radius && y < height - radius { in_border = true; }
-                            // Right Edge
-                            if x >= width - thickness && y >= radius && y < height - radius { in_border = true; }
-                        }
-
-                        if in_border {
-                             let idx = (y * width + x) as usize;
-                             if idx < buffer.len() {
-                                 buffer[idx] = frame_color;
-                             }
-                        }
-                    }
-                }
+                draw_unlocked_frame(&mut buffer, width, height);
             }
 
             buffer.present().ok();
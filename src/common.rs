@@ -1,10 +1,9 @@
-use crate::config::ChartConfig;
+use crate::config::{ChartStyle, WidgetConfig};
 use winit::event::WindowEvent;
 use winit::window::WindowId;
 use winit::event_loop::ActiveEventLoop;
-use yahoo_finance_api as yahoo;
-use crate::ipc::IpcMessage;
-use tokio::sync::mpsc::Sender;
+use crate::provider::QuoteSeries;
+use crate::ipc::{FrameSender, IpcMessage};
 
 use serde::{Serialize, Deserialize};
 
@@ -20,23 +19,34 @@ pub enum UpdateStatus {
 
 #[derive(Debug)]
 pub enum UserEvent {
-    DataLoaded(String, Vec<yahoo::Quote>, String), // Symbol, Quotes, Currency
+    DataLoaded(String, String, QuoteSeries), // Symbol, Timeframe, Quotes + Currency
     Error(String, crate::language::AppError), // Symbol, AppError
-    AddChart(String),
+    AddChart(String, Option<(u64, u64)>), // Symbol, IPC (connection, request) to answer once the chart loaded or failed
     DeleteChart(WindowId),
     OpenSettings,
     ToggleLock(WindowId, bool),
     UpdateInterval(u64),
     UsePrereleases(bool),
     ChartTimeframe(WindowId, String),
+    ChartStyle(WindowId, ChartStyle),
+    ChartIndicators(WindowId, Vec<crate::indicators::Indicator>),
+    ChartCompare(WindowId, Vec<String>),
+    AddWatchlist(Vec<String>),
+    WidgetSymbols(WindowId, Vec<String>),
+    AddTicker(Vec<String>),
+    TickerSpeed(WindowId, u32),
+    Positions(Vec<crate::portfolio::Position>),
+    ShowPortfolio(bool),
+    BaseCurrency(Option<String>),
+    ConvertPrices(bool),
     LanguageChanged(crate::language::Language),
     CheckForUpdates,
     UpdateStatus(UpdateStatus),
     PerformUpdate,
     RestartApp,
-    IpcConnected(Sender<IpcMessage>),
-    IpcMessageReceived(IpcMessage),
-    IpcDisconnected,
+    IpcConnected(u64, String, FrameSender), // Connection id, client name, outgoing frames
+    IpcMessageReceived(u64, u64, IpcMessage), // Connection id, request id, message
+    IpcDisconnected(u64), // Connection id
 }
 
 pub trait WindowHandler {
@@ -44,14 +54,29 @@ pub trait WindowHandler {
     fn handle_event(&mut self, event: WindowEvent, event_loop: &ActiveEventLoop);
     fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>);
     fn redraw(&mut self);
-    fn update_data(&mut self, quotes: Vec<yahoo::Quote>, currency: String);
-    fn get_config(&self) -> Option<ChartConfig> { None }
+    /// Symbols the window shows, `DataLoaded` events for them are routed to it.
+    fn symbols(&self) -> Vec<String> { Vec::new() }
+    fn update_data(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries);
+    fn get_config(&self) -> Option<WidgetConfig> { None }
     fn set_locked(&mut self, _locked: bool) {}
     fn set_timeframe(&mut self, _timeframe: String) {}
+    fn set_style(&mut self, _style: ChartStyle) {}
+    fn set_indicators(&mut self, _indicators: Vec<crate::indicators::Indicator>) {}
+    fn set_symbols(&mut self, _symbols: Vec<String>) {}
+    fn set_compare_symbols(&mut self, _symbols: Vec<String>) {}
+    fn set_positions(&mut self, _positions: Vec<crate::portfolio::Position>) {}
+    fn set_speed(&mut self, _speed: u32) {}
+    /// Current exchange rates, None without a base currency. Prices are shown converted if `convert_prices`.
+    fn set_fx_rates(&mut self, _rates: Option<&crate::currency::FxRates>, _convert_prices: bool) {}
     fn refresh(&mut self) {}
     fn tick(&mut self) {}
+    /// Earliest time the handler wants `tick` to be called again, for animations.
+    fn next_wake(&self) -> Option<std::time::Instant> { None }
     fn show_error(&mut self, _message: String) {}
     fn set_language(&mut self, _language: crate::language::Language) {}
     fn has_data(&self) -> bool { true }
+    /// Notes that `symbol` could not be fetched. True once none of the window's symbols could be loaded.
+    fn fetch_failed(&mut self, _symbol: &str) -> bool { false }
+    fn latest_quote(&self) -> Option<crate::http_api::Quote> { None }
     fn update_status(&mut self, _status: UpdateStatus) {}
 }
@@ -1,7 +1,57 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{Local, NaiveDateTime, TimeDelta};
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::env;
+use std::sync::OnceLock;
+
+/// Environment variable overriding where config.json is kept, `--config` takes precedence over it.
+pub const CONFIG_ENV: &str = "DESKTOP_WIDGET_CONFIG";
+// Directory in the per-user config dir
+const APP_DIR: &str = "desktop-widget-rs";
+static PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Layout version of config.json written by this build, bumped with every step added to `MIGRATIONS`.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` turns a version n config into version n + 1. Files from before versioning are version 0.
+const MIGRATIONS: [fn(&mut Value); CONFIG_VERSION as usize] = [
+    migrate_v0_to_v1,
+];
+
+// Ring of timestamped copies of config.json in `backups/` next to it
+const MAX_BACKUPS: usize = 10;
+const BACKUP_NAME_FORMAT: &str = "config-%Y%m%d-%H%M%S%3f.json";
+// Saves run every 500 ms while a widget is dragged, only some of them keep the file they replace
+const BACKUP_INTERVAL: TimeDelta = TimeDelta::hours(1);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChartStyle {
+    Line,
+    #[default]
+    Area,
+    Candlestick,
+    Ohlc,
+    HeikinAshi,
+}
+
+impl ChartStyle {
+    pub const ALL: [ChartStyle; 5] = [ChartStyle::Line, ChartStyle::Area, ChartStyle::Candlestick, ChartStyle::Ohlc, ChartStyle::HeikinAshi];
+}
+
+impl std::fmt::Display for ChartStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartStyle::Line => write!(f, "Line"),
+            ChartStyle::Area => write!(f, "Area"),
+            ChartStyle::Candlestick => write!(f, "Candles"),
+            ChartStyle::Ohlc => write!(f, "OHLC"),
+            ChartStyle::HeikinAshi => write!(f, "Heikin-Ashi"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChartConfig {
@@ -12,17 +62,114 @@ pub struct ChartConfig {
     pub height: u32,
     #[serde(default)]
     pub timeframe: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>, // Data source name, None = crate::provider::DEFAULT_PROVIDER
+    #[serde(default)]
+    pub style: ChartStyle,
+    #[serde(default)]
+    pub volume_pane_height: Option<u32>, // Height of the volume pane in pixels, None = hidden
+    #[serde(default)]
+    pub indicators: Vec<crate::indicators::Indicator>,
+    #[serde(default)]
+    pub compare_symbols: Vec<String>, // Plotted with `symbol` as percent change, empty = regular chart
+}
+
+/// A watchlist widget: one compact row per symbol with last price, change and a sparkline.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchlistConfig {
+    pub symbols: Vec<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub timeframe: Option<String>, // Timeframe the change and sparkline cover, None = "1D"
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+/// A ticker tape: a thin window scrolling symbols with their price and change.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TickerConfig {
+    pub symbols: Vec<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_ticker_speed")]
+    pub speed: u32, // Pixels per second
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+pub fn default_ticker_speed() -> u32 {
+    60
+}
+
+/// Placement of the portfolio widget, the positions themselves are in `AppConfig::positions`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PortfolioConfig {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Persisted state of any widget window.
+#[derive(Debug, Clone)]
+pub enum WidgetConfig {
+    Chart(ChartConfig),
+    Watchlist(WatchlistConfig),
+    Ticker(TickerConfig),
+    Portfolio(PortfolioConfig),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AppConfig {
+    #[serde(default)]
+    pub version: u32, // Always CONFIG_VERSION once loaded, older files are migrated first
     pub charts: Vec<ChartConfig>,
+    #[serde(default)]
+    pub watchlists: Vec<WatchlistConfig>,
+    #[serde(default)]
+    pub tickers: Vec<TickerConfig>,
+    #[serde(default)]
+    pub positions: Vec<crate::portfolio::Position>,
+    #[serde(default)]
+    pub portfolio: Option<PortfolioConfig>, // None = portfolio widget closed
     #[serde(default = "default_interval")]
     pub update_interval_minutes: u64,
     #[serde(default)]
     pub language: crate::language::Language,
     #[serde(default)]
     pub use_prereleases: bool,
+    #[serde(default)]
+    pub base_currency: Option<String>, // ISO code portfolio totals (and optionally prices) are converted to
+    #[serde(default)]
+    pub convert_prices: bool, // Show chart, watchlist and ticker prices in `base_currency`
+    #[serde(default)]
+    pub alerts: Vec<crate::alerts::AlertRule>,
+    #[serde(default)]
+    pub http_api: HttpApiConfig,
+}
+
+/// Optional local HTTP/JSON API, see `crate::http_api`. Only read on startup.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HttpApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_http_port")]
+    pub port: u16, // Bound on 127.0.0.1 only
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        Self { enabled: false, port: default_http_port() }
+    }
+}
+
+fn default_http_port() -> u16 {
+    8917
 }
 
 fn default_interval() -> u64 {
@@ -32,73 +179,348 @@ fn default_interval() -> u64 {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             charts: Vec::new(),
+            watchlists: Vec::new(),
+            tickers: Vec::new(),
+            positions: Vec::new(),
+            portfolio: None,
             update_interval_minutes: default_interval(),
             language: crate::language::Language::default(),
             use_prereleases: false,
+            base_currency: None,
+            convert_prices: false,
+            alerts: Vec::new(),
+            http_api: HttpApiConfig::default(),
         }
     }
 }
 
 impl AppConfig {
     pub fn load() -> Self {
-        // 1. Try executable directory
-        if let Ok(mut path) = env::current_exe() {
-            path.pop();
-            path.push("config.json");
-            if path.exists() {
-                 return Self::load_from_path(&path);
+        let path = Self::path();
+
+        // Earlier versions kept config.json next to the executable (or in the working directory).
+        // It is copied over once, as long as the per-user location has no config of its own
+        if !path.exists() && Self::path_override().is_none() && env_path().is_none()
+            && let Some(legacy) = legacy_path().filter(|legacy| *legacy != path) {
+            match import_legacy(&legacy, &path) {
+                Ok(()) => log::info!("Imported config from {:?} to {:?}", legacy, path),
+                Err(e) => log::error!("Failed to import config from {:?}: {}", legacy, e),
             }
         }
-        
-        // 2. Try current working directory
-        let cwd_path = Path::new("config.json");
-        if cwd_path.exists() {
-            return Self::load_from_path(cwd_path);
-        }
 
-        AppConfig::default()
+        if path.exists() {
+            Self::load_from_path(&path)
+        } else {
+            AppConfig::default()
+        }
     }
 
+    // The file is backed up before anything that would lose its contents on the next save:
+    // a migration, a file from a newer version or one that can't be read or parsed (defaults are used then)
     fn load_from_path(path: &Path) -> Self {
-         if let Ok(content) = fs::read_to_string(path) {
-             if let Ok(config) = serde_json::from_str(&content) {
-                 log::info!("Loaded config from {:?}", path);
-                 return config;
-             }
-         }
-         log::warn!("Failed to load config from {:?}, using defaults", path);
-         AppConfig::default()
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Failed to read config from {:?}, using defaults: {}", path, e);
+                Self::backup(path, "unreadable");
+                return AppConfig::default();
+            }
+        };
+
+        match Self::parse(&content) {
+            Ok((config, version)) => {
+                match version.cmp(&CONFIG_VERSION) {
+                    std::cmp::Ordering::Less => log::info!("Migrated config {:?} from version {} to {}", path, version, CONFIG_VERSION),
+                    std::cmp::Ordering::Equal => log::info!("Loaded config from {:?}", path),
+                    // Read as far as this build understands it, the newer build's additions are lost on the next save
+                    std::cmp::Ordering::Greater => log::warn!("Config {:?} is from a newer version ({} > {}), downgrading it", path, version, CONFIG_VERSION),
+                }
+                if version != CONFIG_VERSION {
+                    Self::backup(path, &format!("v{}", version));
+                }
+                config
+            },
+            Err(e) => {
+                log::error!("Failed to load config from {:?}, using defaults: {}", path, e);
+                Self::backup(path, "invalid");
+                AppConfig::default()
+            }
+        }
+    }
+
+    /// Parses the contents of config.json, migrating older layouts to the current one.
+    /// Also returns the version the file had.
+    pub fn parse(content: &str) -> Result<(Self, u32), String> {
+        let mut value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let version = match value.get("version") {
+            None => 0,
+            Some(v) => v.as_u64().and_then(|v| u32::try_from(v).ok()).ok_or_else(|| format!("invalid version {}", v))?,
+        };
+
+        for migration in MIGRATIONS.iter().skip(version as usize) {
+            migration(&mut value);
+        }
+
+        let mut config: AppConfig = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        config.version = CONFIG_VERSION;
+        Ok((config, version))
+    }
+
+    // Copies config.json to e.g. config.json.v0.bak
+    fn backup(path: &Path, label: &str) {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".{}.bak", label));
+        match fs::copy(path, &backup) {
+            Ok(_) => log::info!("Backed up config to {:?}", backup),
+            Err(e) => log::error!("Failed to back up config to {:?}: {}", backup, e),
+        }
+    }
+
+    /// Uses `path` instead of the standard location for the rest of the process (`--config`).
+    pub fn set_path_override(path: PathBuf) {
+        let _ = PATH_OVERRIDE.set(path);
+    }
+
+    pub fn path_override() -> Option<&'static Path> {
+        PATH_OVERRIDE.get().map(PathBuf::as_path)
+    }
+
+    /// Path config.json is read from and written to: `--config`, `DESKTOP_WIDGET_CONFIG` or the per-user
+    /// config dir (`%APPDATA%\desktop-widget-rs`, `$XDG_CONFIG_HOME/desktop-widget-rs`).
+    /// Other persistent files (e.g. the quote cache and backups) live next to it.
+    pub fn path() -> PathBuf {
+        if let Some(path) = Self::path_override() {
+            return path.to_path_buf();
+        }
+        if let Some(path) = env_path() {
+            return path;
+        }
+        match dirs::config_dir() {
+            Some(dir) => dir.join(APP_DIR).join("config.json"),
+            None => PathBuf::from("config.json"),
+        }
     }
 
     pub fn save(&self) {
-        let mut path = Path::new("config.json").to_path_buf();
-        
-        if let Ok(mut exe_dist) = env::current_exe() {
-            exe_dist.pop();
-            exe_dist.push("config.json");
-            
-            // If it exists in exe dir, use it.
-            if exe_dist.exists() {
-                path = exe_dist;
-            } 
-            // Else if it exists in CWD, use it (path is already CWD).
-            // (Only if exe_dist doesn't exist)
-            else if Path::new("config.json").exists() {
-                // keep path as is (CWD)
-            }
-            // Else, default to creating in exe dir
-            else {
-                path = exe_dist;
-            }
-        } 
+        let path = Self::path();
+
+        // Whatever kept the file from being read (permissions, I/O errors) may also have kept it from being backed up
+        if path.exists() && let Err(e) = fs::read(&path) {
+            log::error!("Not saving config, {:?} can't be read: {}", path, e);
+            return;
+        }
         
         if let Ok(content) = serde_json::to_string_pretty(self) {
-            if let Err(e) = fs::write(&path, content) {
+            let now = Local::now().naive_local();
+            let backup_due = backups(&path).first().is_none_or(|(time, _)| now - *time >= BACKUP_INTERVAL);
+            if path.exists() && backup_due && let Err(e) = add_backup(&path, now) {
+                log::error!("Failed to back up config {:?}: {}", path, e);
+            }
+
+            if let Err(e) = write_atomic(&path, &content) {
                 log::error!("Failed to save config to {:?}: {}", path, e);
             } else {
                 log::info!("Saved config to {:?}", path);
             }
         }
     }
+
+    /// Reads the newest backup that differs from config.json. The current file is backed up
+    /// first, so restoring once more brings it back.
+    pub fn restore_previous() -> Result<Self, String> {
+        let path = Self::path();
+        let current = fs::read(&path).unwrap_or_default();
+        let (_, backup) = backups(&path)
+            .into_iter()
+            .find(|(_, backup)| fs::read(backup).is_ok_and(|content| content != current))
+            .ok_or("no earlier layout has been backed up")?;
+        let content = fs::read_to_string(&backup).map_err(|e| e.to_string())?;
+        let (config, _) = Self::parse(&content)?;
+
+        if !current.is_empty() {
+            add_backup(&path, Local::now().naive_local()).map_err(|e| e.to_string())?;
+        }
+        log::info!("Restoring config from {:?}", backup);
+        Ok(config)
+    }
+}
+
+/// Writes `content` to a temporary file that only replaces `path` once it's completely on disk,
+/// so a crash mid-write leaves either the old or the new file behind.
+pub(crate) fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
+
+    // The rename itself is only durable once the directory is synced (directories can't be opened on Windows)
+    #[cfg(unix)]
+    {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// An empty DESKTOP_WIDGET_CONFIG counts as unset
+fn env_path() -> Option<PathBuf> {
+    env::var_os(CONFIG_ENV).filter(|path| !path.is_empty()).map(PathBuf::from)
+}
+
+/// config.json of versions that kept it next to the executable or in the working directory.
+fn legacy_path() -> Option<PathBuf> {
+    let exe_dir = env::current_exe().ok().map(|exe| exe.with_file_name("config.json"));
+    exe_dir.into_iter()
+        .chain(std::iter::once(PathBuf::from("config.json")))
+        .find(|path| path.is_file())
+}
+
+// The legacy file stays where it is, older versions still find it there
+fn import_legacy(legacy: &Path, path: &Path) -> io::Result<()> {
+    let content = fs::read_to_string(legacy)?;
+    write_atomic(path, &content)
+}
+
+fn backup_dir(path: &Path) -> PathBuf {
+    path.with_file_name("backups")
+}
+
+/// Backups of the config at `path` with the time they were taken, newest first.
+fn backups(path: &Path) -> Vec<(NaiveDateTime, PathBuf)> {
+    let Ok(entries) = fs::read_dir(backup_dir(path)) else { return Vec::new() };
+    let mut backups: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let time = NaiveDateTime::parse_from_str(path.file_name()?.to_str()?, BACKUP_NAME_FORMAT).ok()?;
+            Some((time, path))
+        })
+        .collect();
+    backups.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    backups
+}
+
+/// Copies the config at `path` into the backup ring, dropping the oldest backups beyond `MAX_BACKUPS`.
+fn add_backup(path: &Path, now: NaiveDateTime) -> io::Result<()> {
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    fs::copy(path, dir.join(now.format(BACKUP_NAME_FORMAT).to_string()))?;
+
+    for (_, old) in backups(path).into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Files from before versioning only lack the version: everything added since (watchlists, alerts,
+/// positions, ...) has serde defaults and the fields they had are unchanged.
+fn migrate_v0_to_v1(config: &mut Value) {
+    config["version"] = 1.into();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(version: u32) -> Value {
+        let content = match version {
+            0 => include_str!("../tests/fixtures/config_v0.json"),
+            1 => include_str!("../tests/fixtures/config_v1.json"),
+            _ => unreachable!(),
+        };
+        serde_json::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn migrates_v0_to_v1() {
+        let mut config = fixture(0);
+        migrate_v0_to_v1(&mut config);
+        assert_eq!(config, fixture(1));
+    }
+
+    #[test]
+    fn parses_old_files_into_the_current_layout() {
+        let (config, version) = AppConfig::parse(&fixture(0).to_string()).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.language, crate::language::Language::De);
+        assert_eq!(config.update_interval_minutes, 30);
+        assert_eq!(config.charts.len(), 3);
+        assert_eq!(config.charts[0].symbol, "VVSM.DE");
+        assert_eq!((config.charts[0].x, config.charts[0].y), (-831, 384));
+        assert_eq!(config.charts[0].timeframe.as_deref(), Some("1D"));
+        assert!(config.watchlists.is_empty() && config.alerts.is_empty());
+
+        let (current, version) = AppConfig::parse(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(version, CONFIG_VERSION);
+        assert_eq!(current.charts.len(), 3);
+    }
+
+    #[test]
+    fn keeps_a_ring_of_backups() {
+        let dir = env::temp_dir().join(format!("desktop-widget-rs-backups-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+
+        let start = NaiveDateTime::parse_from_str("2024-03-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        for i in 0..MAX_BACKUPS + 2 {
+            write_atomic(&path, &format!("{{ \"charts\": [], \"update_interval_minutes\": {} }}", i)).unwrap();
+            add_backup(&path, start + TimeDelta::minutes(i as i64)).unwrap();
+        }
+
+        let backups = backups(&path);
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(backups[0].0, start + TimeDelta::minutes(MAX_BACKUPS as i64 + 1));
+        assert_eq!(fs::read_to_string(&backups[0].1).unwrap(), fs::read_to_string(&path).unwrap());
+        assert!(!dir.join("config.json.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imports_legacy_config_into_a_new_directory() {
+        let dir = env::temp_dir().join(format!("desktop-widget-rs-import-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("config.json");
+        fs::write(&legacy, fixture(0).to_string()).unwrap();
+
+        let path = dir.join("user").join(APP_DIR).join("config.json");
+        import_legacy(&legacy, &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), fs::read_to_string(&legacy).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backs_up_files_that_cannot_be_read() {
+        let dir = env::temp_dir().join(format!("desktop-widget-rs-unreadable-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, b"{ \"charts\": [\xff] }").unwrap();
+
+        let config = AppConfig::load_from_path(&path);
+        assert!(config.charts.is_empty());
+        assert_eq!(fs::read(dir.join("config.json.unreadable.bak")).unwrap(), fs::read(&path).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unreadable_files() {
+        assert!(AppConfig::parse("{ \"charts\": [").is_err());
+        assert!(AppConfig::parse("{ \"version\": \"two\", \"charts\": [] }").is_err());
+        assert!(AppConfig::parse("{ \"language\": \"De\" }").is_err());
+    }
 }
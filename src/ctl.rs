@@ -0,0 +1,253 @@
+use std::io;
+use std::time::Duration;
+use crate::ipc::{self, ChartData, Frame, IpcMessage, IpcStream};
+use crate::portfolio::{self, Position};
+
+const CLIENT_NAME: &str = "ctl";
+
+const USAGE: &str = "\
+Usage: desktop-widget-rs ctl <command>
+
+Commands:
+  add <SYMBOL>                  Open a chart for SYMBOL, prints its id once loaded
+  list [--json]                 List the open charts
+  set-timeframe <ID|SYMBOL> <TF>
+                                Switch a chart to 1D, 1W, 1M, 3M, 6M, YTD or 1Y
+  refresh                       Reload the data of all charts
+  restore-layout                Reopen the widgets of the previous layout backup
+  portfolio export [FILE]       Write the positions as CSV to FILE or stdout
+  portfolio import <FILE>       Replace the positions with those in a CSV file
+  quit                          Exit the running widget host";
+
+/// Entry point of `desktop-widget-rs ctl ...`: sends one command to the running host and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    crate::platform::attach_console();
+
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+    let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    match rt.block_on(execute(command, &args[1..])) {
+        Ok(()) => 0,
+        Err(CtlError::Usage) => {
+            eprintln!("{}", USAGE);
+            2
+        },
+        Err(CtlError::Failed(msg)) => {
+            eprintln!("Error: {}", msg);
+            1
+        },
+    }
+}
+
+enum CtlError {
+    Usage,
+    Failed(String),
+}
+
+impl From<io::Error> for CtlError {
+    fn from(e: io::Error) -> Self {
+        CtlError::Failed(e.to_string())
+    }
+}
+
+async fn execute(command: &str, args: &[String]) -> Result<(), CtlError> {
+    match (command, args) {
+        ("add", [symbol]) => {
+            let symbol = symbol.to_uppercase();
+            let mut conn = Connection::open().await?;
+            let before = conn.charts().await?;
+            conn.request(IpcMessage::AddChart(symbol.clone())).await?;
+            // The host doesn't name the new chart in its answer, find it by diffing the chart list
+            let after = conn.charts().await?;
+            for chart in after.iter().filter(|c| c.symbol == symbol && !before.iter().any(|b| b.id == c.id)) {
+                println!("{}", chart.id);
+            }
+            Ok(())
+        },
+        ("list", []) => print_charts(&Connection::open().await?.charts().await?, false),
+        ("list", [flag]) if flag == "--json" => print_charts(&Connection::open().await?.charts().await?, true),
+        ("set-timeframe", [target, timeframe]) => {
+            let timeframe = timeframe.to_uppercase();
+            let mut conn = Connection::open().await?;
+            let id = resolve_chart(&conn.charts().await?, target)?;
+            conn.request(IpcMessage::SetChartTimeframe(id, timeframe)).await?;
+            Ok(())
+        },
+        ("portfolio", [action, rest @ ..]) => match (action.as_str(), rest) {
+            ("export", [] | [_]) => {
+                let csv = portfolio::to_csv(&Connection::open().await?.positions().await?).map_err(CtlError::Failed)?;
+                match rest.first() {
+                    Some(file) => std::fs::write(file, csv)?,
+                    None => print!("{}", csv),
+                }
+                Ok(())
+            },
+            ("import", [file]) => {
+                let positions = portfolio::from_csv(&std::fs::read_to_string(file)?).map_err(|e| CtlError::Failed(format!("{}: {}", file, e)))?;
+                let count = positions.len();
+                Connection::open().await?.request(IpcMessage::SetPositions(positions)).await?;
+                println!("Imported {} positions", count);
+                Ok(())
+            },
+            _ => Err(CtlError::Usage),
+        },
+        ("refresh", []) => {
+            Connection::open().await?.request(IpcMessage::Refresh).await?;
+            Ok(())
+        },
+        ("restore-layout", []) => {
+            Connection::open().await?.request(IpcMessage::RestorePreviousLayout).await?;
+            Ok(())
+        },
+        ("quit", []) => {
+            match Connection::open().await?.request(IpcMessage::Quit).await {
+                // The host may exit before its answer made it through the pipe
+                Err(CtlError::Failed(e)) if e == CONNECTION_CLOSED => Ok(()),
+                other => other,
+            }
+        },
+        _ => Err(CtlError::Usage),
+    }
+}
+
+const CONNECTION_CLOSED: &str = "the widget host closed the connection";
+/// How long a request may wait for its answer. `add` is only answered once the chart has its data.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Accepts a chart id as shown by `list` or a symbol, as long as only one chart shows it.
+fn resolve_chart(charts: &[ChartData], target: &str) -> Result<String, CtlError> {
+    if let Some(chart) = charts.iter().find(|c| c.id == target) {
+        return Ok(chart.id.clone());
+    }
+    let matches: Vec<&ChartData> = charts.iter().filter(|c| c.symbol.eq_ignore_ascii_case(target)).collect();
+    match matches.as_slice() {
+        [chart] => Ok(chart.id.clone()),
+        [] => Err(CtlError::Failed(format!("No chart with id or symbol {}", target))),
+        _ => Err(CtlError::Failed(format!("{} charts show {}, pass an id instead", matches.len(), target))),
+    }
+}
+
+fn print_charts(charts: &[ChartData], json: bool) -> Result<(), CtlError> {
+    if json {
+        let out = serde_json::to_string_pretty(charts).map_err(|e| CtlError::Failed(e.to_string()))?;
+        println!("{}", out);
+        return Ok(());
+    }
+    println!("{:<24} {:<10} {:<4} {:<8} LOCKED", "ID", "SYMBOL", "TF", "STYLE");
+    for chart in charts {
+        println!("{:<24} {:<10} {:<4} {:<8} {}", chart.id, chart.symbol, chart.timeframe, format!("{:?}", chart.style), chart.locked);
+    }
+    Ok(())
+}
+
+/// A handshaken connection to the host. Requests are sent one at a time and waited for.
+struct Connection {
+    stream: Box<dyn IpcStream>,
+    next_id: u64,
+    charts: Option<Vec<ChartData>>, // Latest chart list the host pushed
+    positions: Option<Vec<Position>>, // Latest positions the host pushed
+}
+
+impl Connection {
+    async fn open() -> Result<Self, CtlError> {
+        let mut stream = ipc::connect()
+            .await
+            .map_err(|e| CtlError::Failed(format!("Widget host is not running ({})", e)))?;
+        ipc::client_handshake(&mut stream, CLIENT_NAME).await?;
+        Ok(Self { stream, next_id: 1, charts: None, positions: None })
+    }
+
+    /// Sends `message` and waits for its answer, keeping track of the state events pushed meanwhile.
+    async fn request(&mut self, message: IpcMessage) -> Result<(), CtlError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        ipc::write_frame(&mut self.stream, &Frame::Request { id, message }).await?;
+
+        tokio::time::timeout(REQUEST_TIMEOUT, self.wait_for(id))
+            .await
+            .map_err(|_| CtlError::Failed(format!("the widget host did not answer within {} seconds", REQUEST_TIMEOUT.as_secs())))?
+    }
+
+    async fn wait_for(&mut self, id: u64) -> Result<(), CtlError> {
+        loop {
+            match ipc::read_frame(&mut self.stream).await {
+                Ok(Some(Frame::Ack { id: ack })) if ack == id => return Ok(()),
+                Ok(Some(Frame::Failed { id: failed, error })) if failed == id => return Err(CtlError::Failed(error)),
+                Ok(Some(Frame::Event(IpcMessage::Charts(charts)))) => self.charts = Some(charts),
+                Ok(Some(Frame::Event(IpcMessage::Positions(positions)))) => self.positions = Some(positions),
+                Ok(Some(_)) => {},
+                Ok(None) => return Err(CtlError::Failed(CONNECTION_CLOSED.to_string())),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Current chart list. The host pushes it before acknowledging `GetCharts`.
+    async fn charts(&mut self) -> Result<Vec<ChartData>, CtlError> {
+        self.charts = None;
+        self.request(IpcMessage::GetCharts).await?;
+        self.charts.take().ok_or_else(|| CtlError::Failed("the widget host sent no chart list".to_string()))
+    }
+
+    /// Current positions, pushed before `GetPositions` is acknowledged.
+    async fn positions(&mut self) -> Result<Vec<Position>, CtlError> {
+        self.positions = None;
+        self.request(IpcMessage::GetPositions).await?;
+        self.positions.take().ok_or_else(|| CtlError::Failed("the widget host sent no positions".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(id: &str, symbol: &str) -> ChartData {
+        ChartData {
+            id: id.to_string(),
+            symbol: symbol.to_string(),
+            timeframe: "1M".to_string(),
+            locked: true,
+            style: Default::default(),
+            indicators: Vec::new(),
+            compare_symbols: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn resolves_charts_by_id_or_unique_symbol() {
+        let charts = [chart("WindowId(1)", "MSFT"), chart("WindowId(2)", "SAP.DE"), chart("WindowId(3)", "SAP.DE")];
+
+        assert!(matches!(resolve_chart(&charts, "WindowId(2)"), Ok(id) if id == "WindowId(2)"));
+        assert!(matches!(resolve_chart(&charts, "msft"), Ok(id) if id == "WindowId(1)"));
+        assert!(matches!(resolve_chart(&charts, "SAP.DE"), Err(CtlError::Failed(e)) if e.starts_with("2 charts show")));
+        assert!(matches!(resolve_chart(&charts, "AAPL"), Err(CtlError::Failed(e)) if e.contains("No chart")));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_commands_before_connecting() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        for (command, rest) in [
+            ("bogus", args(&[])),
+            ("add", args(&[])),
+            ("add", args(&["MSFT", "AAPL"])),
+            ("list", args(&["--yaml"])),
+            ("set-timeframe", args(&["MSFT"])),
+            ("portfolio", args(&[])),
+            ("portfolio", args(&["export", "a.csv", "b.csv"])),
+            ("portfolio", args(&["import"])),
+            ("refresh", args(&["now"])),
+        ] {
+            assert!(matches!(execute(command, &rest).await, Err(CtlError::Usage)), "{} {:?}", command, rest);
+        }
+    }
+}
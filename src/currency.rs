@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use crate::provider::Candle;
+
+// Currencies whose ISO 4217 minor unit isn't the usual 2 digits
+const ZERO_DECIMALS: [&str; 16] = ["BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND", "VUV", "XAF", "XOF"];
+const THREE_DECIMALS: [&str; 7] = ["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+/// Some exchanges quote in a subunit, e.g. London in pence ("GBp"). Returns the ISO currency
+/// and how many subunits make one unit of it.
+fn subunit(currency: &str) -> Option<(&'static str, f64)> {
+    match currency {
+        "GBp" | "GBX" => Some(("GBP", 100.0)),
+        "ZAc" | "ZAC" => Some(("ZAR", 100.0)),
+        "ILA" => Some(("ILS", 100.0)),
+        _ => None,
+    }
+}
+
+/// Whether `currency` is an exchange's subunit code. Their case matters, "GBp" is pence and "GBP" pounds.
+pub fn is_subunit(currency: &str) -> bool {
+    subunit(currency).is_some()
+}
+
+/// Converts between denominations of the same currency, e.g. pence into pounds, which needs no
+/// exchange rate. None for different currencies.
+pub fn convert_units(amount: f64, from: &str, to: &str) -> Option<f64> {
+    let (from_iso, from_units) = subunit(from).unwrap_or((from, 1.0));
+    let (to_iso, to_units) = subunit(to).unwrap_or((to, 1.0));
+    (from_iso == to_iso).then(|| amount / from_units * to_units)
+}
+
+/// Symbol shown in front of amounts. Dollars other than USD keep a prefix so they stay
+/// distinguishable, currencies without a well known sign are written as their code.
+fn symbol(currency: &str) -> Option<&'static str> {
+    Some(match currency {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" => "¥",
+        "CNY" => "CN¥",
+        "INR" => "₹",
+        "KRW" => "₩",
+        "ILS" => "₪",
+        "TRY" => "₺",
+        "UAH" => "₴",
+        "NGN" => "₦",
+        "PHP" => "₱",
+        "VND" => "₫",
+        "THB" => "฿",
+        "BRL" => "R$",
+        "CAD" => "CA$",
+        "AUD" => "A$",
+        "NZD" => "NZ$",
+        "HKD" => "HK$",
+        "SGD" => "S$",
+        "MXN" => "MX$",
+        "TWD" => "NT$",
+        _ => return None,
+    })
+}
+
+/// Number of decimals ISO 4217 defines for the currency.
+pub fn decimals(currency: &str) -> usize {
+    if ZERO_DECIMALS.contains(&currency) {
+        0
+    } else if THREE_DECIMALS.contains(&currency) {
+        3
+    } else {
+        2
+    }
+}
+
+/// Whether `code` looks like an ISO 4217 code, e.g. "EUR".
+pub fn is_iso_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Formats an amount with the decimals of its currency, e.g. "$12.30", "¥1520", "CHF 12.30" or "123.40p".
+pub fn format(amount: f64, currency: &str) -> String {
+    let digits = match subunit(currency) {
+        Some(_) => 2,
+        None => decimals(currency),
+    };
+    format_rounded(amount, currency, digits)
+}
+
+/// Like `format`, with a fixed number of decimals (e.g. 0 for large totals).
+pub fn format_rounded(amount: f64, currency: &str, digits: usize) -> String {
+    let sign = if amount < 0.0 { "-" } else { "" };
+    let number = format!("{:.*}", digits, amount.abs());
+    match (symbol(currency), subunit(currency)) {
+        (Some(symbol), _) => format!("{}{}{}", sign, symbol, number),
+        (None, Some(("GBP", _))) => format!("{}{}p", sign, number),
+        (None, Some(("ZAR", _))) => format!("{}{}c", sign, number),
+        _ if currency.is_empty() => format!("{}{}", sign, number),
+        _ => format!("{}{} {}", sign, currency, number),
+    }
+}
+
+/// Yahoo symbol of the rate converting `from` into `to`, e.g. "EURUSD=X" (USD per EUR).
+pub fn fx_symbol(from: &str, to: &str) -> String {
+    format!("{}{}=X", from, to)
+}
+
+/// Inverse of `fx_symbol`.
+pub fn parse_fx_symbol(symbol: &str) -> Option<(&str, &str)> {
+    let pair = symbol.strip_suffix("=X")?;
+    if pair.len() != 6 || !pair.is_char_boundary(3) {
+        return None;
+    }
+    let (from, to) = pair.split_at(3);
+    (is_iso_code(from) && is_iso_code(to)).then_some((from, to))
+}
+
+/// Exchange rates into the base currency, collected from the FX pairs the `FetchService` delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxRates {
+    base: String,
+    rates: HashMap<String, f64>, // ISO currency -> units of `base` per unit
+}
+
+impl FxRates {
+    pub fn new(base: &str) -> Self {
+        Self { base: base.to_string(), rates: HashMap::new() }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// ISO currency whose rate is needed to convert amounts in `currency`, None if no rate is needed.
+    pub fn required(&self, currency: &str) -> Option<String> {
+        let iso = subunit(currency).map_or(currency, |(iso, _)| iso);
+        (iso != self.base && is_iso_code(iso)).then(|| iso.to_string())
+    }
+
+    pub fn set_rate(&mut self, currency: &str, rate: f64) {
+        if rate.is_finite() && rate > 0.0 {
+            self.rates.insert(currency.to_string(), rate);
+        }
+    }
+
+    /// Units of the base currency per unit of `currency`, None while its rate is unknown.
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        if let Some((iso, units)) = subunit(currency) {
+            return self.rate(iso).map(|rate| rate / units);
+        }
+        if currency == self.base {
+            return Some(1.0);
+        }
+        self.rates.get(currency).copied()
+    }
+
+    /// Converts between two currencies by way of the base currency.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        if let Some(amount) = convert_units(amount, from, to) {
+            return Some(amount);
+        }
+        Some(amount * self.rate(from)? / self.rate(to)?)
+    }
+
+    /// Prices of `candles` in the base currency, all at today's rate. None while the rate is unknown.
+    pub fn candles_to_base(&self, candles: &[Candle], currency: &str) -> Option<Vec<Candle>> {
+        let rate = self.rate(currency)?;
+        Some(candles.iter()
+            .map(|c| Candle { open: c.open * rate, high: c.high * rate, low: c.low * rate, close: c.close * rate, ..c.clone() })
+            .collect())
+    }
+}
+
+/// Candles and currency a widget shows: converted to the base currency when `rates` is set and
+/// knows the rate, as they are otherwise.
+pub fn display_prices<'a>(rates: Option<&'a FxRates>, candles: &'a [Candle], currency: &'a str) -> (Cow<'a, [Candle]>, &'a str) {
+    let converted = rates
+        .filter(|rates| rates.base() != currency)
+        .and_then(|rates| Some((rates.candles_to_base(candles, currency)?, rates.base())));
+    match converted {
+        Some((candles, base)) => (Cow::Owned(candles), base),
+        None => (Cow::Borrowed(candles), currency),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_with_iso_decimals_and_symbols() {
+        assert_eq!(format(12.3, "USD"), "$12.30");
+        assert_eq!(format(-12.3, "EUR"), "-€12.30");
+        assert_eq!(format(1520.4, "JPY"), "¥1520");
+        assert_eq!(format(1.23456, "KWD"), "KWD 1.235");
+        assert_eq!(format(12.3, "CHF"), "CHF 12.30");
+        assert_eq!(format(123.4, "GBp"), "123.40p");
+        assert_eq!(format(12.3, ""), "12.30");
+        assert_eq!(format_rounded(12345.6, "CAD", 0), "CA$12346");
+    }
+
+    #[test]
+    fn parses_fx_symbols() {
+        assert_eq!(parse_fx_symbol(&fx_symbol("EUR", "USD")), Some(("EUR", "USD")));
+        assert_eq!(parse_fx_symbol("AAPL"), None);
+        assert_eq!(parse_fx_symbol("EURUSD"), None);
+        assert_eq!(parse_fx_symbol("eurusd=X"), None);
+    }
+
+    #[test]
+    fn converts_through_the_base_currency() {
+        let mut rates = FxRates::new("EUR");
+        rates.set_rate("USD", 0.9);
+        rates.set_rate("GBP", 1.2);
+
+        assert_eq!(rates.rate("EUR"), Some(1.0));
+        assert_eq!(rates.rate("USD"), Some(0.9));
+        assert_eq!(rates.rate("GBp"), Some(0.012));
+        assert_eq!(rates.rate("CHF"), None);
+        assert!((rates.convert(120.0, "GBP", "USD").unwrap() - 160.0).abs() < 1e-9);
+        assert_eq!(rates.convert(250.0, "GBp", "GBP"), Some(2.5));
+        assert_eq!(rates.convert(1.0, "CHF", "CHF"), Some(1.0));
+        assert_eq!(rates.convert(1.0, "CHF", "EUR"), None);
+
+        assert_eq!(rates.required("EUR"), None);
+        assert_eq!(rates.required("GBp").as_deref(), Some("GBP"));
+        assert_eq!(rates.required(""), None);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use winit::event_loop::EventLoopProxy;
+use chrono::{DateTime, Local};
+use crate::cache::QuoteCache;
+use crate::common::UserEvent;
+use crate::market_hours;
+use crate::provider::{self, QuoteSeries};
+
+// Upper bound for requests hitting the data provider at the same time
+const MAX_CONCURRENT_FETCHES: usize = 4;
+// Fetched series are written to disk together, at most this often (and on exit)
+const CACHE_SAVE_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct FetchKey {
+    provider: String,
+    symbol: String,
+    timeframe: String,
+}
+
+/// Central data fetching service owned by `App`.
+/// Runs every request on one shared tokio runtime, coalesces identical (symbol, timeframe) requests
+/// that are still in flight and caps how many requests run concurrently.
+/// Results are delivered as `UserEvent::DataLoaded` and fanned out to all interested windows by `App`,
+/// and written to the on-disk `QuoteCache` in batches.
+#[derive(Clone)]
+pub struct FetchService {
+    runtime: Arc<Runtime>,
+    proxy: EventLoopProxy<UserEvent>,
+    in_flight: Arc<Mutex<HashSet<FetchKey>>>,
+    permits: Arc<Semaphore>,
+    cache: Arc<Mutex<QuoteCache>>,
+    cache_dirty: Arc<AtomicBool>,
+}
+
+impl FetchService {
+    pub fn new(proxy: EventLoopProxy<UserEvent>) -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("fetch-worker")
+            .enable_all()
+            .build()
+            .unwrap();
+
+        Self {
+            runtime: Arc::new(runtime),
+            proxy,
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES)),
+            cache: Arc::new(Mutex::new(QuoteCache::load())),
+            cache_dirty: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Last persisted series for (symbol, timeframe), regardless of its age.
+    pub fn cached(&self, symbol: &str, timeframe: &str, provider_name: Option<&str>) -> Option<(QuoteSeries, DateTime<Local>)> {
+        let interval = provider::provider_for(provider_name).interval(timeframe);
+        let cache = self.cache.lock().unwrap();
+        cache.get(symbol, timeframe, interval).map(|e| (e.series.clone(), e.fetched_at()))
+    }
+
+    pub fn request(&self, symbol: &str, timeframe: &str, provider_name: Option<&str>) {
+        let key = FetchKey {
+            provider: provider_name.unwrap_or(provider::DEFAULT_PROVIDER).to_string(),
+            symbol: symbol.to_string(),
+            timeframe: timeframe.to_string(),
+        };
+
+        // Already being fetched for another window, the result will be fanned out to everyone
+        if !self.in_flight.lock().unwrap().insert(key.clone()) {
+            log::debug!("Coalesced fetch for {} ({})", key.symbol, key.timeframe);
+            return;
+        }
+
+        let proxy = self.proxy.clone();
+        let in_flight = self.in_flight.clone();
+        let permits = self.permits.clone();
+        let cache = self.cache.clone();
+        let cache_dirty = self.cache_dirty.clone();
+
+        self.runtime.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+
+            let provider = provider::provider_for(Some(&key.provider));
+            log::info!("Fetching data for {} ({}) from {}", key.symbol, key.timeframe, provider.name());
+            let result = provider.fetch(&key.symbol, &key.timeframe).await;
+
+            in_flight.lock().unwrap().remove(&key);
+
+            match result {
+                Ok(series) => {
+                    cache.lock().unwrap().insert(&key.symbol, &key.timeframe, provider.interval(&key.timeframe), series.clone());
+                    // The first change since the last save schedules the next one
+                    if !cache_dirty.swap(true, Ordering::SeqCst) {
+                        tokio::spawn(save_cache_later(cache, cache_dirty));
+                    }
+                    let _ = proxy.send_event(UserEvent::DataLoaded(key.symbol, key.timeframe, series));
+                },
+                Err(e) => {
+                    let _ = proxy.send_event(UserEvent::Error(key.symbol, e));
+                }
+            }
+        });
+    }
+
+    /// Writes cached series that haven't been saved yet, called on exit.
+    pub fn save_cache(&self) {
+        if self.cache_dirty.swap(false, Ordering::SeqCst) {
+            QuoteCache::save(&self.cache);
+        }
+    }
+}
+
+async fn save_cache_later(cache: Arc<Mutex<QuoteCache>>, dirty: Arc<AtomicBool>) {
+    tokio::time::sleep(CACHE_SAVE_DELAY).await;
+    if dirty.swap(false, Ordering::SeqCst) {
+        let _ = tokio::task::spawn_blocking(move || QuoteCache::save(&cache)).await;
+    }
+}
+
+/// A series the way a widget keeps it. One restored from the on-disk cache counts as stale until
+/// the network answers, see `market_hours::is_outdated`.
+#[derive(Clone, Debug)]
+pub struct CachedSeries {
+    pub series: QuoteSeries,
+    pub fetched_at: DateTime<Local>,
+    pub from_disk: bool,
+}
+
+/// Latest series of each symbol a watchlist, ticker or the portfolio shows, all for one timeframe.
+/// Starts out with what the on-disk cache has and is kept current through the `FetchService`.
+pub struct SymbolData {
+    fetcher: FetchService,
+    provider: Option<String>,
+    timeframe: String,
+    symbols: Vec<String>,
+    series: HashMap<String, CachedSeries>,
+    failed: HashSet<String>, // Symbols whose last fetch failed
+}
+
+impl SymbolData {
+    pub fn new(fetcher: FetchService, symbols: Vec<String>, timeframe: String, provider: Option<String>) -> Self {
+        let mut data = Self { fetcher, provider, timeframe, symbols, series: HashMap::new(), failed: HashSet::new() };
+        data.load_cached();
+        data
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    pub fn timeframe(&self) -> &str {
+        &self.timeframe
+    }
+
+    pub fn provider(&self) -> Option<&str> {
+        self.provider.as_deref()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&QuoteSeries> {
+        self.series.get(symbol).map(|cached| &cached.series)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// Keeps the series of symbols that are still shown.
+    pub fn set_symbols(&mut self, symbols: Vec<String>) {
+        self.series.retain(|symbol, _| symbols.contains(symbol));
+        self.failed.retain(|symbol| symbols.contains(symbol));
+        self.symbols = symbols;
+        self.load_cached();
+    }
+
+    pub fn set_timeframe(&mut self, timeframe: String) {
+        self.timeframe = timeframe;
+        self.series.clear();
+        self.failed.clear();
+        self.load_cached();
+    }
+
+    /// Requests every series that is missing or outdated, same rule as the charts.
+    pub fn refresh(&self) {
+        for symbol in &self.symbols {
+            let outdated = self.series.get(symbol).is_none_or(|cached| market_hours::is_outdated(symbol, cached.fetched_at, cached.from_disk));
+            if outdated {
+                self.fetcher.request(symbol, &self.timeframe, self.provider.as_deref());
+            }
+        }
+    }
+
+    /// Stores a fetched series. Returns false if it is for another timeframe or a symbol not shown.
+    pub fn update(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries) -> bool {
+        if timeframe != self.timeframe || !self.symbols.iter().any(|s| s == symbol) {
+            return false;
+        }
+        self.series.insert(symbol.to_string(), CachedSeries { series, fetched_at: Local::now(), from_disk: false });
+        self.failed.remove(symbol);
+        true
+    }
+
+    /// Notes a failed fetch. Returns true once there is nothing to show because every symbol failed.
+    pub fn fetch_failed(&mut self, symbol: &str) -> bool {
+        if self.symbols.iter().any(|s| s == symbol) {
+            self.failed.insert(symbol.to_string());
+        }
+        self.series.is_empty() && self.symbols.iter().all(|s| self.failed.contains(s))
+    }
+
+    // Persisted series of the symbols we have nothing for yet
+    fn load_cached(&mut self) {
+        for symbol in &self.symbols {
+            if self.series.contains_key(symbol) {
+                continue;
+            }
+            if let Some((series, fetched_at)) = self.fetcher.cached(symbol, &self.timeframe, self.provider.as_deref()) {
+                self.series.insert(symbol.clone(), CachedSeries { series, fetched_at, from_disk: true });
+            }
+        }
+    }
+}
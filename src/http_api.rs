@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use winit::event_loop::EventLoopProxy;
+use winit::window::WindowId;
+use crate::alerts::{AlertEvent, AlertRule};
+use crate::common::UserEvent;
+use crate::config::ChartStyle;
+use crate::ipc::ChartData;
+
+const MAX_HEAD_LEN: usize = 16 * 1024;
+const MAX_BODY_LEN: usize = 64 * 1024;
+// Slow clients must not pin a connection task forever
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Last price of a chart, taken from its most recently fetched timeframe.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub chart_id: String,
+    pub symbol: String,
+    pub currency: String,
+    pub price: f64,
+    pub change_percent: f64, // Over `timeframe`
+    pub timeframe: String,
+    pub timestamp: i64, // Unix seconds of the last candle
+    pub fetched_at: i64,
+    pub stale: bool, // Restored from the on-disk cache and not refreshed yet
+}
+
+/// Everything the API serves. The event loop republishes it whenever charts, quotes or alerts change,
+/// so requests never have to wait for the UI thread.
+#[derive(Default)]
+pub struct ApiState {
+    pub charts: Vec<(WindowId, ChartData)>,
+    pub quotes: Vec<Quote>,
+    pub alerts: Vec<AlertRule>,
+    pub alert_history: Vec<AlertEvent>, // Newest first
+}
+
+pub type SharedApiState = Arc<Mutex<ApiState>>;
+
+/// Starts the API on `127.0.0.1:port` in its own thread.
+///
+/// `GET /api/charts`, `/api/quotes` and `/api/alerts` return the published state. `POST /api/charts`,
+/// `POST /api/charts/<id>/{timeframe,style,lock}`, `DELETE /api/charts/<id>` and `POST /api/update-check`
+/// are turned into the matching `UserEvent` and answered with 202 before the event loop handled them.
+pub fn spawn(port: u16, state: SharedApiState, proxy: EventLoopProxy<UserEvent>) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let listener = match tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await {
+                Ok(l) => l,
+                Err(e) => {
+                    log::error!("Failed to start HTTP API on port {}: {}", port, e);
+                    return;
+                }
+            };
+            log::info!("HTTP API listening on http://127.0.0.1:{}", port);
+
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept HTTP API connection: {}", e);
+                        continue;
+                    }
+                };
+                let state = state.clone();
+                let proxy = proxy.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &state, &proxy).await {
+                        log::debug!("HTTP API connection error: {}", e);
+                    }
+                });
+            }
+        });
+    });
+}
+
+// One request per connection, the response closes it
+async fn serve(mut stream: tokio::net::TcpStream, state: &SharedApiState, proxy: &EventLoopProxy<UserEvent>) -> io::Result<()> {
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => {
+            let (response, event) = {
+                let state = state.lock().unwrap();
+                route(&request, &state)
+            };
+            if let Some(event) = event {
+                let _ = proxy.send_event(event);
+            }
+            response
+        },
+        Ok(Err(e)) if e.kind() == io::ErrorKind::InvalidData => Response::error(400, e.to_string()),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => Response::error(408, "Request timed out"),
+    };
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String, // Without the query string
+    headers: HashMap<String, String>, // Lowercase names
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    // Web pages can't pick the Host header, so checking it stops DNS rebinding attacks from a browser
+    fn host_is_loopback(&self) -> bool {
+        let Some(host) = self.header("host") else { return true };
+        let name = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        };
+        matches!(name, "127.0.0.1" | "localhost" | "[::1]")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn accepted() -> Self {
+        Self { status: 202, body: json!({ "status": "accepted" }) }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self { status, body: json!({ "error": message.into() }) }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            408 => "Request Timeout",
+            415 => "Unsupported Media Type",
+            _ => "Error",
+        };
+        let body = self.body.to_string();
+        let mut out = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status, reason, body.len()
+        ).into_bytes();
+        out.extend_from_slice(body.as_bytes());
+        out
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+async fn read_request<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Request> {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_LEN {
+            return Err(invalid("Request head too large"));
+        }
+        let mut chunk = [0u8; 1024];
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = std::str::from_utf8(&buf[..head_end]).map_err(|_| invalid("Request head is not UTF-8"))?;
+    let mut request = parse_head(head)?;
+
+    let len = match request.header("content-length") {
+        Some(v) => v.trim().parse::<usize>().map_err(|_| invalid("Invalid Content-Length"))?,
+        None => 0,
+    };
+    if len > MAX_BODY_LEN {
+        return Err(invalid("Request body too large"));
+    }
+    let mut body = buf[head_end + 4..].to_vec();
+    if body.len() < len {
+        let start = body.len();
+        body.resize(len, 0);
+        reader.read_exact(&mut body[start..]).await?;
+    }
+    body.truncate(len);
+    request.body = body;
+    Ok(request)
+}
+
+fn parse_head(head: &str) -> io::Result<Request> {
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid("Malformed request line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(invalid("Unsupported HTTP version"));
+    }
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or_else(|| invalid("Malformed header"))?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    Ok(Request { method: method.to_string(), path, headers, body: Vec::new() })
+}
+
+// Chart ids contain parentheses, clients may send them encoded
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(b) = s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Deserialize)]
+struct AddChartBody {
+    symbol: String,
+}
+
+#[derive(Deserialize)]
+struct TimeframeBody {
+    timeframe: String,
+}
+
+#[derive(Deserialize)]
+struct StyleBody {
+    style: ChartStyle,
+}
+
+#[derive(Deserialize)]
+struct LockBody {
+    locked: bool,
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(request: &'a Request) -> Result<T, Response> {
+    serde_json::from_slice(&request.body).map_err(|e| Response::error(400, format!("Invalid request body: {}", e)))
+}
+
+fn find_chart(state: &ApiState, id: &str) -> Result<WindowId, Response> {
+    let id = percent_decode(id);
+    state.charts.iter()
+        .find(|(_, chart)| chart.id == id)
+        .map(|(wid, _)| *wid)
+        .ok_or_else(|| Response::error(404, format!("Unknown chart id {}", id)))
+}
+
+fn route(request: &Request, state: &ApiState) -> (Response, Option<UserEvent>) {
+    match route_inner(request, state) {
+        Ok((response, event)) => (response, event),
+        Err(response) => (response, None),
+    }
+}
+
+fn route_inner(request: &Request, state: &ApiState) -> Result<(Response, Option<UserEvent>), Response> {
+    if !request.host_is_loopback() {
+        return Err(Response::error(403, "Forbidden host"));
+    }
+    // Browsers only send JSON cross-origin after a CORS preflight, which is never answered
+    if request.method == "POST" && !request.header("content-type").is_some_and(|t| t.starts_with("application/json")) {
+        return Err(Response::error(415, "Expected Content-Type: application/json"));
+    }
+
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let action = |event: UserEvent| Ok((Response::accepted(), Some(event)));
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "charts"]) => {
+            let charts: Vec<&ChartData> = state.charts.iter().map(|(_, c)| c).collect();
+            Ok((Response::ok(json!(charts)), None))
+        },
+        ("GET", ["api", "quotes"]) => Ok((Response::ok(json!(state.quotes)), None)),
+        ("GET", ["api", "alerts"]) => Ok((Response::ok(json!({ "rules": state.alerts, "history": state.alert_history })), None)),
+        ("POST", ["api", "charts"]) => {
+            let body: AddChartBody = parse_body(request)?;
+            let symbol = body.symbol.trim().to_uppercase();
+            if symbol.is_empty() {
+                return Err(Response::error(400, "Empty symbol"));
+            }
+            action(UserEvent::AddChart(symbol, None))
+        },
+        ("DELETE", ["api", "charts", id]) => action(UserEvent::DeleteChart(find_chart(state, id)?)),
+        ("POST", ["api", "charts", id, "timeframe"]) => {
+            let wid = find_chart(state, id)?;
+            let body: TimeframeBody = parse_body(request)?;
+            if !crate::provider::TIMEFRAMES.contains(&body.timeframe.as_str()) {
+                return Err(Response::error(400, format!("Unknown timeframe {}, expected one of {}", body.timeframe, crate::provider::TIMEFRAMES.join(", "))));
+            }
+            action(UserEvent::ChartTimeframe(wid, body.timeframe))
+        },
+        ("POST", ["api", "charts", id, "style"]) => {
+            let wid = find_chart(state, id)?;
+            let body: StyleBody = parse_body(request)?;
+            action(UserEvent::ChartStyle(wid, body.style))
+        },
+        ("POST", ["api", "charts", id, "lock"]) => {
+            let wid = find_chart(state, id)?;
+            let body: LockBody = parse_body(request)?;
+            action(UserEvent::ToggleLock(wid, body.locked))
+        },
+        ("POST", ["api", "update-check"]) => action(UserEvent::CheckForUpdates),
+        _ => Err(Response::error(404, format!("No route for {} {}", request.method, request.path))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(raw: &str) -> Request {
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        rt.block_on(read_request(&mut raw.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn parses_request_with_body() {
+        let req = request("POST /api/charts?x=1 HTTP/1.1\r\nHost: 127.0.0.1:8917\r\nContent-Type: application/json\r\nContent-Length: 18\r\n\r\n{\"symbol\":\"msft\"}\n");
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/api/charts");
+        assert_eq!(req.header("content-type"), Some("application/json"));
+        assert_eq!(req.body, b"{\"symbol\":\"msft\"}\n");
+    }
+
+    #[test]
+    fn rejects_oversized_body() {
+        let raw = format!("POST /api/charts HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_LEN + 1);
+        let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let err = rt.block_on(read_request(&mut raw.as_bytes())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn serves_state_and_validates_actions() {
+        let state = ApiState {
+            alerts: vec![AlertRule::new(1, "MSFT".to_string(), crate::alerts::AlertCondition::NewHigh52w)],
+            ..ApiState::default()
+        };
+
+        let (res, event) = route(&request("GET /api/alerts HTTP/1.1\r\nHost: localhost:8917\r\n\r\n"), &state);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body["rules"][0]["symbol"], "MSFT");
+        assert!(event.is_none());
+
+        // Foreign Host header, e.g. a rebound DNS name
+        let (res, _) = route(&request("GET /api/charts HTTP/1.1\r\nHost: evil.example:8917\r\n\r\n"), &state);
+        assert_eq!(res.status, 403);
+
+        // Form posts from web pages don't get through
+        let (res, event) = route(&request("POST /api/charts HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}"), &state);
+        assert_eq!(res.status, 415);
+        assert!(event.is_none());
+
+        let (res, _) = route(&request("POST /api/charts/nope/timeframe HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}"), &state);
+        assert_eq!(res.status, 404);
+
+        let (res, event) = route(&request("POST /api/charts HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 17\r\n\r\n{\"symbol\":\"msft\"}"), &state);
+        assert_eq!(res.status, 202);
+        assert!(matches!(event, Some(UserEvent::AddChart(symbol, None)) if symbol == "MSFT"));
+    }
+
+    #[test]
+    fn decodes_chart_ids() {
+        assert_eq!(percent_decode("WindowId%28WindowId%281%29%29"), "WindowId(WindowId(1))");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
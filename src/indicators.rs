@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use crate::provider::Candle;
+
+/// Technical indicator with its parameters. Overlays are drawn on top of the price series,
+/// oscillators get their own pane below the chart.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind")]
+pub enum Indicator {
+    Sma { period: usize },
+    Ema { period: usize },
+    Bollinger { period: usize, std_dev: f64 },
+    Vwap,
+    Rsi { period: usize },
+    Macd { fast: usize, slow: usize, signal: usize },
+    Stochastic { k_period: usize, d_period: usize },
+}
+
+impl Indicator {
+    /// Defaults offered when adding an indicator in the settings
+    pub const DEFAULTS: [Indicator; 7] = [
+        Indicator::Sma { period: 20 },
+        Indicator::Ema { period: 50 },
+        Indicator::Bollinger { period: 20, std_dev: 2.0 },
+        Indicator::Vwap,
+        Indicator::Rsi { period: 14 },
+        Indicator::Macd { fast: 12, slow: 26, signal: 9 },
+        Indicator::Stochastic { k_period: 14, d_period: 3 },
+    ];
+
+    pub fn is_oscillator(&self) -> bool {
+        matches!(self, Indicator::Rsi { .. } | Indicator::Macd { .. } | Indicator::Stochastic { .. })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Indicator::Sma { .. } => "SMA",
+            Indicator::Ema { .. } => "EMA",
+            Indicator::Bollinger { .. } => "BB",
+            Indicator::Vwap => "VWAP",
+            Indicator::Rsi { .. } => "RSI",
+            Indicator::Macd { .. } => "MACD",
+            Indicator::Stochastic { .. } => "Stoch",
+        }
+    }
+}
+
+impl std::fmt::Display for Indicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Indicator::Sma { period } => write!(f, "SMA({})", period),
+            Indicator::Ema { period } => write!(f, "EMA({})", period),
+            Indicator::Bollinger { period, std_dev } => write!(f, "BB({}, {})", period, std_dev),
+            Indicator::Vwap => write!(f, "VWAP"),
+            Indicator::Rsi { period } => write!(f, "RSI({})", period),
+            Indicator::Macd { fast, slow, signal } => write!(f, "MACD({}, {}, {})", fast, slow, signal),
+            Indicator::Stochastic { k_period, d_period } => write!(f, "Stoch({}, {})", k_period, d_period),
+        }
+    }
+}
+
+// All functions return one value per input element, `None` until enough data is available.
+
+/// Simple moving average
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return result;
+    }
+    let mut sum: f64 = values[..period].iter().sum();
+    result[period - 1] = Some(sum / period as f64);
+    for i in period..values.len() {
+        sum += values[i] - values[i - period];
+        result[i] = Some(sum / period as f64);
+    }
+    result
+}
+
+/// Exponential moving average, seeded with the SMA of the first `period` values
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return result;
+    }
+    let k = 2.0 / (period as f64 + 1.0);
+    let mut prev = values[..period].iter().sum::<f64>() / period as f64;
+    result[period - 1] = Some(prev);
+    for i in period..values.len() {
+        prev = values[i] * k + prev * (1.0 - k);
+        result[i] = Some(prev);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+/// Bollinger bands: SMA +/- `std_dev` population standard deviations
+pub fn bollinger(values: &[f64], period: usize, std_dev: f64) -> Vec<Option<Band>> {
+    sma(values, period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, mean)| {
+            let mean = mean?;
+            let window = &values[i + 1 - period..=i];
+            let variance = window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / period as f64;
+            let offset = variance.sqrt() * std_dev;
+            Some(Band { middle: mean, upper: mean + offset, lower: mean - offset })
+        })
+        .collect()
+}
+
+/// Volume weighted average price of the typical price (H+L+C)/3, anchored at the first candle
+pub fn vwap(candles: &[Candle]) -> Vec<Option<f64>> {
+    let mut cum_pv = 0.0;
+    let mut cum_volume = 0.0;
+    candles
+        .iter()
+        .map(|c| {
+            let typical = (c.high + c.low + c.close) / 3.0;
+            cum_pv += typical * c.volume as f64;
+            cum_volume += c.volume as f64;
+            if cum_volume > 0.0 { Some(cum_pv / cum_volume) } else { None }
+        })
+        .collect()
+}
+
+/// Relative strength index with Wilder's smoothing, 0..100
+pub fn rsi(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if period == 0 || values.len() <= period {
+        return result;
+    }
+    let change = |i: usize| values[i] - values[i - 1];
+    let mut avg_gain = (1..=period).map(|i| change(i).max(0.0)).sum::<f64>() / period as f64;
+    let mut avg_loss = (1..=period).map(|i| (-change(i)).max(0.0)).sum::<f64>() / period as f64;
+
+    let to_rsi = |gain: f64, loss: f64| if loss == 0.0 { 100.0 } else { 100.0 - 100.0 / (1.0 + gain / loss) };
+    result[period] = Some(to_rsi(avg_gain, avg_loss));
+    for (i, slot) in result.iter_mut().enumerate().skip(period + 1) {
+        avg_gain = (avg_gain * (period - 1) as f64 + change(i).max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change(i)).max(0.0)) / period as f64;
+        *slot = Some(to_rsi(avg_gain, avg_loss));
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdPoint {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// MACD line (fast EMA - slow EMA), its signal EMA and the histogram between both
+pub fn macd(values: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<Option<MacdPoint>> {
+    let fast_ema = ema(values, fast);
+    let slow_ema = ema(values, slow);
+    let macd_line: Vec<Option<f64>> = fast_ema.iter().zip(&slow_ema).map(|(f, s)| Some((*f)? - (*s)?)).collect();
+
+    // Signal line only runs over the defined part of the MACD line
+    let start = macd_line.iter().position(|v| v.is_some()).unwrap_or(macd_line.len());
+    let defined: Vec<f64> = macd_line[start..].iter().map(|v| v.unwrap_or_default()).collect();
+    let signal_line = ema(&defined, signal);
+
+    let mut result = vec![None; values.len()];
+    for (offset, sig) in signal_line.into_iter().enumerate() {
+        if let Some(sig) = sig {
+            let m = defined[offset];
+            result[start + offset] = Some(MacdPoint { macd: m, signal: sig, histogram: m - sig });
+        }
+    }
+    result
+}
+
+/// Stochastic oscillator (%K, %D), 0..100
+pub fn stochastic(candles: &[Candle], k_period: usize, d_period: usize) -> Vec<Option<(f64, f64)>> {
+    if k_period == 0 || candles.len() < k_period {
+        return vec![None; candles.len()];
+    }
+    let k_line: Vec<Option<f64>> = (0..candles.len())
+        .map(|i| {
+            if i + 1 < k_period {
+                return None;
+            }
+            let window = &candles[i + 1 - k_period..=i];
+            let lowest = window.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+            let highest = window.iter().map(|c| c.high).fold(f64::NEG_INFINITY, f64::max);
+            if highest > lowest {
+                Some((candles[i].close - lowest) / (highest - lowest) * 100.0)
+            } else {
+                Some(50.0)
+            }
+        })
+        .collect();
+
+    let start = k_period - 1;
+    let defined: Vec<f64> = k_line[start..].iter().map(|v| v.unwrap_or_default()).collect();
+    let d_line = sma(&defined, d_period);
+
+    let mut result = vec![None; candles.len()];
+    for (offset, d) in d_line.into_iter().enumerate() {
+        if let Some(d) = d {
+            result[start + offset] = Some((defined[offset], d));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10-day SMA/EMA reference values from the StockCharts "Moving Averages" example
+    const CLOSES: [f64; 20] = [
+        22.27, 22.19, 22.08, 22.17, 22.18, 22.13, 22.23, 22.43, 22.24, 22.29,
+        22.15, 22.39, 22.38, 22.61, 23.36, 24.05, 23.75, 23.83, 23.95, 23.63,
+    ];
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("value should be defined");
+        assert!((actual - expected).abs() < 0.01, "expected {}, got {}", expected, actual);
+    }
+
+    fn candle(high: f64, low: f64, close: f64, volume: u64) -> Candle {
+        Candle { timestamp: 0, open: close, high, low, close, volume }
+    }
+
+    #[test]
+    fn sma_matches_reference() {
+        let result = sma(&CLOSES, 10);
+        assert!(result[..9].iter().all(|v| v.is_none()));
+        assert_close(result[9], 22.22);
+        assert_close(result[10], 22.21);
+        assert_close(result[19], 23.21);
+    }
+
+    #[test]
+    fn ema_matches_reference() {
+        let result = ema(&CLOSES, 10);
+        assert!(result[..9].iter().all(|v| v.is_none()));
+        assert_close(result[9], 22.22);
+        assert_close(result[10], 22.21);
+        assert_close(result[11], 22.24);
+        assert_close(result[14], 22.52);
+        assert_close(result[19], 23.34);
+    }
+
+    #[test]
+    fn moving_averages_need_enough_data() {
+        assert!(sma(&[1.0, 2.0], 3).iter().all(|v| v.is_none()));
+        assert!(ema(&[1.0, 2.0], 0).iter().all(|v| v.is_none()));
+    }
+
+    #[test]
+    fn bollinger_uses_population_std_dev() {
+        let result = bollinger(&[1.0, 2.0, 3.0, 4.0, 5.0], 5, 2.0);
+        let band = result[4].unwrap();
+        assert_close(Some(band.middle), 3.0);
+        assert_close(Some(band.upper), 5.8284);
+        assert_close(Some(band.lower), 0.1716);
+        assert!(result[3].is_none());
+    }
+
+    // 14-day RSI input from the StockCharts "RSI" example
+    const RSI_CLOSES: [f64; 20] = [
+        44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08,
+        45.89, 46.03, 45.61, 46.28, 46.28, 46.00, 46.03, 46.41, 46.22, 45.64,
+    ];
+
+    #[test]
+    fn rsi_uses_wilder_smoothing() {
+        let result = rsi(&RSI_CLOSES, 14);
+        assert!(result[..14].iter().all(|v| v.is_none()));
+        assert_close(result[14], 70.46);
+        assert_close(result[15], 66.25);
+        assert_close(result[19], 57.92);
+        assert_close(rsi(&[1.0, 2.0, 3.0], 2)[2], 100.0);
+    }
+
+    #[test]
+    fn macd_is_difference_of_emas() {
+        let result = macd(&CLOSES, 3, 6, 4);
+        let fast = ema(&CLOSES, 3);
+        let slow = ema(&CLOSES, 6);
+        // MACD defined from index 5, signal needs 4 more values
+        assert!(result[..8].iter().all(|v| v.is_none()));
+        let point = result[19].unwrap();
+        assert_close(Some(point.macd), fast[19].unwrap() - slow[19].unwrap());
+        assert_close(Some(point.histogram), point.macd - point.signal);
+    }
+
+    #[test]
+    fn stochastic_ranges_between_low_and_high() {
+        let candles = [
+            candle(10.0, 8.0, 9.0, 0),
+            candle(12.0, 9.0, 12.0, 0),
+            candle(11.0, 10.0, 10.0, 0),
+            candle(13.0, 10.0, 13.0, 0),
+        ];
+        let result = stochastic(&candles, 2, 2);
+        assert!(result[..2].iter().all(|v| v.is_none()));
+        // %K: 100 (close at the 2-bar high), 33.3 ((10-9)/(12-9)), then 100 again
+        let (k, d) = result[2].unwrap();
+        assert_close(Some(k), 100.0 / 3.0);
+        assert_close(Some(d), (100.0 + 100.0 / 3.0) / 2.0);
+        assert_close(result[3].map(|(k, _)| k), 100.0);
+    }
+
+    #[test]
+    fn vwap_weights_typical_price_by_volume() {
+        let candles = [
+            candle(11.0, 9.0, 10.0, 100),  // typical 10
+            candle(13.0, 11.0, 12.0, 300), // typical 12
+            candle(20.0, 20.0, 20.0, 0),   // no volume, no change
+        ];
+        let result = vwap(&candles);
+        assert_close(result[0], 10.0);
+        assert_close(result[1], 11.5);
+        assert_close(result[2], 11.5);
+        assert_eq!(vwap(&[candle(1.0, 1.0, 1.0, 0)]), vec![None]);
+    }
+}
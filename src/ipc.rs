@@ -1,6 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::config::ChartStyle;
+use crate::indicators::Indicator;
+use crate::alerts::{AlertCondition, AlertEvent, AlertRule};
+use crate::portfolio::Position;
 
+/// Bumped whenever `Frame` or `IpcMessage` change incompatibly.
+pub const PROTOCOL_VERSION: u32 = 8;
+/// Client name the settings process announces in its `Hello`.
+pub const SETTINGS_CLIENT: &str = "settings";
+/// Upper bound for a single frame. A larger length prefix means a broken or hostile peer.
+pub const MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+#[cfg(windows)]
 pub const PIPE_NAME: &str = r"\\.\pipe\desktop-widget-rs-ipc";
+#[cfg(unix)]
+const SOCKET_NAME: &str = "desktop-widget-rs.sock";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChartData {
@@ -8,6 +26,25 @@ pub struct ChartData {
     pub symbol: String,
     pub timeframe: String,
     pub locked: bool,
+    pub style: ChartStyle,
+    pub indicators: Vec<Indicator>,
+    #[serde(default)]
+    pub compare_symbols: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchlistData {
+    pub id: String,
+    pub symbols: Vec<String>,
+    pub locked: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TickerData {
+    pub id: String,
+    pub symbols: Vec<String>,
+    pub speed: u32, // Pixels per second
+    pub locked: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -16,6 +53,9 @@ pub struct ConfigData {
     pub update_interval: u64,
     pub auto_start: bool,
     pub use_prereleases: bool,
+    pub show_portfolio: bool,
+    pub base_currency: Option<String>, // None = no conversion
+    pub convert_prices: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -25,17 +65,385 @@ pub enum IpcMessage {
     Config(ConfigData), // Response with config
     Charts(Vec<ChartData>),
     AddChart(String),
-    DeleteChart(String),
-    ToggleChartLock(String, bool),
+    DeleteChart(String), // Chart, watchlist or ticker id
+    ToggleChartLock(String, bool), // Chart, watchlist or ticker id
     SetChartTimeframe(String, String),
+    SetChartStyle(String, ChartStyle),
+    SetChartIndicators(String, Vec<Indicator>),
+    SetChartCompare(String, Vec<String>), // Empty turns comparison mode off
+    Watchlists(Vec<WatchlistData>),
+    AddWatchlist(Vec<String>),
+    SetSymbols(String, Vec<String>), // Watchlist or ticker id
+    Tickers(Vec<TickerData>),
+    AddTicker(Vec<String>),
+    SetTickerSpeed(String, u32),
+    GetPositions,
+    Positions(Vec<Position>),
+    SetPositions(Vec<Position>), // Replaces all positions
+    ShowPortfolio(bool),
+    GetAlerts,
+    Alerts(Vec<AlertRule>),
+    AlertHistory(Vec<AlertEvent>), // Newest first
+    AddAlert(String, AlertCondition), // Symbol, the host assigns the id
+    UpdateAlert(u64, AlertCondition),
+    SetAlertEnabled(u64, bool),
+    SetAlertCooldown(u64, u64), // Minutes
+    DeleteAlert(u64),
     SetLanguage(String),
     SetUpdateInterval(u64),
     SetAutoStart(bool),
     SetUsePrereleases(bool),
+    SetBaseCurrency(Option<String>), // ISO code, None turns conversion off
+    SetConvertPrices(bool),
     CheckForUpdates,
     PerformUpdate,
     UpdateStatus(crate::common::UpdateStatus),
-    Error(String),
     Restart,
+    Refresh, // Reload the data of all charts now
+    RestorePreviousLayout, // Reopen the widgets of the newest config backup that differs from the current layout
+    Quit, // Exit the host as if quit from the tray
     Shutdown, 
 }
+
+/// Unit sent over the wire. After the handshake (`Hello` -> `Welcome`/`Rejected`) the client sends
+/// `Request`s, each answered by exactly one `Ack` or `Failed` with the same id. The host pushes
+/// state (charts, config, update status, ...) as `Event`s at any time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Frame {
+    Hello { version: u32, client: String },
+    Welcome { version: u32 },
+    Rejected { version: u32, reason: String }, // The host closes the connection afterwards
+    Request { id: u64, message: IpcMessage },
+    Ack { id: u64 },
+    Failed { id: u64, error: String },
+    Event(IpcMessage),
+}
+
+impl Frame {
+    /// Response to request `id`.
+    pub fn response(id: u64, result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Frame::Ack { id },
+            Err(error) => Frame::Failed { id, error },
+        }
+    }
+}
+
+/// Events that may wait for a slow client before further ones are dropped.
+pub const MAX_QUEUED_EVENTS: usize = 32;
+
+/// Host side queue of the frames going out on one connection, drained in order by its writer task.
+/// Responses are never dropped, a client waiting for an `Ack` would hang otherwise. Events are
+/// dropped once `MAX_QUEUED_EVENTS` are pending; each one carries a full state, so a later one makes up for it.
+#[derive(Debug, Clone)]
+pub struct FrameSender {
+    tx: tokio::sync::mpsc::UnboundedSender<Frame>,
+    queued_events: Arc<AtomicUsize>,
+}
+
+pub struct FrameReceiver {
+    rx: tokio::sync::mpsc::UnboundedReceiver<Frame>,
+    queued_events: Arc<AtomicUsize>,
+}
+
+pub fn frame_queue() -> (FrameSender, FrameReceiver) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let queued_events = Arc::new(AtomicUsize::new(0));
+    (FrameSender { tx, queued_events: queued_events.clone() }, FrameReceiver { rx, queued_events })
+}
+
+impl FrameSender {
+    /// Queues the answer to request `id`.
+    pub fn respond(&self, id: u64, result: Result<(), String>) {
+        let _ = self.tx.send(Frame::response(id, result));
+    }
+
+    /// Queues an event unless the client is too far behind. Returns whether it was queued.
+    pub fn push(&self, message: IpcMessage) -> bool {
+        if self.queued_events.fetch_add(1, Ordering::Relaxed) >= MAX_QUEUED_EVENTS || self.tx.send(Frame::Event(message)).is_err() {
+            self.queued_events.fetch_sub(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+}
+
+impl FrameReceiver {
+    /// Next frame to write, None once every sender is gone.
+    pub async fn recv(&mut self) -> Option<Frame> {
+        let frame = self.rx.recv().await?;
+        if matches!(frame, Frame::Event(_)) {
+            self.queued_events.fetch_sub(1, Ordering::Relaxed);
+        }
+        Some(frame)
+    }
+}
+
+/// Bidirectional connection between the widget host and a client, independent of the transport.
+pub trait IpcStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IpcStream for T {}
+
+/// Unix domain socket the host listens on: `$XDG_RUNTIME_DIR/desktop-widget-rs.sock`,
+/// or one in a per-user directory in the temp dir if the runtime dir isn't set.
+#[cfg(unix)]
+pub fn socket_path() -> std::path::PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => std::path::PathBuf::from(dir).join(SOCKET_NAME),
+        _ => {
+            // SAFETY: getuid has no preconditions and can't fail
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("desktop-widget-rs-{}", uid)).join(SOCKET_NAME)
+        }
+    }
+}
+
+/// Creates the directory a socket is bound in, accessible by the current user only, or checks that an
+/// existing one (e.g. `$XDG_RUNTIME_DIR`) is. The socket itself is created with the umask, the directory
+/// keeps others out until its permissions are tightened.
+#[cfg(unix)]
+fn private_dir(dir: &std::path::Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {},
+        Err(e) => return Err(e),
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    // SAFETY: getuid has no preconditions and can't fail
+    let uid = unsafe { libc::getuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{:?} is accessible by other users", dir)));
+    }
+    Ok(())
+}
+
+/// Server side of the IPC transport: a named pipe on Windows, a Unix domain socket elsewhere.
+pub struct IpcListener {
+    #[cfg(windows)]
+    server: tokio::net::windows::named_pipe::NamedPipeServer,
+    #[cfg(unix)]
+    listener: tokio::net::UnixListener,
+    #[cfg(unix)]
+    path: std::path::PathBuf,
+}
+
+impl IpcListener {
+    #[cfg(windows)]
+    pub fn bind() -> io::Result<Self> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        let server = ServerOptions::new().first_pipe_instance(true).create(PIPE_NAME)?;
+        Ok(Self { server })
+    }
+
+    #[cfg(unix)]
+    pub fn bind() -> io::Result<Self> {
+        Self::bind_at(socket_path())
+    }
+
+    #[cfg(unix)]
+    pub fn bind_at(path: std::path::PathBuf) -> io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            private_dir(dir)?;
+        }
+        if path.exists() {
+            // A socket nobody listens on is left over from a crashed host
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is already listening"));
+            }
+            std::fs::remove_file(&path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        // Only the current user may talk to the host
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Self { listener, path })
+    }
+
+    /// Waits for the next client.
+    #[cfg(windows)]
+    pub async fn accept(&mut self) -> io::Result<Box<dyn IpcStream>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+        self.server.connect().await?;
+        // Create the next instance before handing out the connected one, so clients never find the pipe missing
+        let next = ServerOptions::new().create(PIPE_NAME)?;
+        Ok(Box::new(std::mem::replace(&mut self.server, next)))
+    }
+
+    #[cfg(unix)]
+    pub async fn accept(&mut self) -> io::Result<Box<dyn IpcStream>> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for IpcListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Client side of the IPC transport. Fails if the host isn't running.
+#[cfg(windows)]
+pub async fn connect() -> io::Result<Box<dyn IpcStream>> {
+    let client = tokio::net::windows::named_pipe::ClientOptions::new().open(PIPE_NAME)?;
+    Ok(Box::new(client))
+}
+
+#[cfg(unix)]
+pub async fn connect() -> io::Result<Box<dyn IpcStream>> {
+    connect_at(&socket_path()).await
+}
+
+#[cfg(unix)]
+pub async fn connect_at(path: &std::path::Path) -> io::Result<Box<dyn IpcStream>> {
+    let stream = tokio::net::UnixStream::connect(path).await?;
+    Ok(Box::new(stream))
+}
+
+/// Writes one frame as a little-endian u32 length followed by its JSON.
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> io::Result<()> {
+    let json = serde_json::to_vec(frame)?;
+    if json.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("frame of {} bytes exceeds the limit", json.len())));
+    }
+    writer.write_all(&(json.len() as u32).to_le_bytes()).await?;
+    writer.write_all(&json).await?;
+    writer.flush().await
+}
+
+/// Reads one frame written by `write_frame`. Returns None once the other side closed the connection.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Frame>> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf).await {
+        Ok(_) => {},
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame of {} bytes exceeds the limit", len)));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(Some(serde_json::from_slice(&buf)?))
+}
+
+/// Host side of the handshake: expects `Hello` and answers `Welcome`, or `Rejected` plus an error
+/// if the client speaks a different protocol version (e.g. a settings process started from a freshly
+/// auto-updated binary while the old host is still running).
+pub async fn accept_handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S) -> io::Result<String> {
+    let (version, client) = match read_frame(stream).await? {
+        Some(Frame::Hello { version, client }) => (version, client),
+        Some(other) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected Hello, got {:?}", other))),
+        None => return Err(io::ErrorKind::UnexpectedEof.into()),
+    };
+
+    if version != PROTOCOL_VERSION {
+        let reason = format!("client '{}' speaks protocol v{}, host speaks v{}", client, version, PROTOCOL_VERSION);
+        write_frame(stream, &Frame::Rejected { version: PROTOCOL_VERSION, reason: reason.clone() }).await?;
+        return Err(io::Error::new(io::ErrorKind::Unsupported, reason));
+    }
+
+    write_frame(stream, &Frame::Welcome { version: PROTOCOL_VERSION }).await?;
+    Ok(client)
+}
+
+/// Client side of the handshake. Fails with `ErrorKind::Unsupported` if the host rejected our version.
+pub async fn client_handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, client: &str) -> io::Result<()> {
+    write_frame(stream, &Frame::Hello { version: PROTOCOL_VERSION, client: client.to_string() }).await?;
+    match read_frame(stream).await? {
+        Some(Frame::Welcome { .. }) => Ok(()),
+        Some(Frame::Rejected { reason, .. }) => Err(io::Error::new(io::ErrorKind::Unsupported, reason)),
+        Some(other) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected Welcome, got {:?}", other))),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn round_trip_over_unix_socket() {
+        let dir = std::env::temp_dir().join(format!("desktop-widget-rs-test-{}", std::process::id()));
+        let path = dir.join(SOCKET_NAME);
+        let mut listener = IpcListener::bind_at(path.clone()).unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = connect_at(&path).await.unwrap();
+            client_handshake(&mut stream, "test").await.unwrap();
+            write_frame(&mut stream, &Frame::Request { id: 7, message: IpcMessage::AddChart("MSFT".to_string()) }).await.unwrap();
+            read_frame(&mut stream).await.unwrap()
+        });
+
+        let mut server = listener.accept().await.unwrap();
+        assert_eq!(accept_handshake(&mut server).await.unwrap(), "test");
+        match read_frame(&mut server).await.unwrap() {
+            Some(Frame::Request { id: 7, message: IpcMessage::AddChart(symbol) }) => assert_eq!(symbol, "MSFT"),
+            other => panic!("unexpected frame {:?}", other),
+        }
+        write_frame(&mut server, &Frame::response(7, Ok(()))).await.unwrap();
+        assert!(matches!(client.await.unwrap(), Some(Frame::Ack { id: 7 })));
+
+        drop(listener);
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shared_socket_dir_is_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("desktop-widget-rs-shared-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let err = IpcListener::bind_at(dir.join(SOCKET_NAME)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[tokio::test]
+    async fn mismatched_version_is_rejected() {
+        let (mut host, mut client) = tokio::io::duplex(1024);
+        let host = tokio::spawn(async move { accept_handshake(&mut host).await });
+
+        write_frame(&mut client, &Frame::Hello { version: PROTOCOL_VERSION + 1, client: "settings".to_string() }).await.unwrap();
+        assert!(matches!(read_frame(&mut client).await.unwrap(), Some(Frame::Rejected { version: PROTOCOL_VERSION, .. })));
+        assert_eq!(host.await.unwrap().unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn oversized_frame_is_refused() {
+        let (mut a, mut b) = tokio::io::duplex(64);
+        b.write_all(&(MAX_FRAME_LEN as u32 + 1).to_le_bytes()).await.unwrap();
+        assert_eq!(read_frame(&mut a).await.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn closed_connection_reads_none() {
+        let (mut a, b) = tokio::io::duplex(64);
+        drop(b);
+        assert!(read_frame(&mut a).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn responses_are_kept_when_events_are_dropped() {
+        let (tx, mut rx) = frame_queue();
+        for _ in 0..MAX_QUEUED_EVENTS {
+            assert!(tx.push(IpcMessage::GetCharts));
+        }
+        assert!(!tx.push(IpcMessage::GetCharts));
+        tx.respond(3, Ok(()));
+
+        for _ in 0..MAX_QUEUED_EVENTS {
+            assert!(matches!(rx.recv().await, Some(Frame::Event(_))));
+        }
+        assert!(matches!(rx.recv().await, Some(Frame::Ack { id: 3 })));
+        // Draining made room again
+        assert!(tx.push(IpcMessage::GetCharts));
+    }
+}
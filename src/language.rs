@@ -53,6 +53,55 @@ pub enum TextId {
     UpdateSuccess,
     SymbolPlaceholder,
     ShowPrereleases,
+    CachedData,
+    AddIndicator,
+    AlertTitle,
+    AlertCrossAbove, // "crossed above {}"
+    AlertCrossBelow,
+    AlertPercentChange,
+    AlertNewHigh,
+    Alerts,
+    AddAlert,
+    AlertLastFired, // "Last fired: {}"
+    AlertNever,
+    AlertEnabled,
+    AlertCooldown,
+    AlertHistory,
+    AlertKindCrossAbove, // Condition types in the alert settings
+    AlertKindCrossBelow,
+    AlertKindPercentChange,
+    AlertKindNewHigh,
+    IpcVersionMismatch,
+    Watchlists,
+    WatchlistPlaceholder,
+    Tickers,
+    TickerSpeed,
+    ComparePlaceholder,
+    Portfolio,
+    ShowPortfolio,
+    PositionPlaceholder,
+    CsvPathPlaceholder,
+    ImportButton,
+    ExportButton,
+    PortfolioSymbol,
+    PortfolioQuantity,
+    PortfolioPrice,
+    PortfolioValue,
+    PortfolioProfit,
+    PortfolioDay,
+    PortfolioAllocation,
+    PortfolioTotal, // "Total {}" with the currency
+    PortfolioImported, // "Imported {} positions"
+    PortfolioExported,
+    PortfolioTotalConverted, // "Total in {}" with the base currency
+    BaseCurrency,
+    BaseCurrencyPlaceholder,
+    ConvertPrices,
+    SessionOpen,
+    SessionClosed,
+    SessionPre,
+    SessionPost,
+    RestoreLayout,
 }
 
 pub fn get_text(lang: Language, id: TextId) -> &'static str {
@@ -86,6 +135,55 @@ pub fn get_text(lang: Language, id: TextId) -> &'static str {
             TextId::UpdateAvailableWithVersions => "Update available: v{} -> v{}",
             TextId::UpdateSuccess => "Update installed!",
             TextId::ShowPrereleases => "Show Beta Releases",
+            TextId::CachedData => "cached",
+            TextId::AddIndicator => "+ Indicator",
+            TextId::AlertTitle => "Price alert",
+            TextId::AlertCrossAbove => "crossed above {}",
+            TextId::AlertCrossBelow => "crossed below {}",
+            TextId::AlertPercentChange => "moved more than {}% today",
+            TextId::AlertNewHigh => "new 52-week high",
+            TextId::Alerts => "Alerts",
+            TextId::AddAlert => "+ Alert",
+            TextId::AlertLastFired => "Last fired: {}",
+            TextId::AlertNever => "never",
+            TextId::AlertEnabled => "Enabled",
+            TextId::AlertCooldown => "Cooldown (min)",
+            TextId::AlertHistory => "Recent alerts",
+            TextId::AlertKindCrossAbove => "Price >=",
+            TextId::AlertKindCrossBelow => "Price <=",
+            TextId::AlertKindPercentChange => "Day change %",
+            TextId::AlertKindNewHigh => "52w high",
+            TextId::IpcVersionMismatch => "The running widget is a different version. Please restart it.",
+            TextId::Watchlists => "Watchlists",
+            TextId::WatchlistPlaceholder => "Symbols, e.g. MSFT, AAPL, SAP.DE",
+            TextId::Tickers => "Tickers",
+            TextId::TickerSpeed => "Speed (px/s)",
+            TextId::ComparePlaceholder => "Compare with, e.g. MSFT, ^GSPC",
+            TextId::Portfolio => "Portfolio",
+            TextId::ShowPortfolio => "Show portfolio widget",
+            TextId::PositionPlaceholder => "Symbol, quantity, average cost, currency, e.g. AAPL 10 150 USD",
+            TextId::CsvPathPlaceholder => "CSV file, e.g. C:\\Users\\me\\portfolio.csv",
+            TextId::ImportButton => "Import",
+            TextId::ExportButton => "Export",
+            TextId::PortfolioSymbol => "Symbol",
+            TextId::PortfolioQuantity => "Qty",
+            TextId::PortfolioPrice => "Price",
+            TextId::PortfolioValue => "Value",
+            TextId::PortfolioProfit => "P/L",
+            TextId::PortfolioDay => "Day",
+            TextId::PortfolioAllocation => "Alloc.",
+            TextId::PortfolioTotal => "Total {}",
+            TextId::PortfolioImported => "Imported {} positions",
+            TextId::PortfolioTotalConverted => "Total in {}",
+            TextId::BaseCurrency => "Base Currency:",
+            TextId::BaseCurrencyPlaceholder => "e.g. EUR, empty = off",
+            TextId::ConvertPrices => "Show prices in base currency",
+            TextId::SessionOpen => "Open",
+            TextId::SessionClosed => "Closed",
+            TextId::SessionPre => "Pre-market",
+            TextId::SessionPost => "After hours",
+            TextId::RestoreLayout => "Restore previous layout",
+            TextId::PortfolioExported => "Exported {} positions",
         },
         Language::De => match id {
             TextId::SettingsTitle => "Einstellungen",
@@ -116,6 +214,55 @@ pub fn get_text(lang: Language, id: TextId) -> &'static str {
             TextId::UpdateAvailableWithVersions => "Update verfügbar: v{} -> v{}",
             TextId::UpdateSuccess => "Update installiert!",
             TextId::ShowPrereleases => "Beta-Versionen anzeigen",
+            TextId::CachedData => "zwischengespeichert",
+            TextId::AddIndicator => "+ Indikator",
+            TextId::AlertTitle => "Kursalarm",
+            TextId::AlertCrossAbove => "über {} gestiegen",
+            TextId::AlertCrossBelow => "unter {} gefallen",
+            TextId::AlertPercentChange => "heute um mehr als {}% bewegt",
+            TextId::AlertNewHigh => "neues 52-Wochen-Hoch",
+            TextId::Alerts => "Alarme",
+            TextId::AddAlert => "+ Alarm",
+            TextId::AlertLastFired => "Zuletzt ausgelöst: {}",
+            TextId::AlertNever => "nie",
+            TextId::AlertEnabled => "Aktiv",
+            TextId::AlertCooldown => "Pause (Min.)",
+            TextId::AlertHistory => "Letzte Alarme",
+            TextId::AlertKindCrossAbove => "Kurs >=",
+            TextId::AlertKindCrossBelow => "Kurs <=",
+            TextId::AlertKindPercentChange => "Tagesänderung %",
+            TextId::AlertKindNewHigh => "52W-Hoch",
+            TextId::IpcVersionMismatch => "Das laufende Widget hat eine andere Version. Bitte neu starten.",
+            TextId::Watchlists => "Watchlisten",
+            TextId::WatchlistPlaceholder => "Symbole, z.B. MSFT, AAPL, SAP.DE",
+            TextId::Tickers => "Laufbänder",
+            TextId::TickerSpeed => "Tempo (px/s)",
+            TextId::ComparePlaceholder => "Vergleichen mit, z.B. MSFT, ^GSPC",
+            TextId::Portfolio => "Depot",
+            TextId::ShowPortfolio => "Depot-Widget anzeigen",
+            TextId::PositionPlaceholder => "Symbol, Stückzahl, Einstandskurs, Währung, z.B. SAP.DE 10 120 EUR",
+            TextId::CsvPathPlaceholder => "CSV-Datei, z.B. C:\\Users\\ich\\depot.csv",
+            TextId::ImportButton => "Importieren",
+            TextId::ExportButton => "Exportieren",
+            TextId::PortfolioSymbol => "Symbol",
+            TextId::PortfolioQuantity => "Stk.",
+            TextId::PortfolioPrice => "Kurs",
+            TextId::PortfolioValue => "Wert",
+            TextId::PortfolioProfit => "G/V",
+            TextId::PortfolioDay => "Tag",
+            TextId::PortfolioAllocation => "Anteil",
+            TextId::PortfolioTotal => "Gesamt {}",
+            TextId::PortfolioImported => "{} Positionen importiert",
+            TextId::PortfolioTotalConverted => "Gesamt in {}",
+            TextId::BaseCurrency => "Basiswährung:",
+            TextId::BaseCurrencyPlaceholder => "z.B. EUR, leer = aus",
+            TextId::ConvertPrices => "Kurse in Basiswährung anzeigen",
+            TextId::SessionOpen => "Geöffnet",
+            TextId::SessionClosed => "Geschlossen",
+            TextId::SessionPre => "Vorbörse",
+            TextId::SessionPost => "Nachbörse",
+            TextId::RestoreLayout => "Vorheriges Layout wiederherstellen",
+            TextId::PortfolioExported => "{} Positionen exportiert",
         },
     }
 }
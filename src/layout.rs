@@ -0,0 +1,94 @@
+use std::ops::Range;
+
+/// Requested height of one pane in a chart window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaneHeight {
+    /// Exact height in pixels (e.g. the configured volume pane height)
+    Fixed(u32),
+    /// Share of the space left over after fixed panes, relative to the other flexible panes
+    Flex(u32),
+}
+
+/// Splits the window height into vertically stacked panes.
+///
+/// `header` pixels at the top are reserved for the symbol/price text and `axis` pixels are added
+/// below the bottom pane for the shared x axis labels. Fixed panes are shrunk proportionally if they
+/// don't fit, flexible panes share whatever remains by weight.
+/// Returns the y range of every pane (the last one including the axis area), in the given order.
+/// A window too small for header and axis gets empty panes, no range reaches past `total`.
+pub fn split_panes(total: u32, header: u32, axis: u32, panes: &[PaneHeight]) -> Vec<Range<u32>> {
+    if panes.is_empty() {
+        return Vec::new();
+    }
+
+    let available = total.saturating_sub(header + axis);
+    let fixed_total: u32 = panes.iter().map(|p| if let PaneHeight::Fixed(h) = p { *h } else { 0 }).sum();
+    let flex_total: u32 = panes.iter().map(|p| if let PaneHeight::Flex(w) = p { *w } else { 0 }).sum();
+
+    // Fixed panes may use at most half the space when flexible panes need room as well
+    let fixed_budget = if flex_total > 0 { fixed_total.min(available / 2) } else { fixed_total.min(available) };
+    let flex_space = available - fixed_budget;
+
+    let mut heights: Vec<u32> = panes
+        .iter()
+        .map(|p| match *p {
+            PaneHeight::Fixed(h) if fixed_total > 0 => (h as u64 * fixed_budget as u64 / fixed_total as u64) as u32,
+            PaneHeight::Fixed(_) => 0,
+            PaneHeight::Flex(w) if flex_total > 0 => (w as u64 * flex_space as u64 / flex_total as u64) as u32,
+            PaneHeight::Flex(_) => 0,
+        })
+        .collect();
+
+    // Rounding leftovers go to the first pane (the price chart)
+    let used: u32 = heights.iter().sum();
+    heights[0] += available - used.min(available);
+
+    let last = heights.len() - 1;
+    heights[last] += axis;
+
+    let mut top = header;
+    heights
+        .into_iter()
+        .map(|h| {
+            let range = top.min(total)..(top + h).min(total);
+            top += h;
+            range
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PaneHeight::{Fixed, Flex};
+
+    #[test]
+    fn flexible_panes_share_what_fixed_ones_leave() {
+        // 350 px between header and axis, the volume pane keeps its 60
+        let ranges = split_panes(400, 30, 20, &[Flex(3), Fixed(60), Flex(1)]);
+        assert_eq!(ranges, vec![30..248, 248..308, 308..400]);
+    }
+
+    #[test]
+    fn fixed_panes_over_budget_shrink_proportionally() {
+        // Half of the 200 px is left to the flexible pane
+        let ranges = split_panes(250, 30, 20, &[Flex(1), Fixed(150), Fixed(50)]);
+        assert_eq!(ranges, vec![30..130, 130..205, 205..250]);
+
+        // Without flexible panes they may use all of it
+        assert_eq!(split_panes(250, 30, 20, &[Fixed(300)]), vec![30..250]);
+    }
+
+    #[test]
+    fn rounding_leftovers_go_to_the_first_pane() {
+        let ranges = split_panes(150, 30, 20, &[Flex(1), Flex(1), Flex(1)]);
+        assert_eq!(ranges, vec![30..64, 64..97, 97..150]);
+    }
+
+    #[test]
+    fn too_small_windows_get_empty_panes() {
+        let ranges = split_panes(40, 30, 20, &[Flex(1), Fixed(60)]);
+        assert_eq!(ranges, vec![30..30, 30..40]);
+        assert!(split_panes(400, 30, 20, &[]).is_empty());
+    }
+}
@@ -10,39 +10,53 @@ mod config;
 mod ipc;
 mod settings_iced;
 mod icons;
+mod provider;
+mod fetcher;
+mod cache;
+mod indicators;
+mod layout;
+mod alerts;
+mod platform;
+mod render;
+mod watchlist;
+mod ticker;
+mod portfolio;
+mod currency;
+mod market_hours;
+mod ctl;
+mod http_api;
 
 use winit::application::ApplicationHandler;
 use winit::event::WindowEvent;
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
 use winit::window::WindowId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use tray_icon::{TrayIcon, TrayIconBuilder, Icon};
 use tray_icon::menu::{Menu, MenuItem, MenuEvent}; 
 use common::{UserEvent, WindowHandler, UpdateStatus};
 use chart::ChartWindow;
-use winreg::{enums::HKEY_CURRENT_USER, RegKey};
-use std::path::Path;
-use config::AppConfig;
+use watchlist::WatchlistWindow;
+use ticker::TickerWindow;
+use portfolio::PortfolioWindow;
+use currency::FxRates;
+use fetcher::FetchService;
+use provider::QuoteSeries;
+use config::{AppConfig, WidgetConfig};
 use language::{TextId, get_text};
-use std::os::windows::process::CommandExt;
 
 
 
-// TODO: might want to delete as well
-fn register_aumid(aum_id: &str, display_name: &str, icon_path: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
-    // HKCU\SOFTWARE\Classes\AppUserModelId\desktop-widget-rs
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey(format!(r"SOFTWARE\Classes\AppUserModelId\{}", aum_id))?;
-
-    key.set_value("DisplayName", &display_name)?;
-
-    if let Some(path) = icon_path {
-        key.set_value("IconUri", &path.to_string_lossy().to_string())?;
-    } else {
-        let _ = key.delete_value("IconUri");
+// `--config` of this process, passed on to the instances it starts
+fn config_args() -> Vec<String> {
+    match AppConfig::path_override() {
+        Some(path) => vec!["--config".to_string(), path.to_string_lossy().to_string()],
+        None => Vec::new(),
     }
+}
 
-    Ok(())
+fn auto_launch() -> auto_launch::AutoLaunch {
+    let args = config_args();
+    auto_launch::AutoLaunch::new("desktop-widget-rs", std::env::current_exe().unwrap().to_str().unwrap(), &args)
 }
 
 struct App {
@@ -52,6 +66,8 @@ struct App {
     tray_menu: Option<Menu>,
     // Store IDs to manage settings list
     chart_ids: Vec<(WindowId, String, bool, String)>, 
+    widget_ids: Vec<(WindowId, bool)>, // Watchlist, ticker and portfolio windows and their lock state
+    portfolio_id: Option<WindowId>,
     settings_id: Option<WindowId>,
     settings_item: Option<MenuItem>,
     quit_item: Option<MenuItem>,
@@ -60,65 +76,379 @@ struct App {
     last_save_time: std::time::Instant,
     last_auto_refresh: std::time::Instant,
     last_update_check: std::time::Instant,
-    ipc_tx: Option<tokio::sync::mpsc::Sender<crate::ipc::IpcMessage>>,
+    ipc_clients: HashMap<u64, (String, crate::ipc::FrameSender)>, // Connection id -> (client name, outgoing frames)
     pending_charts: HashMap<WindowId, String>,
+    pending_requests: HashMap<WindowId, (u64, u64)>, // AddChart (connection, request) answered when the pending chart resolves
+    fetcher: FetchService,
+    alert_history: VecDeque<alerts::AlertEvent>, // Newest first
+    api_state: Option<http_api::SharedApiState>, // Set when the HTTP API is enabled
+    fx: Option<FxRates>, // Set when a base currency is configured
+    currencies: HashSet<String>, // Every currency a quote arrived in, their rates are kept up to date
+    fetched_at: HashMap<(String, String), chrono::DateTime<chrono::Local>>, // (Symbol, timeframe) -> last fetch of this session
 }
 
+// FX pairs are fetched like any symbol, the latest intraday quote is the rate
+const FX_TIMEFRAME: &str = "1D";
+
 impl App {
-    fn refresh_settings_window(&mut self) {
-        // Collect chart data
+    // Currencies needing a rate into the base currency, with the symbol of their FX pair
+    fn fx_pairs(&self) -> Vec<(String, String)> {
+        let Some(fx) = &self.fx else { return Vec::new() };
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for code in self.currencies.iter().chain(self.config.positions.iter().map(|p| &p.currency)) {
+            if let Some(iso) = fx.required(code) && !pairs.iter().any(|(c, _)| *c == iso) {
+                let symbol = currency::fx_symbol(&iso, fx.base());
+                pairs.push((iso, symbol));
+            }
+        }
+        pairs
+    }
+
+    /// Starts over with the configured base currency: rates from disk right away, fresh ones from the network.
+    fn reset_fx_rates(&mut self) {
+        self.fx = self.config.base_currency.as_deref().map(FxRates::new);
+        for (iso, symbol) in self.fx_pairs() {
+            if let Some(fx) = &mut self.fx
+                && let Some(last) = self.fetcher.cached(&symbol, FX_TIMEFRAME, None).and_then(|(series, _)| series.candles.last().cloned()) {
+                fx.set_rate(&iso, last.close);
+            }
+            self.request_if_outdated(&symbol, FX_TIMEFRAME);
+        }
+        self.broadcast_fx_rates();
+    }
+
+    fn request_missing_fx_rates(&self) {
+        for (iso, symbol) in self.fx_pairs() {
+            if self.fx.as_ref().is_some_and(|fx| fx.rate(&iso).is_none()) {
+                self.fetcher.request(&symbol, FX_TIMEFRAME, None);
+            }
+        }
+    }
+
+    fn broadcast_fx_rates(&mut self) {
+        for handler in self.windows.values_mut() {
+            handler.set_fx_rates(self.fx.as_ref(), self.config.convert_prices);
+        }
+    }
+
+    fn chart_data(&self) -> Vec<(WindowId, crate::ipc::ChartData)> {
         let mut charts_data = Vec::new();
         for (id, symbol, locked, timeframe) in &self.chart_ids {
             // Check if window exists (it should)
-            if self.windows.contains_key(id) {
-                 charts_data.push(crate::ipc::ChartData {
+            if let Some(handler) = self.windows.get(id) {
+                 let chart_config = match handler.get_config() {
+                     Some(WidgetConfig::Chart(c)) => Some(c),
+                     _ => None,
+                 };
+                 charts_data.push((*id, crate::ipc::ChartData {
                      id: format!("{:?}", id),
                      symbol: symbol.clone(),
                      timeframe: timeframe.clone(),
                      locked: *locked,
-                 });
+                     style: chart_config.as_ref().map(|c| c.style).unwrap_or_default(),
+                     indicators: chart_config.as_ref().map(|c| c.indicators.clone()).unwrap_or_default(),
+                     compare_symbols: chart_config.map(|c| c.compare_symbols).unwrap_or_default(),
+                 }));
             }
         }
+        charts_data
+    }
+
+    fn watchlist_data(&self) -> Vec<crate::ipc::WatchlistData> {
+        self.widget_ids.iter()
+            .filter_map(|(id, locked)| {
+                let handler = self.windows.get(id)?;
+                let Some(WidgetConfig::Watchlist(_)) = handler.get_config() else { return None };
+                Some(crate::ipc::WatchlistData {
+                    id: format!("{:?}", id),
+                    symbols: handler.symbols(),
+                    locked: *locked,
+                })
+            })
+            .collect()
+    }
+
+    fn ticker_data(&self) -> Vec<crate::ipc::TickerData> {
+        self.widget_ids.iter()
+            .filter_map(|(id, locked)| {
+                let Some(WidgetConfig::Ticker(config)) = self.windows.get(id)?.get_config() else { return None };
+                Some(crate::ipc::TickerData {
+                    id: format!("{:?}", id),
+                    symbols: config.symbols,
+                    speed: config.speed,
+                    locked: *locked,
+                })
+            })
+            .collect()
+    }
+
+    fn refresh_settings_window(&mut self) {
+        let charts_data = self.chart_data().into_iter().map(|(_, c)| c).collect();
         
-        let args: &[&str] = &[];
-        let auto_start = auto_launch::AutoLaunch::new("desktop-widget-rs", std::env::current_exe().unwrap().to_str().unwrap(), args).is_enabled().unwrap_or(false);
+        let auto_start = auto_launch().is_enabled().unwrap_or(false);
 
         let config_data = crate::ipc::ConfigData {
             language: self.config.language.as_str().to_string(), // Ensure Language has as_str
             update_interval: self.config.update_interval_minutes,
             auto_start,
             use_prereleases: self.config.use_prereleases,
+            show_portfolio: self.portfolio_id.is_some(),
+            base_currency: self.config.base_currency.clone(),
+            convert_prices: self.config.convert_prices,
         };
         
         // Send to IPC if connected
-        if let Some(tx) = &self.ipc_tx {
-            let msg_charts = crate::ipc::IpcMessage::Charts(charts_data);
-            let msg_config = crate::ipc::IpcMessage::Config(config_data);
-            let tx1 = tx.clone();
-            let tx2 = tx.clone();
-            // Send both
-            let _ = tx1.try_send(msg_charts);
-            let _ = tx2.try_send(msg_config);
+        self.send_ipc(crate::ipc::IpcMessage::Charts(charts_data));
+        self.send_ipc(crate::ipc::IpcMessage::Watchlists(self.watchlist_data()));
+        self.send_ipc(crate::ipc::IpcMessage::Tickers(self.ticker_data()));
+        self.send_ipc(crate::ipc::IpcMessage::Positions(self.config.positions.clone()));
+        self.send_ipc(crate::ipc::IpcMessage::Config(config_data));
+        self.publish_api_state();
+    }
+
+    fn refresh_alerts_window(&self) {
+        self.send_ipc(crate::ipc::IpcMessage::Alerts(self.config.alerts.clone()));
+        self.send_ipc(crate::ipc::IpcMessage::AlertHistory(self.alert_history.iter().cloned().collect()));
+        self.publish_api_state();
+    }
+
+    // Hand the current charts, quotes and alerts to the HTTP API, if it's running
+    fn publish_api_state(&self) {
+        let Some(state) = &self.api_state else { return };
+        let quotes = self.chart_ids.iter()
+            .filter_map(|(id, _, _, _)| self.windows.get(id)?.latest_quote())
+            .collect();
+        let mut state = state.lock().unwrap();
+        state.charts = self.chart_data();
+        state.quotes = quotes;
+        state.alerts = self.config.alerts.clone();
+        state.alert_history = self.alert_history.iter().cloned().collect();
+    }
+
+    // Push a state update to all connected clients
+    fn send_ipc(&self, msg: crate::ipc::IpcMessage) {
+        for (client, tx) in self.ipc_clients.values() {
+            if !tx.push(msg.clone()) {
+                log::warn!("IPC client '{}' is not keeping up, dropped an event", client);
+            }
+        }
+    }
+
+    // Answer an IPC request on the connection it came from
+    fn respond(&self, conn_id: u64, request_id: u64, result: Result<(), String>) {
+        if let Some((_, tx)) = self.ipc_clients.get(&conn_id) {
+            tx.respond(request_id, result);
+        }
+    }
+
+    // Whether the settings process is connected (ctl clients come and go and don't count)
+    fn settings_connected(&self) -> bool {
+        self.ipc_clients.values().any(|(name, _)| name == crate::ipc::SETTINGS_CLIENT)
+    }
+
+    /// Opens the widgets of `self.config`, on startup and when a layout is restored.
+    fn open_widgets(&mut self, event_loop: &ActiveEventLoop) {
+        for chart_cfg in &self.config.charts {
+            let chart = ChartWindow::new(event_loop, self.fetcher.clone(), chart_cfg.symbol.clone(), Some(chart_cfg.clone()), self.config.language);
+            let id = chart.window_id();
+            self.windows.insert(id, Box::new(chart));
+            let tf = chart_cfg.timeframe.clone().unwrap_or("1M".to_string());
+            self.chart_ids.push((id, chart_cfg.symbol.clone(), true, tf));
         }
+        for watchlist_cfg in &self.config.watchlists {
+            let watchlist = WatchlistWindow::new(event_loop, self.fetcher.clone(), watchlist_cfg.symbols.clone(), Some(watchlist_cfg.clone()));
+            let id = watchlist.window_id();
+            self.windows.insert(id, Box::new(watchlist));
+            self.widget_ids.push((id, true));
+        }
+        for ticker_cfg in &self.config.tickers {
+            let ticker = TickerWindow::new(event_loop, self.fetcher.clone(), ticker_cfg.symbols.clone(), Some(ticker_cfg.clone()));
+            let id = ticker.window_id();
+            self.windows.insert(id, Box::new(ticker));
+            self.widget_ids.push((id, true));
+        }
+        if let Some(portfolio_cfg) = &self.config.portfolio {
+            let portfolio = PortfolioWindow::new(event_loop, self.fetcher.clone(), self.config.positions.clone(), self.config.language, Some(portfolio_cfg.clone()));
+            let id = portfolio.window_id();
+            self.windows.insert(id, Box::new(portfolio));
+            self.widget_ids.push((id, true));
+            self.portfolio_id = Some(id);
+        }
+        log::info!("Restored {} charts, {} watchlists and {} tickers from config", self.config.charts.len(), self.config.watchlists.len(), self.config.tickers.len());
+    }
+
+    // Watchlists, tickers and the portfolio added from the settings start unlocked so they can be placed right away
+    fn add_widget(&mut self, mut handler: Box<dyn WindowHandler>) -> WindowId {
+        let id = handler.window_id();
+        handler.set_locked(false);
+        handler.set_fx_rates(self.fx.as_ref(), self.config.convert_prices);
+        self.windows.insert(id, handler);
+        self.widget_ids.push((id, false));
+        id
+    }
+
+    /// Replaces all widgets with those of the previous layout backup.
+    fn restore_previous_layout(&mut self, event_loop: &ActiveEventLoop) -> Result<(), String> {
+        // The restore backs up config.json first, which has to hold the layout being replaced
+        self.save_config();
+        let restored = AppConfig::restore_previous()?;
+
+        for (id, _, _, _) in self.chart_ids.drain(..) {
+            self.windows.remove(&id);
+        }
+        for (id, _) in self.widget_ids.drain(..) {
+            self.windows.remove(&id);
+        }
+        self.portfolio_id = None;
+        // Charts still waiting for their first data aren't part of either layout
+        for (id, _) in self.pending_charts.drain() {
+            self.windows.remove(&id);
+        }
+        for (_, (conn_id, request_id)) in std::mem::take(&mut self.pending_requests) {
+            self.respond(conn_id, request_id, Err("The chart was removed when the previous layout was restored".to_string()));
+        }
+
+        self.config.charts = restored.charts;
+        self.config.watchlists = restored.watchlists;
+        self.config.tickers = restored.tickers;
+        self.config.portfolio = restored.portfolio;
+        self.open_widgets(event_loop);
+        // Written as restored: `save_config` would leave out the widgets that are still loading
+        self.config.save();
+        self.broadcast_fx_rates();
+        self.refresh_settings_window();
+        Ok(())
+    }
+
+    fn quit(&mut self, event_loop: &ActiveEventLoop) {
+        self.save_config();
+        if !self.ipc_clients.is_empty() {
+            log::info!("Sending shutdown signal to IPC");
+            self.send_ipc(crate::ipc::IpcMessage::Shutdown);
+        }
+        event_loop.exit();
+    }
+
+    // Resolve the chart, watchlist or ticker id string used over IPC
+    fn find_widget(&self, id_str: &str) -> Result<WindowId, String> {
+        self.chart_ids.iter()
+            .map(|(wid, _, _, _)| *wid)
+            .chain(self.widget_ids.iter().map(|(wid, _)| *wid))
+            .find(|wid| format!("{:?}", wid) == id_str)
+            .ok_or_else(|| format!("Unknown widget id {}", id_str))
+    }
+
+    // Apply an edit to one alert rule, then persist and sync the settings
+    fn update_alert(&mut self, id: u64, edit: impl FnOnce(&mut alerts::AlertRule)) -> Result<(), String> {
+        let rule = self.config.alerts.iter_mut().find(|r| r.id == id).ok_or_else(|| format!("Unknown alert id {}", id))?;
+        edit(rule);
+        self.save_config();
+        self.refresh_alerts_window();
+        Ok(())
     }
 
     fn save_config(&self) {
         let mut charts = Vec::new();
+        let mut watchlists = Vec::new();
+        let mut tickers = Vec::new();
+        let mut portfolio = None;
         for handler in self.windows.values() {
             if handler.has_data() {
-                if let Some(config) = handler.get_config() {
-                    charts.push(config);
+                match handler.get_config() {
+                    Some(WidgetConfig::Chart(config)) => charts.push(config),
+                    Some(WidgetConfig::Watchlist(config)) => watchlists.push(config),
+                    Some(WidgetConfig::Ticker(config)) => tickers.push(config),
+                    Some(WidgetConfig::Portfolio(config)) => portfolio = Some(config),
+                    None => {},
                 }
             }
         }
         let app_config = AppConfig { 
+            version: config::CONFIG_VERSION,
             charts,
+            watchlists,
+            tickers,
+            positions: self.config.positions.clone(),
+            portfolio,
             update_interval_minutes: self.config.update_interval_minutes,
             language: self.config.language,
             use_prereleases: self.config.use_prereleases,
+            base_currency: self.config.base_currency.clone(),
+            convert_prices: self.config.convert_prices,
+            alerts: self.config.alerts.clone(),
+            http_api: self.config.http_api.clone(),
         };
         app_config.save();
     }
+
+    /// Fetches data the App itself needs (alerts, FX rates) by the rule the widgets follow, see
+    /// `market_hours::is_outdated`. Data of earlier sessions only exists on disk.
+    fn request_if_outdated(&self, symbol: &str, timeframe: &str) {
+        let outdated = match self.fetched_at.get(&(symbol.to_string(), timeframe.to_string())) {
+            Some(fetched_at) => market_hours::is_outdated(symbol, *fetched_at, false),
+            None => self.fetcher.cached(symbol, timeframe, None)
+                .is_none_or(|(_, fetched_at)| market_hours::is_outdated(symbol, fetched_at, true)),
+        };
+        if outdated {
+            self.fetcher.request(symbol, timeframe, None);
+        }
+    }
+
+    // Symbol and timeframe a rule is evaluated on, None when a chart fetches the symbol anyway
+    fn alert_data<'a>(&self, rule: &'a alerts::AlertRule) -> Option<(&'a str, &'static str)> {
+        match rule.condition.timeframe() {
+            Some(tf) => Some((rule.symbol.as_str(), tf)),
+            None if self.chart_ids.iter().any(|(_, s, _, _)| *s == rule.symbol) => None,
+            None => Some((rule.symbol.as_str(), "1D")),
+        }
+    }
+
+    /// Requests the data alert rules are evaluated on, for symbols/timeframes no chart is fetching anyway.
+    fn refresh_alert_data(&self) {
+        for rule in self.config.alerts.iter().filter(|r| r.enabled) {
+            if let Some((symbol, timeframe)) = self.alert_data(rule) {
+                self.request_if_outdated(symbol, timeframe);
+            }
+        }
+    }
+
+    // A new or changed rule is evaluated on fresh data right away
+    fn fetch_alert_data(&self, id: u64) {
+        if let Some((symbol, timeframe)) = self.config.alerts.iter().find(|r| r.id == id).and_then(|rule| self.alert_data(rule)) {
+            self.fetcher.request(symbol, timeframe, None);
+        }
+    }
+
+    fn check_alerts(&mut self, symbol: &str, timeframe: &str, series: &QuoteSeries) {
+        if !self.config.alerts.iter().any(|r| r.symbol == symbol) {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp();
+        let state = |rules: &[alerts::AlertRule]| rules.iter().map(|r| (r.armed, r.last_fired)).collect::<Vec<_>>();
+        let before = state(&self.config.alerts);
+        let fired = alerts::evaluate(&mut self.config.alerts, symbol, timeframe, series, now);
+        // Rules may have been re-armed as well, persist their state once it changed
+        if state(&self.config.alerts) != before {
+            self.dirty = true;
+        }
+
+        let lang = self.config.language;
+        let mut fired_any = false;
+        for event in fired {
+            let message = event.message(lang);
+            log::info!("Alert fired: {}", message);
+            if let Err(e) = platform::show_toast(AUM_ID, get_text(lang, TextId::AlertTitle), &message, self.proxy.clone()) {
+                log::error!("Failed to show alert notification: {}", e);
+            }
+            self.alert_history.push_front(event);
+            self.alert_history.truncate(alerts::MAX_HISTORY);
+            fired_any = true;
+        }
+
+        if fired_any {
+            self.refresh_alerts_window();
+        }
+    }
 }
 
 impl ApplicationHandler<UserEvent> for App {
@@ -128,6 +458,12 @@ impl ApplicationHandler<UserEvent> for App {
              // Load config here so we have the correct language for the tray menu
              self.config = AppConfig::load();
 
+             if self.config.http_api.enabled {
+                 let state = http_api::SharedApiState::default();
+                 http_api::spawn(self.config.http_api.port, state.clone(), self.proxy.clone());
+                 self.api_state = Some(state);
+             }
+
              let tray_menu = Menu::new();
              let settings_text = get_text(self.config.language, TextId::SettingsMenu);
              let quit_text = get_text(self.config.language, TextId::Quit);
@@ -162,22 +498,17 @@ impl ApplicationHandler<UserEvent> for App {
 
         // Open initial charts from config
         if self.windows.is_empty() {
-            if self.config.charts.is_empty() {
-                 let chart = ChartWindow::new(event_loop, self.proxy.clone(), "AAPL".to_string(), None, self.config.language);
+            if self.config.charts.is_empty() && self.config.watchlists.is_empty() && self.config.tickers.is_empty() && self.config.portfolio.is_none() {
+                 let chart = ChartWindow::new(event_loop, self.fetcher.clone(), "AAPL".to_string(), None, self.config.language);
                  let id = chart.window_id();
                  self.windows.insert(id, Box::new(chart));
                  self.chart_ids.push((id, "AAPL".to_string(), true, "1M".to_string()));
                  log::info!("Created default initial chart for AAPL");
             } else {
-                 for chart_cfg in &self.config.charts {
-                     let chart = ChartWindow::new(event_loop, self.proxy.clone(), chart_cfg.symbol.clone(), Some(chart_cfg.clone()), self.config.language);
-                     let id = chart.window_id();
-                     self.windows.insert(id, Box::new(chart));
-                     let tf = chart_cfg.timeframe.clone().unwrap_or("1M".to_string());
-                     self.chart_ids.push((id, chart_cfg.symbol.clone(), true, tf));
-                 }
-                 log::info!("Restored {} charts from config", self.config.charts.len());
+                 self.open_widgets(event_loop);
             }
+            self.refresh_alert_data();
+            self.reset_fx_rates();
          }
     }
 
@@ -185,7 +516,12 @@ impl ApplicationHandler<UserEvent> for App {
         if let WindowEvent::CloseRequested = event {
             self.windows.remove(&window_id);
             self.chart_ids.retain(|(id, _, _, _)| *id != window_id);
+            self.widget_ids.retain(|(id, _)| *id != window_id);
+            if self.portfolio_id == Some(window_id) {
+                self.portfolio_id = None;
+            }
             self.pending_charts.remove(&window_id);
+            self.pending_requests.remove(&window_id);
             
             if Some(window_id) == self.settings_id {
                 self.settings_id = None;
@@ -193,6 +529,9 @@ impl ApplicationHandler<UserEvent> for App {
                 for entry in &mut self.chart_ids {
                     entry.2 = true; // Set locked to true
                 }
+                for entry in &mut self.widget_ids {
+                    entry.1 = true;
+                }
                 // We need to clone the IDs to iterate and mutate windows
                 let ids: Vec<WindowId> = self.windows.keys().cloned().collect(); 
                 for id in ids {
@@ -237,6 +576,10 @@ impl ApplicationHandler<UserEvent> for App {
              for handler in self.windows.values_mut() {
                  handler.refresh();
              }
+             self.refresh_alert_data();
+             for (_, symbol) in self.fx_pairs() {
+                 self.request_if_outdated(&symbol, FX_TIMEFRAME);
+             }
              self.last_auto_refresh = std::time::Instant::now();
          }
 
@@ -262,11 +605,18 @@ impl ApplicationHandler<UserEvent> for App {
                  next_wake = next_save;
              }
          }
+
+         // Animated widgets (tickers) ask for their next frame
+         for handler in self.windows.values() {
+             if let Some(wake) = handler.next_wake() && wake < next_wake {
+                 next_wake = wake;
+             }
+         }
          
          event_loop.set_control_flow(ControlFlow::WaitUntil(next_wake));
 
          // If we have pending debounces, we might want to wake up sooner.
-         // Ideally, handlers should provide "next_wake" hint, but for now we rely on user input waking us 
+         // Chart debounces don't report a "next_wake" hint yet, so we rely on user input waking us
          // or the next refresh/save interval. To support debounce timeout (e.g. 500ms), we should cap wait time.
          if next_wake > std::time::Instant::now() + std::time::Duration::from_millis(100) {
              event_loop.set_control_flow(ControlFlow::WaitUntil(std::time::Instant::now() + std::time::Duration::from_millis(100)));
@@ -276,6 +626,8 @@ impl ApplicationHandler<UserEvent> for App {
 
          use tray_icon::{TrayIconEvent, MouseButton, MouseButtonState};
 
+         platform::pump_events();
+
          while let Ok(event) = MenuEvent::receiver().try_recv() {
              let id = event.id;
              if let Some(item) = &self.settings_item {
@@ -286,14 +638,7 @@ impl ApplicationHandler<UserEvent> for App {
              }
              if let Some(item) = &self.quit_item {
                  if id == item.id() {
-                     self.save_config(); 
-                    if let Some(tx) = &self.ipc_tx {
-                        log::info!("Sending shutdown signal to IPC");
-                        let _ = tx.try_send(crate::ipc::IpcMessage::Shutdown);
-                        // Give a tiny yield to allow tokio scheduling (best effort)
-                        // Actually we can't await here easily as we are in event loop handler.
-                    }
-                    event_loop.exit();
+                     self.quit(event_loop);
                  }
              }
          }
@@ -308,19 +653,41 @@ impl ApplicationHandler<UserEvent> for App {
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
         log::info!("Application exiting");
         self.save_config();
+        self.fetcher.save_cache();
     }
     
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
         match event {
-             UserEvent::DataLoaded(symbol, quotes, currency) => {
+             UserEvent::DataLoaded(symbol, timeframe, series) => {
+                 self.fetched_at.insert((symbol.clone(), timeframe.clone()), chrono::Local::now());
+                 if let Some((from, to)) = currency::parse_fx_symbol(&symbol)
+                     && let Some(fx) = self.fx.as_mut().filter(|fx| fx.base() == to && timeframe == FX_TIMEFRAME)
+                     && let Some(last) = series.candles.last() {
+                     fx.set_rate(from, last.close);
+                     self.broadcast_fx_rates();
+                 }
+                 // First quote in this currency, its rate is needed from now on
+                 if self.currencies.insert(series.currency.clone()) {
+                     self.request_missing_fx_rates();
+                 }
+
+                 // Charts showing the symbol, either as their own or as one they compare against
                  let targets: Vec<WindowId> = self.chart_ids.iter()
-                     .filter(|(_, s, _, _)| *s == symbol)
                      .map(|(id, _, _, _)| *id)
+                     .filter(|id| self.windows.get(id).is_some_and(|h| h.symbols().contains(&symbol)))
                      .collect();
                 
                  for id in &targets {
                      if let Some(h) = self.windows.get_mut(&id) {
-                         h.update_data(quotes.clone(), currency.clone());
+                         h.update_data(&symbol, &timeframe, series.clone());
+                     }
+                 }
+
+                 for (id, _) in &self.widget_ids {
+                     if let Some(h) = self.windows.get_mut(id) && h.symbols().contains(&symbol) {
+                         h.update_data(&symbol, &timeframe, series.clone());
+                         // A new watchlist or ticker is only persisted once it has data
+                         self.dirty = true;
                      }
                  }
                  
@@ -334,15 +701,20 @@ impl ApplicationHandler<UserEvent> for App {
 
                  for id in pending_ids {
                      if let Some(h) = self.windows.get_mut(&id) {
-                         h.update_data(quotes.clone(), currency.clone());
+                         h.update_data(&symbol, &timeframe, series.clone());
                          if h.has_data() {
                              self.chart_ids.push((id, symbol.clone(), true, "1M".to_string()));
                              self.pending_charts.remove(&id);
+                             if let Some((conn_id, request_id)) = self.pending_requests.remove(&id) {
+                                 self.respond(conn_id, request_id, Ok(()));
+                             }
                              promoted = true;
                          }
                      }
                  }
 
+                 self.check_alerts(&symbol, &timeframe, &series);
+
                  if !targets.is_empty() || promoted {
                      self.refresh_settings_window();
                      self.save_config();
@@ -386,26 +758,114 @@ impl ApplicationHandler<UserEvent> for App {
                          self.pending_charts.remove(&id);
                          // Don't refresh settings (wasn't in list)
                          
-                         // Send IPC Error
-                         if let Some(tx) = &self.ipc_tx {
-                             let _ = tx.try_send(crate::ipc::IpcMessage::Error(localized_err));
+                         // Fail the IPC request that added it
+                         if let Some((conn_id, request_id)) = self.pending_requests.remove(&id) {
+                             self.respond(conn_id, request_id, Err(localized_err));
                          }
                          log::warn!("Removed pending chart {} due to error", symbol);
                      }
                  }
+
+                 // Watchlists and tickers none of whose symbols could be loaded are removed the same way
+                 let failed: Vec<WindowId> = self.widget_ids.iter()
+                     .map(|(id, _)| *id)
+                     .filter(|id| self.windows.get_mut(id).is_some_and(|h| h.fetch_failed(&symbol)))
+                     .collect();
+                 if !failed.is_empty() {
+                     for id in &failed {
+                         self.windows.remove(id);
+                     }
+                     self.widget_ids.retain(|(id, _)| !failed.contains(id));
+                     log::warn!("Removed {} widgets without data due to error for {}", failed.len(), symbol);
+                     self.refresh_settings_window();
+                     self.save_config();
+                 }
              },
-             UserEvent::AddChart(symbol) => {
-                 let chart = ChartWindow::new(event_loop, self.proxy.clone(), symbol.clone(), None, self.config.language);
+             UserEvent::AddChart(symbol, request) => {
+                 let chart = ChartWindow::new(event_loop, self.fetcher.clone(), symbol.clone(), None, self.config.language);
                  let id = chart.window_id();
                  self.windows.insert(id, Box::new(chart));
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_fx_rates(self.fx.as_ref(), self.config.convert_prices);
+                 }
                  // Don't add to chart_ids yet, mark as pending
                  self.pending_charts.insert(id, symbol.clone());
+                 if let Some(request) = request {
+                     self.pending_requests.insert(id, request);
+                 }
                  log::info!("Requested new chart for {}", symbol);
                  // self.refresh_settings_window(); // Only refresh when data is loaded
              },
+             UserEvent::AddWatchlist(symbols) => {
+                 let watchlist = WatchlistWindow::new(event_loop, self.fetcher.clone(), symbols.clone(), None);
+                 self.add_widget(Box::new(watchlist));
+                 log::info!("Added watchlist for {}", symbols.join(", "));
+                 self.refresh_settings_window();
+             },
+             UserEvent::AddTicker(symbols) => {
+                 let ticker = TickerWindow::new(event_loop, self.fetcher.clone(), symbols.clone(), None);
+                 self.add_widget(Box::new(ticker));
+                 log::info!("Added ticker for {}", symbols.join(", "));
+                 self.refresh_settings_window();
+             },
+             UserEvent::WidgetSymbols(id, symbols) => {
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_symbols(symbols);
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::TickerSpeed(id, speed) => {
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_speed(speed);
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
              UserEvent::DeleteChart(id) => {
                  self.windows.remove(&id);
                  self.chart_ids.retain(|(wid, _, _, _)| *wid != id);
+                 self.widget_ids.retain(|(wid, _)| *wid != id);
+                 if self.portfolio_id == Some(id) {
+                     self.portfolio_id = None;
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::Positions(positions) => {
+                 self.config.positions = positions.clone();
+                 if let Some(handler) = self.portfolio_id.and_then(|id| self.windows.get_mut(&id)) {
+                     handler.set_positions(positions);
+                 }
+                 self.request_missing_fx_rates();
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::ShowPortfolio(show) => {
+                 match (show, self.portfolio_id) {
+                     (true, None) => {
+                         let portfolio = PortfolioWindow::new(event_loop, self.fetcher.clone(), self.config.positions.clone(), self.config.language, None);
+                         self.portfolio_id = Some(self.add_widget(Box::new(portfolio)));
+                     },
+                     (false, Some(id)) => {
+                         self.windows.remove(&id);
+                         self.widget_ids.retain(|(wid, _)| *wid != id);
+                         self.portfolio_id = None;
+                     },
+                     _ => {},
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::BaseCurrency(base) => {
+                 self.config.base_currency = base;
+                 self.reset_fx_rates();
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::ConvertPrices(enabled) => {
+                 self.config.convert_prices = enabled;
+                 self.broadcast_fx_rates();
                  self.refresh_settings_window();
                  self.save_config();
              },
@@ -414,6 +874,9 @@ impl ApplicationHandler<UserEvent> for App {
                  if let Some(entry) = self.chart_ids.iter_mut().find(|(wid, _, _, _)| *wid == id) {
                      entry.2 = locked;
                  }
+                 if let Some(entry) = self.widget_ids.iter_mut().find(|(wid, _)| *wid == id) {
+                     entry.1 = locked;
+                 }
                  // Update window
                  if let Some(handler) = self.windows.get_mut(&id) {
                      handler.set_locked(locked);
@@ -440,9 +903,30 @@ impl ApplicationHandler<UserEvent> for App {
                  self.refresh_settings_window();
                  self.save_config();
              },
+             UserEvent::ChartStyle(id, style) => {
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_style(style);
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::ChartIndicators(id, indicators) => {
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_indicators(indicators);
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
+             UserEvent::ChartCompare(id, symbols) => {
+                 if let Some(handler) = self.windows.get_mut(&id) {
+                     handler.set_compare_symbols(symbols);
+                 }
+                 self.refresh_settings_window();
+                 self.save_config();
+             },
              UserEvent::OpenSettings => {
                  // Only spawn if not already connected
-                 if self.ipc_tx.is_some() {
+                 if self.settings_connected() {
                      // Maybe bring to front if possible? 
                      // For now just prevent duplicate spawn
                      return;
@@ -519,12 +1003,10 @@ impl ApplicationHandler<UserEvent> for App {
                  });
              },
              UserEvent::UpdateStatus(status) => {
-                 if let Some(tx) = &self.ipc_tx {
-                     let _ = tx.try_send(crate::ipc::IpcMessage::UpdateStatus(status.clone()));
-                 }
+                 self.send_ipc(crate::ipc::IpcMessage::UpdateStatus(status.clone()));
 
                  if let UpdateStatus::Available(ref version) = status {
-                     if self.ipc_tx.is_none() {
+                     if !self.settings_connected() {
                          if let Err(e) = updater::show_update_notification(version, AUM_ID, self.proxy.clone(), self.config.language) {
                              log::error!("Failed to show notification: {}", e);
                          }
@@ -534,81 +1016,212 @@ impl ApplicationHandler<UserEvent> for App {
              UserEvent::RestartApp => {
                  // Spawn a new instance of the application
                  if let Ok(exe_path) = std::env::current_exe() {
-                     const CREATE_NO_WINDOW: u32 = 0x08000000;
-                     let _ = std::process::Command::new(exe_path)
-                         .creation_flags(CREATE_NO_WINDOW)
-                         .spawn();
+                     let _ = platform::hide_console(std::process::Command::new(exe_path).args(config_args())).spawn();
                  }
                  event_loop.exit();
              },
-             UserEvent::IpcConnected(tx) => {
-                 self.ipc_tx = Some(tx);
+             UserEvent::IpcConnected(conn_id, client, tx) => {
+                 self.ipc_clients.insert(conn_id, (client, tx));
                  self.refresh_settings_window();
              },
-             UserEvent::IpcMessageReceived(msg) => {
-                 match msg {
-                     crate::ipc::IpcMessage::GetCharts | crate::ipc::IpcMessage::GetConfig => {
+             UserEvent::IpcMessageReceived(conn_id, request_id, msg) => {
+                 use crate::ipc::IpcMessage;
+                 let result = match msg {
+                     IpcMessage::GetCharts | IpcMessage::GetConfig | IpcMessage::GetPositions => {
                          self.refresh_settings_window();
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::AddChart(symbol) => {
-                         let _ = self.proxy.send_event(UserEvent::AddChart(symbol));
+                     IpcMessage::AddChart(symbol) => {
+                         // Answered once the chart has loaded its data or failed to
+                         let _ = self.proxy.send_event(UserEvent::AddChart(symbol, Some((conn_id, request_id))));
+                         return;
                      },
-                     crate::ipc::IpcMessage::DeleteChart(id_str) => {
-                         if let Some((wid, _, _, _)) = self.chart_ids.iter().find(|(wid, _, _, _)| format!("{:?}", wid) == id_str) {
-                             let _ = self.proxy.send_event(UserEvent::DeleteChart(*wid));
+                     IpcMessage::DeleteChart(id_str) => self.find_widget(&id_str).map(|wid| {
+                         let _ = self.proxy.send_event(UserEvent::DeleteChart(wid));
+                     }),
+                     IpcMessage::ToggleChartLock(id_str, locked) => self.find_widget(&id_str).map(|wid| {
+                         let _ = self.proxy.send_event(UserEvent::ToggleLock(wid, locked));
+                     }),
+                     IpcMessage::SetChartTimeframe(id_str, tf) => {
+                         if provider::TIMEFRAMES.contains(&tf.as_str()) {
+                             self.find_widget(&id_str).map(|wid| {
+                                 let _ = self.proxy.send_event(UserEvent::ChartTimeframe(wid, tf));
+                             })
+                         } else {
+                             Err(format!("Unknown timeframe {}, expected one of {}", tf, provider::TIMEFRAMES.join(", ")))
                          }
                      },
-                     crate::ipc::IpcMessage::ToggleChartLock(id_str, locked) => {
-                         if let Some((wid, _, _, _)) = self.chart_ids.iter().find(|(wid, _, _, _)| format!("{:?}", wid) == id_str) {
-                             let _ = self.proxy.send_event(UserEvent::ToggleLock(*wid, locked));
+                     IpcMessage::SetChartStyle(id_str, style) => self.find_widget(&id_str).map(|wid| {
+                         let _ = self.proxy.send_event(UserEvent::ChartStyle(wid, style));
+                     }),
+                     IpcMessage::SetChartIndicators(id_str, indicators) => self.find_widget(&id_str).map(|wid| {
+                         let _ = self.proxy.send_event(UserEvent::ChartIndicators(wid, indicators));
+                     }),
+                     IpcMessage::SetChartCompare(id_str, symbols) => self.find_widget(&id_str).map(|wid| {
+                         let _ = self.proxy.send_event(UserEvent::ChartCompare(wid, symbols));
+                     }),
+                     IpcMessage::AddWatchlist(symbols) => {
+                         if symbols.is_empty() {
+                             Err("A watchlist needs at least one symbol".to_string())
+                         } else {
+                             let _ = self.proxy.send_event(UserEvent::AddWatchlist(symbols));
+                             Ok(())
                          }
                      },
-                     crate::ipc::IpcMessage::SetChartTimeframe(id_str, tf) => {
-                         if let Some((wid, _, _, _)) = self.chart_ids.iter().find(|(wid, _, _, _)| format!("{:?}", wid) == id_str) {
-                             let _ = self.proxy.send_event(UserEvent::ChartTimeframe(*wid, tf));
+                     IpcMessage::SetSymbols(id_str, symbols) => {
+                         if symbols.is_empty() {
+                             Err("A watchlist or ticker needs at least one symbol".to_string())
+                         } else {
+                             self.find_widget(&id_str).map(|wid| {
+                                 let _ = self.proxy.send_event(UserEvent::WidgetSymbols(wid, symbols));
+                             })
+                         }
+                     },
+                     IpcMessage::AddTicker(symbols) => {
+                         if symbols.is_empty() {
+                             Err("A ticker needs at least one symbol".to_string())
+                         } else {
+                             let _ = self.proxy.send_event(UserEvent::AddTicker(symbols));
+                             Ok(())
                          }
                      },
-                     crate::ipc::IpcMessage::SetLanguage(lang_str) => {
+                     IpcMessage::SetTickerSpeed(id_str, speed) => {
+                         if speed == 0 || speed > ticker::MAX_SPEED {
+                             Err(format!("Ticker speed must be between 1 and {} px/s", ticker::MAX_SPEED))
+                         } else {
+                             self.find_widget(&id_str).map(|wid| {
+                                 let _ = self.proxy.send_event(UserEvent::TickerSpeed(wid, speed));
+                             })
+                         }
+                     },
+                     IpcMessage::SetPositions(positions) => {
+                         match crate::portfolio::validate(positions) {
+                             Ok(positions) => {
+                                 let _ = self.proxy.send_event(UserEvent::Positions(positions));
+                                 Ok(())
+                             },
+                             Err(e) => Err(format!("Invalid positions: {}", e)),
+                         }
+                     },
+                     IpcMessage::ShowPortfolio(show) => {
+                         let _ = self.proxy.send_event(UserEvent::ShowPortfolio(show));
+                         Ok(())
+                     },
+                     IpcMessage::GetAlerts => {
+                         self.refresh_alerts_window();
+                         Ok(())
+                     },
+                     IpcMessage::AddAlert(symbol, condition) => {
+                         let id = self.config.alerts.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+                         self.config.alerts.push(alerts::AlertRule::new(id, symbol, condition));
+                         self.save_config();
+                         self.refresh_alerts_window();
+                         self.fetch_alert_data(id);
+                         Ok(())
+                     },
+                     IpcMessage::UpdateAlert(id, condition) => {
+                         let result = self.update_alert(id, |rule| {
+                             rule.condition = condition;
+                             rule.armed = true;
+                         });
+                         self.fetch_alert_data(id);
+                         result
+                     },
+                     IpcMessage::SetAlertEnabled(id, enabled) => {
+                         self.update_alert(id, |rule| rule.enabled = enabled)
+                     },
+                     IpcMessage::SetAlertCooldown(id, minutes) => {
+                         self.update_alert(id, |rule| rule.cooldown_minutes = minutes)
+                     },
+                     IpcMessage::DeleteAlert(id) => {
+                         if self.config.alerts.iter().any(|r| r.id == id) {
+                             self.config.alerts.retain(|r| r.id != id);
+                             self.save_config();
+                             self.refresh_alerts_window();
+                             Ok(())
+                         } else {
+                             Err(format!("Unknown alert id {}", id))
+                         }
+                     },
+                     IpcMessage::SetLanguage(lang_str) => {
                          // Parse language
                          let lang = if lang_str == "de" { language::Language::De } else { language::Language::En };
                          let _ = self.proxy.send_event(UserEvent::LanguageChanged(lang));
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::SetUpdateInterval(min) => {
+                     IpcMessage::SetUpdateInterval(min) => {
                          let _ = self.proxy.send_event(UserEvent::UpdateInterval(min));
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::SetUsePrereleases(val) => {
+                     IpcMessage::SetUsePrereleases(val) => {
                          let _ = self.proxy.send_event(UserEvent::UsePrereleases(val));
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::SetAutoStart(enable) => {
-                         let args: &[&str] = &[];
-                         let auto = auto_launch::AutoLaunch::new("desktop-widget-rs", std::env::current_exe().unwrap().to_str().unwrap(), args);
-                         if enable {
-                             let _ = auto.enable();
-                         } else {
-                             let _ = auto.disable();
+                     IpcMessage::SetBaseCurrency(base) => {
+                         let base = base.map(|b| b.trim().to_uppercase()).filter(|b| !b.is_empty());
+                         match base {
+                             Some(code) if !currency::is_iso_code(&code) => Err(format!("Invalid currency code: {}", code)),
+                             base => {
+                                 let _ = self.proxy.send_event(UserEvent::BaseCurrency(base));
+                                 Ok(())
+                             }
                          }
+                     },
+                     IpcMessage::SetConvertPrices(enabled) => {
+                         let _ = self.proxy.send_event(UserEvent::ConvertPrices(enabled));
+                         Ok(())
+                     },
+                     IpcMessage::SetAutoStart(enable) => {
+                         let auto = auto_launch();
+                         let result = if enable { auto.enable() } else { auto.disable() };
                          self.refresh_settings_window(); // Sync back
+                         result.map_err(|e| e.to_string())
                      },
-                     crate::ipc::IpcMessage::CheckForUpdates => {
+                     IpcMessage::CheckForUpdates => {
                          let _ = self.proxy.send_event(UserEvent::CheckForUpdates);
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::PerformUpdate => {
+                     IpcMessage::PerformUpdate => {
                          let _ = self.proxy.send_event(UserEvent::PerformUpdate);
+                         Ok(())
                      },
-                     crate::ipc::IpcMessage::Restart => {
+                     IpcMessage::Restart => {
                          let _ = self.proxy.send_event(UserEvent::RestartApp);
+                         Ok(())
                      },
-                     _ => {}
-                 }
+                     IpcMessage::RestorePreviousLayout => self.restore_previous_layout(event_loop),
+                     IpcMessage::Refresh => {
+                         for handler in self.windows.values_mut() {
+                             handler.refresh();
+                         }
+                         self.refresh_alert_data();
+                         self.last_auto_refresh = std::time::Instant::now();
+                         Ok(())
+                     },
+                     IpcMessage::Quit => {
+                         // Acknowledge first, the connection is gone once the event loop exits
+                         self.respond(conn_id, request_id, Ok(()));
+                         self.quit(event_loop);
+                         return;
+                     },
+                     other => Err(format!("Unsupported request {:?}", other)),
+                 };
+                 self.respond(conn_id, request_id, result);
              },
-             UserEvent::IpcDisconnected => {
-                 self.ipc_tx = None;
-                 self.refresh_settings_window(); // Likely does nothing if ipc_tx is None, but cleaner
+             UserEvent::IpcDisconnected(conn_id) => {
+                 let Some((client, _)) = self.ipc_clients.remove(&conn_id) else { return };
+                 self.pending_requests.retain(|_, (c, _)| *c != conn_id);
+                 if client != crate::ipc::SETTINGS_CLIENT {
+                     return;
+                 }
                  
                  // Lock all charts
                  for entry in &mut self.chart_ids {
                      entry.2 = true;
                  }
+                 for entry in &mut self.widget_ids {
+                     entry.1 = true;
+                 }
                  for handler in self.windows.values_mut() {
                      handler.set_locked(true);
                  }
@@ -636,6 +1249,16 @@ fn main() {
 
     // Check arguments for settings mode
     let args: Vec<String> = std::env::args().collect();
+
+    // Config file to use instead of the per-user one
+    if let Some(i) = args.iter().position(|a| a == "--config") {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("Missing path after --config");
+            std::process::exit(2);
+        };
+        // Absolute, so a restart or the autostart entry finds the same file from any working dir
+        AppConfig::set_path_override(std::path::absolute(path).unwrap_or_else(|_| path.into()));
+    }
     if args.iter().any(|a| a == "--settings") {
         if let Err(e) = settings_iced::run() {
             log::error!("Settings Error: {}", e);
@@ -643,15 +1266,34 @@ fn main() {
         return;
     }
 
+    // Scripting client for an already running host, talks IPC and exits
+    if args.get(1).is_some_and(|a| a == "ctl") {
+        std::process::exit(ctl::run(&args[2..]));
+    }
+
+    // Headless rendering to a PNG file, no windows or tray involved
+    if args.iter().any(|a| a == "--render") {
+        if let Err(e) = render::run_headless(&args) {
+            log::error!("Render Error: {}", e);
+            eprintln!("Render Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let event_loop = EventLoop::<UserEvent>::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
 
+    // Toolkit the tray icon relies on (gtk on Linux), must run on the event loop's thread
+    platform::init();
+
     // Register AUMID in registry to make notifications work
-    if let Err(e) = register_aumid(AUM_ID, "Desktop Widget", None) {
+    if let Err(e) = platform::register_app_id(AUM_ID, "Desktop Widget", None) {
         log::error!("Failed to register AUMID: {:?}", e);
     }
     
     let proxy = event_loop.create_proxy();
+    let fetcher = FetchService::new(event_loop.create_proxy());
     
     let mut app = App { 
         windows: HashMap::new(),
@@ -659,6 +1301,8 @@ fn main() {
         tray_icon: None,
         tray_menu: None,
         chart_ids: Vec::new(),
+        widget_ids: Vec::new(),
+        portfolio_id: None,
         settings_id: None,
         settings_item: None,
         quit_item: None,
@@ -667,8 +1311,15 @@ fn main() {
         last_save_time: std::time::Instant::now(),
         last_auto_refresh: std::time::Instant::now(),
         last_update_check: std::time::Instant::now(),
-        ipc_tx: None,
+        ipc_clients: HashMap::new(),
         pending_charts: HashMap::new(),
+        pending_requests: HashMap::new(),
+        fetcher,
+        alert_history: VecDeque::new(),
+        api_state: None,
+        fx: None,
+        currencies: HashSet::new(),
+        fetched_at: HashMap::new(),
     };
     
     // Start IPC Server
@@ -676,68 +1327,71 @@ fn main() {
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async move {
-            use tokio::net::windows::named_pipe::ServerOptions;
-            use tokio::io::{AsyncReadExt, AsyncWriteExt};
-            
+            let mut listener = loop {
+                match crate::ipc::IpcListener::bind() {
+                    Ok(l) => break l,
+                    Err(e) => {
+                        log::error!("Failed to create IPC listener: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    }
+                }
+            };
+
+            let mut next_conn_id: u64 = 1;
             loop {
-                 let server = ServerOptions::new()
-                    .first_pipe_instance(true)
-                    .create(crate::ipc::PIPE_NAME)
-                    .or_else(|_| {
-                         // If first instance fails, try allowing multiple? 
-                         // Actually named pipes reuse the name. 
-                         // But for now let's just try creation.
-                         // If "All pipe instances are busy", we need loop.
-                         ServerOptions::new().first_pipe_instance(false).create(crate::ipc::PIPE_NAME)
-                    });
-                 
-                 let server = match server {
+                 let stream = match listener.accept().await {
                      Ok(s) => s,
                      Err(e) => {
-                         log::error!("Failed to create pipe: {}", e);
+                         log::error!("Failed to accept IPC client: {}", e);
                          tokio::time::sleep(std::time::Duration::from_secs(1)).await;
                          continue;
                      }
                  };
                  
-                 if server.connect().await.is_err() { continue; }
-                 
                  let proxy = proxy.clone();
+                 let conn_id = next_conn_id;
+                 next_conn_id += 1;
                  tokio::spawn(async move {
-                     let (mut reader, mut writer) = tokio::io::split(server);
-                     let (tx, mut rx) = tokio::sync::mpsc::channel::<crate::ipc::IpcMessage>(32);
-                     
-                     let _ = proxy.send_event(UserEvent::IpcConnected(tx));
+                     let mut stream = stream;
+                     let client = match crate::ipc::accept_handshake(&mut stream).await {
+                         Ok(client) => {
+                             log::info!("IPC client '{}' connected (connection {})", client, conn_id);
+                             client
+                         },
+                         Err(e) => {
+                             log::warn!("IPC handshake failed: {}", e);
+                             return;
+                         }
+                     };
+
+                     let (mut reader, mut writer) = tokio::io::split(stream);
+                     let (tx, mut rx) = crate::ipc::frame_queue();
                      
+                     let _ = proxy.send_event(UserEvent::IpcConnected(conn_id, client, tx));
+
+                     // Writes run in their own task so a pending read is never cancelled halfway through a frame
+                     let writer_task = tokio::spawn(async move {
+                         while let Some(frame) = rx.recv().await {
+                             if crate::ipc::write_frame(&mut writer, &frame).await.is_err() { break; }
+                         }
+                     });
+
                      loop {
-                         tokio::select! {
-                             msg = rx.recv() => {
-                                 if let Some(msg) = msg {
-                                      if let Ok(json) = serde_json::to_string(&msg) {
-                                         let bytes = json.as_bytes();
-                                         let len = bytes.len() as u32;
-                                         if writer.write_all(&len.to_le_bytes()).await.is_err() { break; }
-                                         if writer.write_all(bytes).await.is_err() { break; }
-                                     }
-                                 } else { break; }
-                             }
-                             res = async {
-                                 let mut len_buf = [0u8; 4];
-                                 if reader.read_exact(&mut len_buf).await.is_err() { return None; }
-                                 let len = u32::from_le_bytes(len_buf) as usize;
-                                 let mut buf = vec![0u8; len];
-                                 if reader.read_exact(&mut buf).await.is_err() { return None; }
-                                 serde_json::from_slice::<crate::ipc::IpcMessage>(&buf).ok()
-                             } => {
-                                 if let Some(msg) = res {
-                                     let _ = proxy.send_event(UserEvent::IpcMessageReceived(msg));
-                                 } else {
-                                     let _ = proxy.send_event(UserEvent::IpcDisconnected);
-                                     break; 
-                                 }
+                         match crate::ipc::read_frame(&mut reader).await {
+                             Ok(Some(crate::ipc::Frame::Request { id, message })) => {
+                                 let _ = proxy.send_event(UserEvent::IpcMessageReceived(conn_id, id, message));
+                             },
+                             Ok(Some(other)) => log::warn!("Ignoring unexpected IPC frame {:?}", other),
+                             Ok(None) => break,
+                             Err(e) => {
+                                 log::warn!("IPC read error: {}", e);
+                                 break;
                              }
                          }
                      }
+
+                     writer_task.abort();
+                     let _ = proxy.send_event(UserEvent::IpcDisconnected(conn_id));
                  });
             }
         });
@@ -0,0 +1,517 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+// Data restored from disk or older than this is fetched again while the market is open
+const REFRESH_MINUTES: i64 = 30;
+
+/// Trading state of an exchange at some point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    Pre,
+    Open,
+    Post,
+    Closed,
+}
+
+/// Regular trading hours, time zone and holiday rules of one exchange.
+/// Holidays follow the exchanges' standing rules, one-off closures (state funerals etc.) are not known.
+pub struct Exchange {
+    tz: Tz,
+    open: NaiveTime,
+    close: NaiveTime,
+    extended: Option<(NaiveTime, NaiveTime)>, // Start of pre-market, end of after hours trading
+    is_holiday: fn(NaiveDate) -> bool,
+    early_close: fn(NaiveDate) -> Option<NaiveTime>,
+}
+
+const fn time(hour: u32, min: u32) -> NaiveTime {
+    match NaiveTime::from_hms_opt(hour, min, 0) {
+        Some(t) => t,
+        None => panic!("invalid time"),
+    }
+}
+
+pub static NYSE: Exchange = Exchange {
+    tz: chrono_tz::America::New_York,
+    open: time(9, 30),
+    close: time(16, 0),
+    extended: Some((time(4, 0), time(20, 0))),
+    is_holiday: us_holiday,
+    early_close: us_early_close,
+};
+
+pub static XETRA: Exchange = Exchange {
+    tz: chrono_tz::Europe::Berlin,
+    open: time(9, 0),
+    close: time(17, 30),
+    extended: None,
+    is_holiday: xetra_holiday,
+    early_close: no_early_close,
+};
+
+pub static LSE: Exchange = Exchange {
+    tz: chrono_tz::Europe::London,
+    open: time(8, 0),
+    close: time(16, 30),
+    extended: None,
+    is_holiday: uk_holiday,
+    early_close: lse_early_close,
+};
+
+pub static EURONEXT: Exchange = Exchange {
+    tz: chrono_tz::Europe::Paris,
+    open: time(9, 0),
+    close: time(17, 30),
+    extended: None,
+    is_holiday: euronext_holiday,
+    early_close: euronext_early_close,
+};
+
+pub static SIX: Exchange = Exchange {
+    tz: chrono_tz::Europe::Zurich,
+    open: time(9, 0),
+    close: time(17, 30),
+    extended: None,
+    is_holiday: six_holiday,
+    early_close: no_early_close,
+};
+
+pub static TSX: Exchange = Exchange {
+    tz: chrono_tz::America::Toronto,
+    open: time(9, 30),
+    close: time(16, 0),
+    extended: None,
+    is_holiday: canada_holiday,
+    early_close: tsx_early_close,
+};
+
+// The lunch break is not modelled, prices simply don't move during it
+pub static TSE: Exchange = Exchange {
+    tz: chrono_tz::Asia::Tokyo,
+    open: time(9, 0),
+    close: time(15, 30),
+    extended: None,
+    is_holiday: japan_holiday,
+    early_close: no_early_close,
+};
+
+/// Exchange a Yahoo symbol trades on, going by its suffix ("SAP.DE") or, for indices, its name.
+/// None for symbols without a known calendar such as FX pairs, futures and crypto, which are
+/// treated as always open.
+pub fn exchange_for(symbol: &str) -> Option<&'static Exchange> {
+    if let Some(index) = symbol.strip_prefix('^') {
+        return match index {
+            "GSPC" | "DJI" | "IXIC" | "NDX" | "RUT" | "VIX" => Some(&NYSE),
+            "GDAXI" | "MDAXI" | "SDAXI" | "TECDAX" => Some(&XETRA),
+            "FTSE" | "FTMC" => Some(&LSE),
+            "FCHI" | "AEX" | "BFX" => Some(&EURONEXT),
+            "SSMI" => Some(&SIX),
+            "GSPTSE" => Some(&TSX),
+            "N225" => Some(&TSE),
+            _ => None,
+        };
+    }
+    // "EURUSD=X", "ES=F", "BTC-USD"
+    if symbol.contains('=') || symbol.rsplit_once('-').is_some_and(|(_, quote)| crate::currency::is_iso_code(quote)) {
+        return None;
+    }
+    match symbol.rsplit_once('.') {
+        None => Some(&NYSE),
+        Some((_, "DE")) => Some(&XETRA),
+        Some((_, "L" | "IL")) => Some(&LSE),
+        Some((_, "PA" | "AS" | "BR" | "LS")) => Some(&EURONEXT),
+        Some((_, "SW")) => Some(&SIX),
+        Some((_, "TO" | "V")) => Some(&TSX),
+        Some((_, "T")) => Some(&TSE),
+        Some(_) => None,
+    }
+}
+
+impl Exchange {
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !(self.is_holiday)(date)
+    }
+
+    fn at(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        // Sessions never start or end inside a DST gap, `earliest` only guards against panics
+        self.tz.from_local_datetime(&date.and_time(time)).earliest()
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&date.and_time(time)))
+    }
+
+    /// Regular trading hours of `date` (the exchange's local date), None if it doesn't trade.
+    pub fn hours(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let close = (self.early_close)(date).unwrap_or(self.close);
+        Some((self.at(date, self.open), self.at(date, close)))
+    }
+
+    // Pre-market start and after hours end of `date`, the regular hours without extended trading
+    fn extended_hours(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let (open, close) = self.hours(date)?;
+        Some(match self.extended {
+            Some((pre, post)) => (self.at(date, pre), self.at(date, post)),
+            None => (open, close),
+        })
+    }
+
+    pub fn session(&self, now: DateTime<Utc>) -> Session {
+        let date = now.with_timezone(&self.tz).date_naive();
+        let (Some((open, close)), Some((pre, post))) = (self.hours(date), self.extended_hours(date)) else {
+            return Session::Closed;
+        };
+        if now >= open && now < close {
+            Session::Open
+        } else if now >= pre && now < open {
+            Session::Pre
+        } else if now >= close && now < post {
+            Session::Post
+        } else {
+            Session::Closed
+        }
+    }
+
+    /// End of the latest trading (including after hours) that finished before `now`.
+    pub fn last_close(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.tz).date_naive();
+        // Long enough for any run of holidays and weekends
+        (0..14)
+            .filter_map(|days| self.extended_hours(today - Duration::days(days)))
+            .map(|(_, end)| end)
+            .find(|end| *end <= now)
+    }
+}
+
+/// Regular hours (unix seconds) of the trading day `timestamp` falls on, the span of a 1D chart.
+pub fn session_range(symbol: &str, timestamp: i64) -> Option<(i64, i64)> {
+    let exchange = exchange_for(symbol)?;
+    let date = DateTime::from_timestamp(timestamp, 0)?.with_timezone(&exchange.tz).date_naive();
+    exchange.hours(date).map(|(open, close)| (open.timestamp(), close.timestamp()))
+}
+
+pub fn session(symbol: &str) -> Option<Session> {
+    exchange_for(symbol).map(|exchange| exchange.session(Utc::now()))
+}
+
+/// Refresh rule shared by all widgets: data restored from disk or older than 30 minutes is fetched
+/// again, unless the exchange is closed and the data was fetched after its last close.
+pub fn is_outdated(symbol: &str, fetched_at: DateTime<Local>, from_disk: bool) -> bool {
+    is_outdated_at(symbol, fetched_at.with_timezone(&Utc), from_disk, Utc::now())
+}
+
+fn is_outdated_at(symbol: &str, fetched_at: DateTime<Utc>, from_disk: bool, now: DateTime<Utc>) -> bool {
+    if !from_disk && (now - fetched_at).num_minutes() < REFRESH_MINUTES {
+        return false;
+    }
+    match exchange_for(symbol) {
+        Some(exchange) if exchange.session(now) == Session::Closed => {
+            exchange.last_close(now).is_none_or(|close| fetched_at < close)
+        },
+        _ => true,
+    }
+}
+
+// Western Easter Sunday (anonymous Gregorian algorithm)
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+// `n`th (1-based) `weekday` of the month, or the last one for n = -1
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i32) -> NaiveDate {
+    if n > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8).unwrap()
+    } else {
+        let mut day = NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap_or(date(year + 1, 1, 1)).pred_opt().unwrap();
+        while day.weekday() != weekday {
+            day = day.pred_opt().unwrap();
+        }
+        day
+    }
+}
+
+// US rule: Saturday holidays move to Friday, Sunday ones to Monday
+fn observed(day: NaiveDate) -> NaiveDate {
+    match day.weekday() {
+        Weekday::Sat => day.pred_opt().unwrap(),
+        Weekday::Sun => day.succ_opt().unwrap(),
+        _ => day,
+    }
+}
+
+// UK/Canadian rule: weekend holidays move to the next weekday that isn't a holiday itself
+fn substitutes(days: &[NaiveDate]) -> Vec<NaiveDate> {
+    let mut result: Vec<NaiveDate> = Vec::new();
+    for &day in days {
+        let mut day = day;
+        while matches!(day.weekday(), Weekday::Sat | Weekday::Sun) || result.contains(&day) {
+            day = day.succ_opt().unwrap();
+        }
+        result.push(day);
+    }
+    result
+}
+
+fn good_friday(year: i32) -> NaiveDate {
+    easter(year) - Duration::days(2)
+}
+
+fn easter_monday(year: i32) -> NaiveDate {
+    easter(year) + Duration::days(1)
+}
+
+fn us_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    let new_year = date(year, 1, 1);
+    // A Saturday New Year's Day is not made up for on Friday, that would close the previous year
+    let new_year_observed = if new_year.weekday() == Weekday::Sun { Some(date(year, 1, 2)) } else if new_year.weekday() == Weekday::Sat { None } else { Some(new_year) };
+    let mut holidays = vec![
+        nth_weekday(year, 1, Weekday::Mon, 3),  // Martin Luther King Jr. Day
+        nth_weekday(year, 2, Weekday::Mon, 3),  // Washington's Birthday
+        good_friday(year),
+        nth_weekday(year, 5, Weekday::Mon, -1), // Memorial Day
+        observed(date(year, 7, 4)),
+        nth_weekday(year, 9, Weekday::Mon, 1),  // Labor Day
+        nth_weekday(year, 11, Weekday::Thu, 4), // Thanksgiving
+        observed(date(year, 12, 25)),
+    ];
+    holidays.extend(new_year_observed);
+    if year >= 2022 {
+        holidays.push(observed(date(year, 6, 19))); // Juneteenth
+    }
+    holidays.contains(&day)
+}
+
+fn us_early_close(day: NaiveDate) -> Option<NaiveTime> {
+    let year = day.year();
+    let early = [date(year, 7, 3), nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1), date(year, 12, 24)];
+    early.contains(&day).then_some(time(13, 0))
+}
+
+fn xetra_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    [date(year, 1, 1), good_friday(year), easter_monday(year), date(year, 5, 1), date(year, 12, 24), date(year, 12, 25), date(year, 12, 26), date(year, 12, 31)].contains(&day)
+}
+
+fn uk_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    let mut holidays = vec![
+        good_friday(year),
+        easter_monday(year),
+        nth_weekday(year, 5, Weekday::Mon, 1),  // Early May bank holiday
+        nth_weekday(year, 5, Weekday::Mon, -1), // Spring bank holiday
+        nth_weekday(year, 8, Weekday::Mon, -1), // Summer bank holiday
+    ];
+    holidays.extend(substitutes(&[date(year, 1, 1)]));
+    holidays.extend(substitutes(&[date(year, 12, 25), date(year, 12, 26)]));
+    holidays.contains(&day)
+}
+
+fn lse_early_close(day: NaiveDate) -> Option<NaiveTime> {
+    let year = day.year();
+    // Christmas Eve and New Year's Eve, or the last weekday before them
+    let eve = |mut d: NaiveDate| {
+        while matches!(d.weekday(), Weekday::Sat | Weekday::Sun) {
+            d = d.pred_opt().unwrap();
+        }
+        d
+    };
+    [eve(date(year, 12, 24)), eve(date(year, 12, 31))].contains(&day).then_some(time(12, 30))
+}
+
+fn euronext_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    [date(year, 1, 1), good_friday(year), easter_monday(year), date(year, 5, 1), date(year, 12, 25), date(year, 12, 26)].contains(&day)
+}
+
+fn euronext_early_close(day: NaiveDate) -> Option<NaiveTime> {
+    let year = day.year();
+    [date(year, 12, 24), date(year, 12, 31)].contains(&day).then_some(time(14, 5))
+}
+
+fn six_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    let easter = easter(year);
+    [
+        date(year, 1, 1), date(year, 1, 2), good_friday(year), easter_monday(year), date(year, 5, 1),
+        easter + Duration::days(39), // Ascension Day
+        easter + Duration::days(50), // Whit Monday
+        date(year, 8, 1), date(year, 12, 24), date(year, 12, 25), date(year, 12, 26), date(year, 12, 31),
+    ].contains(&day)
+}
+
+fn canada_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    // Victoria Day: the Monday before May 25
+    let mut victoria = date(year, 5, 24);
+    while victoria.weekday() != Weekday::Mon {
+        victoria = victoria.pred_opt().unwrap();
+    }
+    let mut holidays = vec![
+        nth_weekday(year, 2, Weekday::Mon, 3),  // Family Day
+        good_friday(year),
+        victoria,
+        nth_weekday(year, 8, Weekday::Mon, 1),  // Civic Holiday
+        nth_weekday(year, 9, Weekday::Mon, 1),  // Labour Day
+        nth_weekday(year, 10, Weekday::Mon, 2), // Thanksgiving
+    ];
+    holidays.extend(substitutes(&[date(year, 1, 1)]));
+    holidays.extend(substitutes(&[date(year, 7, 1)]));
+    holidays.extend(substitutes(&[date(year, 12, 25), date(year, 12, 26)]));
+    holidays.contains(&day)
+}
+
+fn tsx_early_close(day: NaiveDate) -> Option<NaiveTime> {
+    (day == date(day.year(), 12, 24)).then_some(time(13, 0))
+}
+
+// Equinox days by the usual approximation, good for 1980 to 2099
+fn equinox(year: i32, base: f64) -> NaiveDate {
+    let y = (year - 1980) as f64;
+    let day = (base + 0.242194 * y - (y / 4.0).floor()).floor() as u32;
+    date(year, if base < 22.0 { 3 } else { 9 }, day)
+}
+
+// Japanese national holidays, with substitute days for Sunday holidays and days between two holidays
+fn japan_national_holidays(year: i32) -> Vec<NaiveDate> {
+    let mut holidays = vec![
+        date(year, 1, 1),
+        nth_weekday(year, 1, Weekday::Mon, 2), // Coming of Age Day
+        date(year, 2, 11),
+        date(year, 2, 23),
+        equinox(year, 20.8431),
+        date(year, 4, 29),
+        date(year, 5, 3),
+        date(year, 5, 4),
+        date(year, 5, 5),
+        nth_weekday(year, 7, Weekday::Mon, 3), // Marine Day
+        date(year, 8, 11),
+        nth_weekday(year, 9, Weekday::Mon, 3), // Respect for the Aged Day
+        equinox(year, 23.2488),
+        nth_weekday(year, 10, Weekday::Mon, 2), // Sports Day
+        date(year, 11, 3),
+        date(year, 11, 23),
+    ];
+    // A day between two holidays is a holiday, e.g. in September
+    let between: Vec<NaiveDate> = holidays.iter()
+        .map(|d| *d + Duration::days(2))
+        .filter(|d| holidays.contains(d))
+        .map(|d| d - Duration::days(1))
+        .filter(|d| !holidays.contains(d) && d.weekday() != Weekday::Sun)
+        .collect();
+    holidays.extend(between);
+    let sundays: Vec<NaiveDate> = holidays.iter().copied().filter(|d| d.weekday() == Weekday::Sun).collect();
+    for sunday in sundays {
+        let mut substitute = sunday.succ_opt().unwrap();
+        while holidays.contains(&substitute) {
+            substitute = substitute.succ_opt().unwrap();
+        }
+        holidays.push(substitute);
+    }
+    holidays
+}
+
+fn japan_holiday(day: NaiveDate) -> bool {
+    let year = day.year();
+    // The exchange also closes over the New Year holidays
+    [date(year, 1, 2), date(year, 1, 3), date(year, 12, 31)].contains(&day) || japan_national_holidays(year).contains(&day)
+}
+
+fn no_early_close(_day: NaiveDate) -> Option<NaiveTime> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0).unwrap()
+    }
+
+    fn trades_on(symbol: &str, exchange: &Exchange) -> bool {
+        exchange_for(symbol).is_some_and(|e| std::ptr::eq(e, exchange))
+    }
+
+    #[test]
+    fn maps_symbols_to_exchanges() {
+        assert!(trades_on("AAPL", &NYSE));
+        assert!(trades_on("BRK-B", &NYSE));
+        assert!(trades_on("VVSM.DE", &XETRA));
+        assert!(trades_on("^GDAXI", &XETRA));
+        assert!(trades_on("VOD.L", &LSE));
+        assert!(exchange_for("EURUSD=X").is_none());
+        assert!(exchange_for("BTC-USD").is_none());
+        assert!(exchange_for("SAP.MU").is_none());
+    }
+
+    #[test]
+    fn computes_holidays() {
+        assert_eq!(easter(2024), date(2024, 3, 31));
+        assert_eq!(easter(2025), date(2025, 4, 20));
+
+        assert!(!NYSE.is_trading_day(date(2024, 3, 29))); // Good Friday
+        assert!(!NYSE.is_trading_day(date(2024, 7, 4)));
+        assert!(!NYSE.is_trading_day(date(2026, 7, 3))); // July 4th on a Saturday
+        assert!(!NYSE.is_trading_day(date(2024, 11, 28))); // Thanksgiving
+        assert!(NYSE.is_trading_day(date(2021, 12, 31))); // New Year's Day 2022 was a Saturday
+        assert!(NYSE.is_trading_day(date(2024, 4, 1)));
+        assert!(!XETRA.is_trading_day(date(2024, 4, 1))); // Easter Monday
+        assert!(!XETRA.is_trading_day(date(2024, 12, 24)));
+        assert!(!LSE.is_trading_day(date(2022, 12, 27))); // Substitute for Christmas on a Sunday
+        assert_eq!(lse_early_close(date(2022, 12, 23)), Some(time(12, 30)));
+        assert!(!SIX.is_trading_day(date(2024, 5, 9))); // Ascension Day
+        assert!(!TSE.is_trading_day(date(2024, 9, 23))); // Substitute for the equinox on a Sunday
+        assert!(!TSE.is_trading_day(date(2026, 9, 22))); // Between Respect for the Aged Day and the equinox
+    }
+
+    #[test]
+    fn sessions_follow_local_time() {
+        // Winter and summer time: 9:30 in New York is 14:30 and 13:30 UTC
+        assert_eq!(NYSE.session(utc(2024, 1, 10, 14, 29)), Session::Pre);
+        assert_eq!(NYSE.session(utc(2024, 1, 10, 14, 30)), Session::Open);
+        assert_eq!(NYSE.session(utc(2024, 7, 10, 13, 30)), Session::Open);
+        assert_eq!(NYSE.session(utc(2024, 7, 10, 20, 30)), Session::Post);
+        assert_eq!(NYSE.session(utc(2024, 7, 11, 0, 30)), Session::Closed);
+        assert_eq!(NYSE.session(utc(2024, 7, 13, 15, 0)), Session::Closed); // Saturday
+        // Early close the day after Thanksgiving
+        assert_eq!(NYSE.session(utc(2024, 11, 29, 18, 30)), Session::Post);
+        assert_eq!(XETRA.session(utc(2024, 1, 10, 8, 0)), Session::Open);
+        assert_eq!(XETRA.session(utc(2024, 1, 10, 16, 30)), Session::Closed);
+
+        assert_eq!(session_range("SAP.DE", utc(2024, 7, 10, 12, 0).timestamp()), Some((utc(2024, 7, 10, 7, 0).timestamp(), utc(2024, 7, 10, 15, 30).timestamp())));
+        assert_eq!(session_range("SAP.DE", utc(2024, 7, 13, 12, 0).timestamp()), None);
+    }
+
+    #[test]
+    fn pauses_refreshes_while_closed() {
+        // Saturday: the data fetched Friday night already has the final prices
+        let saturday = utc(2024, 7, 13, 15, 0);
+        assert!(!is_outdated_at("AAPL", utc(2024, 7, 13, 1, 0), true, saturday));
+        assert!(is_outdated_at("AAPL", utc(2024, 7, 12, 19, 0), false, saturday));
+        // Open, or no calendar: the 30 minute rule
+        let open = utc(2024, 7, 10, 15, 0);
+        assert!(!is_outdated_at("AAPL", open - Duration::minutes(10), false, open));
+        assert!(is_outdated_at("AAPL", open - Duration::minutes(40), false, open));
+        assert!(is_outdated_at("EURUSD=X", saturday - Duration::minutes(40), false, saturday));
+    }
+}
@@ -0,0 +1,215 @@
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use winit::dpi::PhysicalPosition;
+use winit::event_loop::EventLoopProxy;
+use winit::platform::x11::{WindowAttributesExtX11, WindowType};
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::window::{CursorIcon, ResizeDirection, Window, WindowAttributes};
+use x11rb::connection::Connection;
+use x11rb::properties::WmHints;
+use x11rb::protocol::xproto::{AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask, PropMode, StackMode};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use crate::common::UserEvent;
+
+// Border of an unlocked widget that resizes instead of moving it, same as the Windows hit test
+const RESIZE_BORDER: f64 = 8.0;
+
+// _NET_WM_STATE client message actions and the EWMH "all desktops" value of _NET_WM_DESKTOP
+const NET_WM_STATE_ADD: u32 = 1;
+const SOURCE_APPLICATION: u32 = 1;
+const ALL_DESKTOPS: u32 = 0xFFFFFFFF;
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_WM_STATE,
+        _NET_WM_STATE_BELOW,
+        _NET_WM_STATE_STICKY,
+        _NET_WM_STATE_SKIP_PAGER,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_DESKTOP,
+    }
+}
+
+/// Our own connection to the X server, for the EWMH hints winit has no API for.
+struct X11 {
+    conn: RustConnection,
+    root: u32,
+    atoms: Atoms,
+}
+
+impl X11 {
+    fn get() -> Option<&'static X11> {
+        static X11: OnceLock<Option<X11>> = OnceLock::new();
+        X11.get_or_init(|| {
+            let connect = || -> Result<X11, Box<dyn Error>> {
+                let (conn, screen) = x11rb::connect(None)?;
+                let root = conn.setup().roots[screen].root;
+                let atoms = Atoms::new(&conn)?.reply()?;
+                Ok(X11 { conn, root, atoms })
+            };
+            connect().map_err(|e| log::error!("Failed to connect to the X server: {}", e)).ok()
+        }).as_ref()
+    }
+
+    fn states(&self) -> [u32; 4] {
+        let atoms = &self.atoms;
+        [atoms._NET_WM_STATE_BELOW, atoms._NET_WM_STATE_STICKY, atoms._NET_WM_STATE_SKIP_PAGER, atoms._NET_WM_STATE_SKIP_TASKBAR]
+    }
+
+    // Requests to change the state of a mapped window go to the window manager through the root window
+    fn send(&self, window: u32, message_type: u32, data: [u32; 5]) -> Result<(), Box<dyn Error>> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        self.conn.send_event(false, self.root, EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY, event)?;
+        Ok(())
+    }
+}
+
+/// X11 id of the window, None on Wayland.
+fn x11_window(window: &Window) -> Option<u32> {
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Xlib(handle) => Some(handle.window as u32),
+        RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+        _ => None,
+    }
+}
+
+fn resize_direction(window: &Window, position: PhysicalPosition<f64>) -> Option<ResizeDirection> {
+    let size = window.inner_size();
+    let left = position.x < RESIZE_BORDER;
+    let right = position.x >= size.width as f64 - RESIZE_BORDER;
+    let top = position.y < RESIZE_BORDER;
+    let bottom = position.y >= size.height as f64 - RESIZE_BORDER;
+
+    Some(match (left, right, top, bottom) {
+        (true, _, true, _) => ResizeDirection::NorthWest,
+        (_, true, true, _) => ResizeDirection::NorthEast,
+        (true, _, _, true) => ResizeDirection::SouthWest,
+        (_, true, _, true) => ResizeDirection::SouthEast,
+        (true, ..) => ResizeDirection::West,
+        (_, true, ..) => ResizeDirection::East,
+        (_, _, true, _) => ResizeDirection::North,
+        (.., true) => ResizeDirection::South,
+        _ => return None,
+    })
+}
+
+/// Notification servers take the app name with every notification, there is nothing to register.
+pub fn register_app_id(_app_id: &str, _display_name: &str, _icon_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+/// The tray icon (libappindicator) needs gtk, initialized on the thread that creates it.
+pub fn init() {
+    if let Err(e) = gtk::init() {
+        log::error!("Failed to initialize gtk, the tray icon won't work: {}", e);
+    }
+}
+
+/// Runs pending gtk events (tray menu clicks) on the winit thread, called whenever the event loop wakes.
+pub fn pump_events() {
+    if gtk::is_initialized_main_thread() {
+        while gtk::events_pending() {
+            gtk::main_iteration_do(false);
+        }
+    }
+}
+
+/// Desktop windows belong to the desktop layer of X11 window managers.
+/// Wayland has no such hint for regular toplevels, widgets show up there like any window.
+pub fn widget_attributes(attributes: WindowAttributes) -> WindowAttributes {
+    attributes
+        .with_name("desktop-widget-rs", "desktop-widget-rs")
+        .with_x11_window_type(vec![WindowType::Desktop])
+        .with_active(false)
+}
+
+/// Sets the EWMH state of the still unmapped widget: below other windows, on all workspaces,
+/// out of taskbar and pager. WM_HINTS keeps the window manager from focusing it on click.
+// Shadows are up to the compositor
+pub fn init_widget_window(window: &Window) {
+    let Some((x11, window)) = X11::get().zip(x11_window(window)) else { return };
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        x11.conn.change_property32(PropMode::REPLACE, window, x11.atoms._NET_WM_STATE, AtomEnum::ATOM, &x11.states())?;
+        x11.conn.change_property32(PropMode::REPLACE, window, x11.atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL, &[ALL_DESKTOPS])?;
+        WmHints { input: Some(false), ..WmHints::new() }.set(&x11.conn, window)?;
+        x11.conn.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        log::error!("Failed to set the widget's window hints: {}", e);
+    }
+}
+
+/// Resets the resize cursor of the borders once the widget is locked.
+pub fn set_locked(window: &Window, locked: bool) {
+    if locked {
+        window.set_cursor(CursorIcon::Default);
+    }
+}
+
+/// Keeps the widget below all other windows; window managers may drop the state of a
+/// window once it's mapped, so it is requested again and the window restacked.
+pub fn force_to_bottom(window: &Window) {
+    let Some((x11, window)) = X11::get().zip(x11_window(window)) else { return };
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        let atoms = &x11.atoms;
+        let [below, sticky, skip_pager, skip_taskbar] = x11.states();
+        x11.send(window, atoms._NET_WM_STATE, [NET_WM_STATE_ADD, below, sticky, SOURCE_APPLICATION, 0])?;
+        x11.send(window, atoms._NET_WM_STATE, [NET_WM_STATE_ADD, skip_pager, skip_taskbar, SOURCE_APPLICATION, 0])?;
+        x11.send(window, atoms._NET_WM_DESKTOP, [ALL_DESKTOPS, SOURCE_APPLICATION, 0, 0, 0])?;
+        x11.conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::BELOW))?;
+        x11.conn.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        log::error!("Failed to move the widget to the bottom: {}", e);
+    }
+}
+
+/// Shows the resize cursor while an unlocked widget's border is hovered.
+pub fn update_cursor(window: &Window, position: PhysicalPosition<f64>, locked: bool) {
+    let cursor = match resize_direction(window, position).filter(|_| !locked) {
+        Some(direction) => CursorIcon::from(direction),
+        None => CursorIcon::Default,
+    };
+    window.set_cursor(cursor);
+}
+
+/// Resizes the widget when pressed on its border, moves it otherwise.
+pub fn drag(window: &Window, position: PhysicalPosition<f64>) {
+    let _ = match resize_direction(window, position) {
+        Some(direction) => window.drag_resize_window(direction),
+        None => window.drag_window(),
+    };
+}
+
+/// Shows a notification through the desktop's notification server. Clicking it opens the settings.
+pub fn show_toast(app_id: &str, title: &str, body: &str, proxy: EventLoopProxy<UserEvent>) -> Result<(), Box<dyn Error>> {
+    let handle = notify_rust::Notification::new()
+        .appname(app_id)
+        .summary(title)
+        .body(body)
+        .action("default", title)
+        .show()?;
+
+    // Blocks until the notification is clicked or closed
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action == "default" {
+                let _ = proxy.send_event(UserEvent::OpenSettings);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub fn hide_console(command: &mut Command) -> &mut Command {
+    command
+}
+
+/// Linux binaries always keep the terminal they were started from.
+pub fn attach_console() {}
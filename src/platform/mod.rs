@@ -0,0 +1,12 @@
+//! Everything the widget host needs from the OS beyond winit: window hints, the tray's
+//! toolkit, notifications and process details. Each target provides the same set of functions.
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub use self::windows::*;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use self::linux::*;
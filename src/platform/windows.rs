@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::path::Path;
+use std::ffi::c_void;
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+use winit::dpi::PhysicalPosition;
+use winit::event_loop::EventLoopProxy;
+use winit::platform::windows::WindowAttributesExtWindows;
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::window::{Window, WindowAttributes};
+use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+use ::windows::{
+    UI::Notifications::{ToastNotification, ToastNotificationManager, ToastTemplateType},
+    core::HSTRING,
+    Foundation::TypedEventHandler,
+};
+use windows_sys::Win32::Graphics::Dwm::DwmExtendFrameIntoClientArea;
+use windows_sys::Win32::UI::Controls::MARGINS;
+use windows_sys::Win32::Foundation::{HWND, WPARAM, LPARAM, LRESULT, RECT, FARPROC, BOOL};
+use windows_sys::Win32::UI::Shell::{SetWindowSubclass, DefSubclassProc};
+use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowRect, SetWindowPos, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTLEFT, HTRIGHT, HTTOP, HTTOPLEFT, HTTOPRIGHT, SWP_NOMOVE, SWP_NOSIZE, SWP_NOACTIVATE};
+use windows_sys::Win32::System::LibraryLoader::{LoadLibraryA, GetProcAddress};
+use crate::common::UserEvent;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct ACCENT_POLICY {
+    AccentState: u32,
+    AccentFlags: u32,
+    GradientColor: u32,
+    AnimationId: u32,
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct WINDOWCOMPOSITIONATTRIBDATA {
+    Attrib: u32,
+    pvData: *mut c_void,
+    cbData: usize,
+}
+
+#[derive(PartialEq)]
+#[repr(C)]
+#[allow(non_camel_case_types)]
+// #[allow(dead_code)]
+enum ACCENT_STATE {
+    // ACCENT_DISABLED = 0,
+    // ACCENT_ENABLE_GRADIENT = 1,
+    // ACCENT_ENABLE_TRANSPARENTGRADIENT = 2,
+    // ACCENT_ENABLE_BLURBEHIND = 3,
+    ACCENT_ENABLE_ACRYLICBLURBEHIND = 4,
+    // ACCENT_INVALID_STATE = 5,
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _uid_subclass: usize,
+    _dw_ref_data: usize,
+) -> LRESULT {
+    const WM_NCHITTEST: u32 = 0x0084;
+    const WM_MOUSEACTIVATE: u32 = 0x0021;
+    const MA_NOACTIVATE: LRESULT = 3;
+
+    // Prevent activation on click
+    if msg == WM_MOUSEACTIVATE {
+        return MA_NOACTIVATE;
+    }
+
+    // Only handle Resize if Unlocked (ref_data == 0)
+    let locked = _dw_ref_data == 1;
+
+    if msg == WM_NCHITTEST && !locked {
+        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        // SAFETY: GetWindowRect is called with a valid HWND and pointer to RECT.
+        unsafe { GetWindowRect(hwnd, &mut rect) };
+        
+        let x = (lparam & 0xFFFF) as i16 as i32;
+        let y = ((lparam >> 16) & 0xFFFF) as i16 as i32;
+        
+        let border_width = 8;
+        let bottom_border_height = 8; 
+
+        let left = x < rect.left + border_width;
+        let right = x >= rect.right - border_width;
+        let top = y < rect.top + border_width;
+        let bottom = y >= rect.bottom - bottom_border_height;
+
+        if top && left { return HTTOPLEFT as LRESULT; }
+        if top && right { return HTTOPRIGHT as LRESULT; }
+        if bottom && left { return HTBOTTOMLEFT as LRESULT; }
+        if bottom && right { return HTBOTTOMRIGHT as LRESULT; }
+        if left { return HTLEFT as LRESULT; }
+        if right { return HTRIGHT as LRESULT; }
+        if top { return HTTOP as LRESULT; }
+        if bottom { return HTBOTTOM as LRESULT; }
+    }
+
+    // SAFETY: DefSubclassProc is safe to call with valid HWND.
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+fn get_function_impl(library: &str, function: &str) -> Option<FARPROC> {
+    let module = unsafe { LoadLibraryA(library.as_ptr()) };
+    if module == 0 {
+        return None;
+    }
+    Some(unsafe { GetProcAddress(module, function.as_ptr()) })
+}
+
+unsafe fn set_window_composition_attribute(hwnd: HWND, accent_state: ACCENT_STATE, color: Option<(u8, u8, u8, u8)>) {
+    type SetWindowCompositionAttributeFn = unsafe extern "system" fn(HWND, *mut WINDOWCOMPOSITIONATTRIBDATA) -> BOOL;
+
+    let library = "user32.dll\0";
+    let function = "SetWindowCompositionAttribute\0";
+
+    if let Some(proc) = get_function_impl(library, function) {
+        // SAFETY: Casting FARPROC to function pointer signature we expect.
+        let set_window_composition_attribute: SetWindowCompositionAttributeFn = unsafe { std::mem::transmute(proc) };
+        
+        let mut color = color.unwrap_or((0, 0, 0, 0));
+        let is_acrylic = accent_state == ACCENT_STATE::ACCENT_ENABLE_ACRYLICBLURBEHIND;
+        if is_acrylic && color.3 == 0 {
+             color.3 = 1;
+        }
+
+        let mut policy = ACCENT_POLICY {
+            AccentState: accent_state as u32,
+            AccentFlags: if is_acrylic { 0 } else { 2 },
+            GradientColor: (color.0 as u32)
+                | ((color.1 as u32) << 8)
+                | ((color.2 as u32) << 16)
+                | ((color.3 as u32) << 24),
+            AnimationId: 0,
+        };
+
+        let mut data = WINDOWCOMPOSITIONATTRIBDATA {
+            Attrib: 0x13, // WCA_ACCENT_POLICY
+            pvData: &mut policy as *mut _ as *mut c_void,
+            cbData: std::mem::size_of_val(&policy),
+        };
+
+        // SAFETY: Calling loaded function pointer.
+        unsafe { set_window_composition_attribute(hwnd, &mut data) };
+    }
+}
+
+
+fn hwnd(window: &Window) -> Option<HWND> {
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(handle.hwnd.get() as HWND),
+        _ => None,
+    }
+}
+
+// TODO: might want to delete as well
+/// Registers the AUMID in the registry, toasts are only shown for registered ids.
+pub fn register_app_id(aum_id: &str, display_name: &str, icon_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    // HKCU\SOFTWARE\Classes\AppUserModelId\desktop-widget-rs
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let (key, _) = hkcu.create_subkey(format!(r"SOFTWARE\Classes\AppUserModelId\{}", aum_id))?;
+
+    key.set_value("DisplayName", &display_name)?;
+
+    if let Some(path) = icon_path {
+        key.set_value("IconUri", &path.to_string_lossy().to_string())?;
+    } else {
+        let _ = key.delete_value("IconUri");
+    }
+
+    Ok(())
+}
+
+/// The tray icon runs on the Win32 message loop winit already pumps, nothing to set up.
+pub fn init() {}
+
+pub fn pump_events() {}
+
+pub fn widget_attributes(attributes: WindowAttributes) -> WindowAttributes {
+    attributes.with_skip_taskbar(true)
+}
+
+/// Lets the DWM draw a shadow and blurs what's behind the transparent widget.
+// The window-shadows crate is deprecated and incompatible with our winit version, so the shadow is applied manually via windows-sys
+pub fn init_widget_window(window: &Window) {
+    let Some(hwnd) = hwnd(window) else { return };
+    let margins = MARGINS {
+        cxLeftWidth: 1,
+        cxRightWidth: 1,
+        cyTopHeight: 1,
+        cyBottomHeight: 1,
+    };
+    unsafe {
+        DwmExtendFrameIntoClientArea(hwnd, &margins);
+
+        // Manual Acrylic Application
+        set_window_composition_attribute(
+            hwnd, 
+            ACCENT_STATE::ACCENT_ENABLE_ACRYLICBLURBEHIND, 
+            Some((18, 18, 18, 125))
+        );
+    }
+}
+
+/// Widgets never take focus on click; while unlocked their borders resize them.
+pub fn set_locked(window: &Window, locked: bool) {
+    let Some(hwnd) = hwnd(window) else { return };
+    let ref_data = if locked { 1 } else { 0 };
+    unsafe {
+        SetWindowSubclass(hwnd, Some(subclass_proc), 1, ref_data);
+    }
+}
+
+/// Keeps the widget below all other windows (AlwaysOnBottom alone doesn't survive every activation).
+pub fn force_to_bottom(window: &Window) {
+    let Some(hwnd) = hwnd(window) else { return };
+    unsafe {
+        SetWindowPos(hwnd, 1 as HWND, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE);
+    }
+}
+
+/// The cursor over the borders comes from the hit test of `subclass_proc`.
+pub fn update_cursor(_window: &Window, _position: PhysicalPosition<f64>, _locked: bool) {}
+
+/// Moves the widget; presses on its borders never get here, `subclass_proc` turns them into resizes.
+pub fn drag(window: &Window, _position: PhysicalPosition<f64>) {
+    let _ = window.drag_window();
+}
+
+/// Shows a two line toast notification. Clicking it opens the settings.
+pub fn show_toast(aum_id: &str, title: &str, body: &str, proxy: EventLoopProxy<UserEvent>) -> Result<(), Box<dyn Error>> {
+    // Create Toast XML
+    let toast_xml = ToastNotificationManager::GetTemplateContent(ToastTemplateType::ToastText02)?;
+
+    let text_nodes = toast_xml.GetElementsByTagName(&HSTRING::from("text"))?;
+    text_nodes.Item(0)?.AppendChild(&toast_xml.CreateTextNode(&HSTRING::from(title))?)?;
+    text_nodes.Item(1)?.AppendChild(&toast_xml.CreateTextNode(&HSTRING::from(body))?)?;
+
+    // Create Toast
+    let toast = ToastNotification::CreateToastNotification(&toast_xml)?;
+
+    // Handle Click
+    toast.Activated(&TypedEventHandler::new(move |_, _| {
+        let _ = proxy.send_event(UserEvent::OpenSettings);
+        Ok(())
+    }))?;
+
+    // Show Toast
+    let notifier = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(aum_id))?;
+    notifier.Show(&toast)?;
+
+    Ok(())
+}
+
+/// Keeps a console window from flashing up for spawned instances of ourselves.
+pub fn hide_console(command: &mut Command) -> &mut Command {
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    command.creation_flags(CREATE_NO_WINDOW)
+}
+
+// Release builds use the windows subsystem and start without a console, print to the one we were started from
+pub fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
@@ -0,0 +1,549 @@
+use serde::{Deserialize, Serialize};
+use winit::window::{Window, WindowId};
+use winit::event_loop::ActiveEventLoop;
+use winit::event::{WindowEvent, ElementState, MouseButton};
+use winit::dpi::PhysicalPosition;
+use std::rc::Rc;
+use softbuffer::{Context, Surface};
+use std::num::NonZeroU32;
+use std::collections::HashMap;
+use crate::chart::{blit_rgba, create_widget_window, draw_unlocked_frame, show_widget};
+use crate::platform;
+use crate::common::WindowHandler;
+use crate::config::{PortfolioConfig, WidgetConfig};
+use crate::currency::{self, FxRates};
+use crate::fetcher::{FetchService, SymbolData};
+use crate::language::Language;
+use crate::provider::QuoteSeries;
+use crate::render::{portfolio_height, render_portfolio};
+
+// Positions are valued at the latest intraday quote, the day's change is measured from its first one
+const TIMEFRAME: &str = "1D";
+const DEFAULT_WIDTH: u32 = 640;
+
+/// One holding. Positions live in `AppConfig` and are shown by the portfolio widget.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    pub quantity: f64,
+    pub average_cost: f64, // Per share, in `currency`
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+/// Latest price of a symbol and the price its daily change is measured against (the day's first quote).
+#[derive(Debug, Clone, Copy)]
+pub struct LastPrice {
+    pub price: f64,
+    pub reference: f64,
+}
+
+/// A position valued at its latest price. Price dependent fields are None until a quote arrived.
+#[derive(Debug, Clone)]
+pub struct PositionSummary {
+    pub symbol: String,
+    pub currency: String,
+    pub quantity: f64,
+    pub price: Option<f64>,
+    pub value: Option<f64>,
+    pub profit: Option<f64>,
+    pub profit_percent: Option<f64>,
+    pub day_change_percent: Option<f64>,
+    pub allocation_percent: Option<f64>, // Share of the total value in the same currency
+}
+
+/// Sum over all priced positions of one currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioTotal {
+    pub currency: String,
+    pub value: f64,
+    pub cost: f64,
+    pub profit: f64,
+    pub profit_percent: f64,
+    pub day_change: f64,
+    pub day_change_percent: f64,
+}
+
+/// Latest price of `series` and its reference in `currency`, the currency of the position's cost.
+/// None while the quote is in another currency without a known rate: counting it as the
+/// position's currency would mix e.g. USD into a EUR total, or pence into a cost in pounds.
+pub fn last_price(series: &QuoteSeries, currency: &str, fx: Option<&FxRates>) -> Option<LastPrice> {
+    let convert = |amount: f64| currency::convert_units(amount, &series.currency, currency)
+        .or_else(|| fx?.convert(amount, &series.currency, currency));
+    Some(LastPrice {
+        price: convert(series.candles.last()?.close)?,
+        reference: convert(series.candles.first()?.open)?,
+    })
+}
+
+fn percent(part: f64, base: f64) -> f64 {
+    if base != 0.0 { part / base * 100.0 } else { 0.0 }
+}
+
+/// Values every position and sums them up per currency. Positions without a price yet are listed
+/// but left out of the totals, so the total P/L only compares cost and value of the same holdings.
+pub fn summarize(positions: &[Position], prices: &HashMap<String, LastPrice>) -> (Vec<PositionSummary>, Vec<PortfolioTotal>) {
+    let mut rows = Vec::new();
+    let mut totals: Vec<PortfolioTotal> = Vec::new();
+
+    for position in positions {
+        let cost = position.quantity * position.average_cost;
+        let last = prices.get(&position.symbol);
+        let value = last.map(|l| l.price * position.quantity);
+        rows.push(PositionSummary {
+            symbol: position.symbol.clone(),
+            currency: position.currency.clone(),
+            quantity: position.quantity,
+            price: last.map(|l| l.price),
+            value,
+            profit: value.map(|v| v - cost),
+            profit_percent: value.map(|v| percent(v - cost, cost)),
+            day_change_percent: last.map(|l| percent(l.price - l.reference, l.reference)),
+            allocation_percent: None,
+        });
+
+        let Some(last) = last else { continue };
+        let total = match totals.iter_mut().find(|t| t.currency == position.currency) {
+            Some(total) => total,
+            None => {
+                totals.push(PortfolioTotal {
+                    currency: position.currency.clone(),
+                    value: 0.0,
+                    cost: 0.0,
+                    profit: 0.0,
+                    profit_percent: 0.0,
+                    day_change: 0.0,
+                    day_change_percent: 0.0,
+                });
+                totals.last_mut().unwrap()
+            }
+        };
+        total.value += last.price * position.quantity;
+        total.cost += cost;
+        total.day_change += (last.price - last.reference) * position.quantity;
+    }
+
+    for total in &mut totals {
+        total.profit = total.value - total.cost;
+        total.profit_percent = percent(total.profit, total.cost);
+        total.day_change_percent = percent(total.day_change, total.value - total.day_change);
+    }
+    for row in &mut rows {
+        let total = totals.iter().find(|t| t.currency == row.currency);
+        row.allocation_percent = row.value.zip(total).map(|(value, total)| percent(value, total.value));
+    }
+
+    (rows, totals)
+}
+
+/// Sums up the per currency totals in the base currency of `rates`, at today's rates.
+/// None while a rate is missing, a partial sum would look like a loss.
+pub fn total_in(totals: &[PortfolioTotal], rates: &FxRates) -> Option<PortfolioTotal> {
+    let mut sum = PortfolioTotal {
+        currency: rates.base().to_string(),
+        value: 0.0,
+        cost: 0.0,
+        profit: 0.0,
+        profit_percent: 0.0,
+        day_change: 0.0,
+        day_change_percent: 0.0,
+    };
+    for total in totals {
+        let rate = rates.rate(&total.currency)?;
+        sum.value += total.value * rate;
+        sum.cost += total.cost * rate;
+        sum.day_change += total.day_change * rate;
+    }
+    sum.profit = sum.value - sum.cost;
+    sum.profit_percent = percent(sum.profit, sum.cost);
+    sum.day_change_percent = percent(sum.day_change, sum.value - sum.day_change);
+    Some(sum)
+}
+
+/// Parses positions from CSV with the header `symbol,quantity,average_cost,currency`.
+/// The currency column is optional and defaults to USD.
+pub fn from_csv(input: &str) -> Result<Vec<Position>, String> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(input.as_bytes());
+    let mut positions: Vec<Position> = Vec::new();
+
+    for (index, record) in reader.deserialize::<Position>().enumerate() {
+        // Line 1 is the header
+        let line = index + 2;
+        let position = record.map_err(|e| format!("line {}: {}", line, e))?;
+        let position = normalize(position, &positions).map_err(|e| format!("line {}: {}", line, e))?;
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+/// Applies the CSV import rules to positions from elsewhere, e.g. an IPC client.
+pub fn validate(input: Vec<Position>) -> Result<Vec<Position>, String> {
+    let mut positions: Vec<Position> = Vec::new();
+    for position in input {
+        let position = normalize(position, &positions)?;
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+fn normalize(mut position: Position, existing: &[Position]) -> Result<Position, String> {
+    position.symbol = position.symbol.trim().to_uppercase();
+    position.currency = normalize_currency(&position.currency);
+
+    if position.symbol.is_empty() {
+        return Err("missing symbol".to_string());
+    }
+    if !position.quantity.is_finite() || position.quantity == 0.0 {
+        return Err(format!("quantity of {} must be a non-zero number", position.symbol));
+    }
+    if !position.average_cost.is_finite() || position.average_cost < 0.0 {
+        return Err(format!("average cost of {} must not be negative", position.symbol));
+    }
+    if existing.iter().any(|p| p.symbol == position.symbol) {
+        return Err(format!("{} is listed twice", position.symbol));
+    }
+    Ok(position)
+}
+
+// Codes are upper case, except for the subunits exchanges quote in ("GBp")
+fn normalize_currency(code: &str) -> String {
+    let code = code.trim();
+    if currency::is_subunit(code) { code.to_string() } else { code.to_uppercase() }
+}
+
+/// Writes positions in the format `from_csv` reads.
+pub fn to_csv(positions: &[Position]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for position in positions {
+        writer.serialize(position).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Parses the quick-add input of the settings: "SYMBOL QUANTITY AVERAGE_COST [CURRENCY]".
+pub fn parse_position(input: &str) -> Option<Position> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let (symbol, quantity, cost, currency) = match parts.as_slice() {
+        [symbol, quantity, cost] => (symbol, quantity, cost, None),
+        [symbol, quantity, cost, currency] => (symbol, quantity, cost, Some(currency)),
+        _ => return None,
+    };
+    let quantity: f64 = quantity.replace(',', ".").parse().ok().filter(|q: &f64| q.is_finite() && *q != 0.0)?;
+    let average_cost: f64 = cost.replace(',', ".").parse().ok().filter(|c: &f64| c.is_finite() && *c >= 0.0)?;
+    Some(Position {
+        symbol: symbol.to_uppercase(),
+        quantity,
+        average_cost,
+        currency: currency.map(|c| normalize_currency(c)).unwrap_or_else(default_currency),
+    })
+}
+
+/// Table widget valuing the configured positions with the "1D" quotes from the `FetchService`.
+pub struct PortfolioWindow {
+    window: Rc<Window>,
+    surface: Surface<Rc<Window>, Rc<Window>>,
+    _context: Context<Rc<Window>>,
+    positions: Vec<Position>,
+    data: SymbolData, // 1D series of the positions' symbols
+    language: Language,
+    fx: Option<FxRates>,
+    locked: bool,
+    cursor_position: PhysicalPosition<f64>,
+}
+
+impl PortfolioWindow {
+    pub fn new(event_loop: &ActiveEventLoop, fetcher: FetchService, positions: Vec<Position>, language: Language, config: Option<PortfolioConfig>) -> Self {
+        let placement = config.as_ref().map(|c| (c.x, c.y, c.width, c.height));
+        let window = create_widget_window(event_loop, "Portfolio", placement);
+        if config.is_none() {
+            let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(DEFAULT_WIDTH, portfolio_height(positions.len(), 1)));
+        }
+
+        let context = Context::new(window.clone()).unwrap();
+        let mut surface = Surface::new(&context, window.clone()).unwrap();
+
+        let size = window.inner_size();
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+             surface.resize(width, height).unwrap();
+        }
+
+        // Persisted data is shown right away, marked as stale until the network answers
+        let data = SymbolData::new(fetcher, position_symbols(&positions), TIMEFRAME.to_string(), None);
+
+        let portfolio = Self {
+            window,
+            surface,
+            _context: context,
+            positions,
+            data,
+            language,
+            fx: None,
+            locked: true,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+        };
+
+        platform::set_locked(&portfolio.window, true);
+        // Shown right away, unlike the other widgets: it was opened on purpose and may have no positions yet
+        show_widget(&portfolio.window);
+        portfolio.data.refresh();
+
+        portfolio
+    }
+}
+
+// Several positions (e.g. lots bought in different currencies) may hold the same symbol
+fn position_symbols(positions: &[Position]) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+    for position in positions {
+        if !symbols.contains(&position.symbol) {
+            symbols.push(position.symbol.clone());
+        }
+    }
+    symbols
+}
+
+impl WindowHandler for PortfolioWindow {
+    fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        platform::set_locked(&self.window, locked);
+        self.window.request_redraw();
+    }
+
+    fn set_language(&mut self, language: Language) {
+        self.language = language;
+        self.window.request_redraw();
+    }
+
+    fn set_fx_rates(&mut self, rates: Option<&FxRates>, _convert_prices: bool) {
+        self.fx = rates.cloned();
+        self.window.request_redraw();
+    }
+
+    fn set_positions(&mut self, positions: Vec<Position>) {
+        self.data.set_symbols(position_symbols(&positions));
+        self.positions = positions;
+        self.data.refresh();
+        self.window.request_redraw();
+    }
+
+    fn refresh(&mut self) {
+        self.data.refresh();
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.data.symbols().to_vec()
+    }
+
+    fn get_config(&self) -> Option<WidgetConfig> {
+        let size = self.window.inner_size();
+        let pos = self.window.outer_position().unwrap_or(winit::dpi::PhysicalPosition::new(0, 0));
+        Some(WidgetConfig::Portfolio(PortfolioConfig {
+            x: pos.x,
+            y: pos.y,
+            width: size.width,
+            height: size.height,
+        }))
+    }
+
+    fn handle_event(&mut self, event: WindowEvent, _event_loop: &ActiveEventLoop) {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.resize(size);
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = position;
+                platform::update_cursor(&self.window, position, self.locked);
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if !self.locked => {
+                platform::drag(&self.window, self.cursor_position);
+            },
+            WindowEvent::RedrawRequested => {
+                self.redraw();
+            },
+            _ => (),
+        }
+    }
+
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            self.surface.resize(width, height).unwrap();
+            self.window.request_redraw();
+        }
+    }
+
+    fn update_data(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries) {
+        if self.data.update(symbol, timeframe, series) {
+            self.window.request_redraw();
+        }
+    }
+
+    fn redraw(&mut self) {
+        if let Ok(mut buffer) = self.surface.buffer_mut() {
+            buffer.fill(0);
+
+            let width = buffer.width().get();
+            let height = buffer.height().get();
+
+            let prices: HashMap<String, LastPrice> = self.positions.iter()
+                .filter_map(|position| {
+                    let series = self.data.get(&position.symbol)?;
+                    Some((position.symbol.clone(), last_price(series, &position.currency, self.fx.as_ref())?))
+                })
+                .collect();
+            let (rows, totals) = summarize(&self.positions, &prices);
+            // Only worth a row when something actually needs converting
+            let base_total = self.fx.as_ref()
+                .filter(|fx| totals.iter().any(|t| t.currency != fx.base()))
+                .and_then(|fx| total_in(&totals, fx));
+            let rendered = render_portfolio(&rows, &totals, base_total.as_ref(), self.language, width, height);
+
+            let (min_width, min_height) = rendered.min_size;
+            self.window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_width as f64, min_height as f64)));
+
+            blit_rgba(&mut buffer, &rendered.rgba);
+
+            if !self.locked {
+                draw_unlocked_frame(&mut buffer, width as i32, height as i32);
+            }
+
+            buffer.present().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, quantity: f64, average_cost: f64, currency: &str) -> Position {
+        Position { symbol: symbol.to_string(), quantity, average_cost, currency: currency.to_string() }
+    }
+
+    #[test]
+    fn summarize_values_positions_per_currency() {
+        let positions = [
+            position("AAPL", 10.0, 100.0, "USD"),
+            position("MSFT", 5.0, 200.0, "USD"),
+            position("SAP.DE", 4.0, 50.0, "EUR"),
+            position("NEW", 1.0, 10.0, "USD"),
+        ];
+        let prices = HashMap::from([
+            ("AAPL".to_string(), LastPrice { price: 120.0, reference: 100.0 }),
+            ("MSFT".to_string(), LastPrice { price: 180.0, reference: 180.0 }),
+            ("SAP.DE".to_string(), LastPrice { price: 60.0, reference: 50.0 }),
+        ]);
+
+        let (rows, totals) = summarize(&positions, &prices);
+
+        assert_eq!(rows[0].value, Some(1200.0));
+        assert_eq!(rows[0].profit, Some(200.0));
+        assert_eq!(rows[0].profit_percent, Some(20.0));
+        assert_eq!(rows[0].day_change_percent, Some(20.0));
+        assert_eq!(rows[1].profit, Some(-100.0));
+        // 1200 of 2100 USD
+        assert!((rows[0].allocation_percent.unwrap() - 57.142).abs() < 0.01);
+        assert_eq!(rows[2].allocation_percent, Some(100.0));
+        // No quote yet: listed, but not part of the totals
+        assert_eq!(rows[3].value, None);
+        assert_eq!(rows[3].allocation_percent, None);
+
+        let usd = totals.iter().find(|t| t.currency == "USD").unwrap();
+        assert_eq!(usd.value, 2100.0);
+        assert_eq!(usd.cost, 2000.0);
+        assert_eq!(usd.profit, 100.0);
+        assert_eq!(usd.day_change, 200.0);
+        assert!((usd.day_change_percent - 10.526).abs() < 0.01);
+        assert_eq!(totals.len(), 2);
+    }
+
+    #[test]
+    fn total_in_converts_to_the_base_currency() {
+        let positions = [position("AAPL", 10.0, 100.0, "USD"), position("SAP.DE", 4.0, 50.0, "EUR")];
+        let prices = HashMap::from([
+            ("AAPL".to_string(), LastPrice { price: 120.0, reference: 100.0 }),
+            ("SAP.DE".to_string(), LastPrice { price: 60.0, reference: 60.0 }),
+        ]);
+        let (_, totals) = summarize(&positions, &prices);
+
+        let mut rates = FxRates::new("EUR");
+        assert_eq!(total_in(&totals, &rates), None);
+
+        rates.set_rate("USD", 0.5);
+        let total = total_in(&totals, &rates).unwrap();
+        assert_eq!(total.currency, "EUR");
+        assert_eq!(total.value, 600.0 + 240.0);
+        assert_eq!(total.cost, 500.0 + 200.0);
+        assert_eq!(total.profit, 140.0);
+        assert_eq!(total.day_change, 100.0);
+    }
+
+    #[test]
+    fn last_price_needs_the_position_currency_or_a_rate() {
+        let candle = |open: f64, close: f64| crate::provider::Candle { timestamp: 0, open, high: open.max(close), low: open.min(close), close, volume: 0 };
+        let series = |currency: &str| QuoteSeries { candles: vec![candle(100.0, 100.0), candle(110.0, 120.0)], currency: currency.to_string() };
+
+        let same = last_price(&series("USD"), "USD", None).unwrap();
+        assert_eq!((same.price, same.reference), (120.0, 100.0));
+        // London quotes in pence, the cost is in pounds
+        let pence = last_price(&series("GBp"), "GBP", None).unwrap();
+        assert_eq!((pence.price, pence.reference), (1.2, 1.0));
+
+        // A US listing held in EUR has no price until the rate is known
+        assert!(last_price(&series("USD"), "EUR", None).is_none());
+        let mut rates = FxRates::new("EUR");
+        assert!(last_price(&series("USD"), "EUR", Some(&rates)).is_none());
+        rates.set_rate("USD", 0.5);
+        let converted = last_price(&series("USD"), "EUR", Some(&rates)).unwrap();
+        assert_eq!((converted.price, converted.reference), (60.0, 50.0));
+
+        let positions = [position("AAPL", 10.0, 100.0, "EUR")];
+        let prices: HashMap<String, LastPrice> = last_price(&series("USD"), "EUR", None)
+            .map(|last| ("AAPL".to_string(), last))
+            .into_iter()
+            .collect();
+        let (rows, totals) = summarize(&positions, &prices);
+        assert_eq!(rows[0].value, None);
+        assert!(totals.is_empty());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let positions = vec![position("AAPL", 10.0, 150.5, "USD"), position("SAP.DE", 3.5, 120.0, "EUR")];
+        let csv = to_csv(&positions).unwrap();
+        assert!(csv.starts_with("symbol,quantity,average_cost,currency\n"));
+        assert_eq!(from_csv(&csv).unwrap(), positions);
+    }
+
+    #[test]
+    fn csv_import_normalizes_and_validates() {
+        let positions = from_csv("symbol,quantity,average_cost\n msft , 2, 300\n").unwrap();
+        assert_eq!(positions, vec![position("MSFT", 2.0, 300.0, "USD")]);
+        let positions = from_csv("symbol,quantity,average_cost,currency\nVOD.L,100,72,GBp\nSAP.DE,1,120,eur\n").unwrap();
+        assert_eq!(positions[0].currency, "GBp");
+        assert_eq!(positions[1].currency, "EUR");
+
+        let err = from_csv("symbol,quantity,average_cost\nMSFT,0,300\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        let err = from_csv("symbol,quantity,average_cost\nMSFT,1,300\nmsft,2,310\n").unwrap_err();
+        assert!(err.starts_with("line 3:"), "{}", err);
+        assert!(from_csv("symbol,quantity,average_cost\nMSFT,ten,300\n").is_err());
+    }
+
+    #[test]
+    fn parses_quick_add_input() {
+        assert_eq!(parse_position("aapl 10 150,5"), Some(position("AAPL", 10.0, 150.5, "USD")));
+        assert_eq!(parse_position("SAP.DE 3 120 eur"), Some(position("SAP.DE", 3.0, 120.0, "EUR")));
+        assert_eq!(parse_position("VOD.L 100 72 GBp"), Some(position("VOD.L", 100.0, 72.0, "GBp")));
+        assert_eq!(parse_position("AAPL 10"), None);
+        assert_eq!(parse_position("AAPL 0 150"), None);
+    }
+}
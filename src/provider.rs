@@ -0,0 +1,198 @@
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use chrono::Utc;
+use time::OffsetDateTime;
+use yahoo_finance_api as yahoo;
+use crate::language::AppError;
+
+pub const DEFAULT_PROVIDER: &str = "yahoo";
+/// Timeframes a chart can show, in display order.
+pub const TIMEFRAMES: [&str; 7] = ["1D", "1W", "1M", "3M", "6M", "YTD", "1Y"];
+
+/// A single OHLCV bar, independent of the data source it came from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub timestamp: i64, // Unix seconds
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuoteSeries {
+    pub candles: Vec<Candle>,
+    pub currency: String,
+}
+
+pub trait MarketDataProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Fetch the candles for `symbol` covering one of the app's timeframes ("1D", "1W", "1M", ...).
+    /// Mapping the timeframe onto source specific intervals/ranges is up to the provider.
+    fn fetch<'a>(&'a self, symbol: &'a str, timeframe: &'a str) -> BoxFuture<'a, Result<QuoteSeries, AppError>>;
+
+    /// Bar interval used for a timeframe (e.g. "5m"), part of the on-disk cache key.
+    fn interval(&self, timeframe: &str) -> &'static str;
+}
+
+/// Resolve the provider named in a `ChartConfig`. Unknown names fall back to the default provider.
+pub fn provider_for(name: Option<&str>) -> Arc<dyn MarketDataProvider> {
+    match name.unwrap_or(DEFAULT_PROVIDER) {
+        "yahoo" => Arc::new(YahooProvider),
+        #[cfg(test)]
+        "memory" => Arc::new(MemoryProvider),
+        other => {
+            log::warn!("Unknown data provider '{}', falling back to {}", other, DEFAULT_PROVIDER);
+            Arc::new(YahooProvider)
+        }
+    }
+}
+
+pub struct YahooProvider;
+
+// Yahoo (interval, range) for each timeframe. "1W" is stitched together from daily 5m requests instead.
+fn yahoo_interval_range(timeframe: &str) -> (&'static str, &'static str) {
+    match timeframe {
+        "1D" => ("2m", "1d"),
+        "1W" => ("5m", "7d"),
+        "1M" => ("1d", "1mo"),
+        "3M" => ("1d", "3mo"),
+        "6M" => ("1d", "6mo"),
+        "1Y" => ("1d", "1y"),
+        "YTD" => ("1d", "ytd"),
+        _ => ("1d", "1mo"),
+    }
+}
+
+impl YahooProvider {
+    fn to_candle(q: &yahoo::Quote) -> Candle {
+        Candle {
+            timestamp: q.timestamp as i64,
+            open: q.open,
+            high: q.high,
+            low: q.low,
+            close: q.close,
+            volume: q.volume,
+        }
+    }
+
+    async fn fetch_week(symbol: &str) -> Result<QuoteSeries, AppError> {
+        let provider = yahoo::YahooConnector::new().map_err(|e| AppError::FetchError(e.to_string()))?;
+
+        // Stitching 7 days of 5m data
+        let mut all_quotes = Vec::new();
+        let now = Utc::now();
+        let start = now - chrono::Duration::days(7);
+
+        let mut currency = "USD".to_string();
+        // Fetch day by day to allow high resolution "5m"
+        for i in 0..8 {
+             let chunk_start = start + chrono::Duration::days(i as i64);
+             let chunk_end = chunk_start + chrono::Duration::days(1);
+
+             if chunk_start > now { break; }
+
+             let sys_start: std::time::SystemTime = chunk_start.into();
+             let sys_end: std::time::SystemTime = chunk_end.into();
+             let odt_start = OffsetDateTime::from(sys_start);
+             let odt_end = OffsetDateTime::from(sys_end);
+
+             if let Ok(response) = provider.get_quote_history_interval(symbol, odt_start, odt_end, "5m").await {
+                 if let Ok(meta) = response.metadata() {
+                     currency = meta.currency.clone().unwrap_or("USD".to_string());
+                 }
+                 if let Ok(quotes) = response.quotes() {
+                     all_quotes.extend(quotes.iter().map(Self::to_candle));
+                 }
+             }
+        }
+
+        // Dedup and sort
+        all_quotes.sort_by_key(|q| q.timestamp);
+        all_quotes.dedup_by_key(|q| q.timestamp);
+
+        if all_quotes.is_empty() {
+            return Err(AppError::WeekDataError);
+        }
+        Ok(QuoteSeries { candles: all_quotes, currency })
+    }
+
+    async fn fetch_range(symbol: &str, timeframe: &str) -> Result<QuoteSeries, AppError> {
+        let provider = yahoo::YahooConnector::new().map_err(|e| AppError::FetchError(e.to_string()))?;
+
+        let (interval, range) = yahoo_interval_range(timeframe);
+        match provider.get_quote_range(symbol, interval, range).await {
+            Ok(response) => {
+                let currency = response.metadata().ok().and_then(|m| m.currency.clone()).unwrap_or("USD".to_string());
+                match response.quotes() {
+                    Ok(quotes) if !quotes.is_empty() => Ok(QuoteSeries {
+                        candles: quotes.iter().map(Self::to_candle).collect(),
+                        currency,
+                    }),
+                    _ => Err(AppError::NoQuotesFound),
+                }
+            },
+            Err(e) => {
+                log::error!("Yahoo API Error for {}: {:?}", symbol, e);
+                Err(AppError::FetchError(e.to_string()))
+            }
+        }
+    }
+}
+
+impl MarketDataProvider for YahooProvider {
+    fn name(&self) -> &'static str {
+        "yahoo"
+    }
+
+    fn fetch<'a>(&'a self, symbol: &'a str, timeframe: &'a str) -> BoxFuture<'a, Result<QuoteSeries, AppError>> {
+        Box::pin(async move {
+            if timeframe == "1W" {
+                Self::fetch_week(symbol).await
+            } else {
+                Self::fetch_range(symbol, timeframe).await
+            }
+        })
+    }
+
+    fn interval(&self, timeframe: &str) -> &'static str {
+        yahoo_interval_range(timeframe).0
+    }
+}
+
+/// Offline provider for tests: every symbol drifts down from 100 by one per bar, "MISSING" has no quotes.
+#[cfg(test)]
+pub struct MemoryProvider;
+
+#[cfg(test)]
+impl MarketDataProvider for MemoryProvider {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn fetch<'a>(&'a self, symbol: &'a str, timeframe: &'a str) -> BoxFuture<'a, Result<QuoteSeries, AppError>> {
+        Box::pin(async move {
+            if symbol == "MISSING" {
+                return Err(AppError::NoQuotesFound);
+            }
+            let step = if self.interval(timeframe) == "1d" { 86_400 } else { 300 };
+            let candles = (0..30)
+                .map(|i| {
+                    let open = 100.0 - i as f64;
+                    Candle { timestamp: 1_700_000_000 + i * step, open, high: open + 0.5, low: open - 1.5, close: open - 1.0, volume: 1000 }
+                })
+                .collect();
+            Ok(QuoteSeries { candles, currency: "USD".to_string() })
+        })
+    }
+
+    fn interval(&self, timeframe: &str) -> &'static str {
+        match timeframe {
+            "1D" | "1W" => "5m",
+            _ => "1d",
+        }
+    }
+}
@@ -0,0 +1,989 @@
+use plotters::prelude::*;
+use plotters::backend::BitMapBackend;
+use chrono::{DateTime, Local};
+use crate::config::ChartStyle;
+use crate::currency;
+use crate::indicators::{self, Indicator};
+use crate::language::{Language, TextId, get_text};
+use crate::layout::{self, PaneHeight};
+use crate::market_hours::Session;
+use crate::portfolio::{PortfolioTotal, PositionSummary};
+use crate::provider::Candle;
+
+// Line colours for indicator overlays, cycled in order
+const INDICATOR_COLORS: [RGBColor; 5] = [
+    RGBColor(0, 191, 255),
+    RGBColor(255, 215, 0),
+    RGBColor(255, 105, 180),
+    RGBColor(186, 85, 211),
+    RGBColor(255, 140, 0),
+];
+
+// Space above the panes for symbol, price and change
+const HEADER_HEIGHT: u32 = 50;
+// Space below the bottom pane for the x axis labels
+const AXIS_HEIGHT: u32 = 50;
+// Minimum window height added per oscillator pane
+const OSCILLATOR_MIN_HEIGHT: u32 = 80;
+// Largest width or height `--render` draws
+const MAX_RENDER_SIZE: u32 = 8192;
+
+/// Everything needed to draw a chart, independent of any window.
+pub struct ChartView<'a> {
+    pub symbol: &'a str,
+    pub currency: &'a str,
+    pub quotes: &'a [Candle],
+    pub timeframe: &'a str,
+    pub style: ChartStyle,
+    pub indicators: &'a [Indicator],
+    pub volume_pane_height: Option<u32>,
+    pub language: Language,
+    pub fetched_at: Option<(DateTime<Local>, bool)>, // Fetch time, restored from the on-disk cache
+    /// Further symbols and their quotes; when set, all symbols are drawn rebased to percent change
+    pub compare: &'a [(&'a str, &'a [Candle])],
+    /// Market state shown as a badge in the header, None for symbols without a known exchange
+    pub session: Option<Session>,
+    /// Regular trading hours (unix seconds) a 1D chart spans
+    pub session_range: Option<(i64, i64)>,
+}
+
+pub struct RenderedChart {
+    /// width * height RGBA pixels. Untouched pixels are fully transparent.
+    pub rgba: Vec<u8>,
+    /// Smallest size the chart fits into
+    pub min_size: (u32, u32),
+}
+
+/// Heikin-Ashi candles: averaged bars that smooth out noise while keeping the trend visible.
+fn heikin_ashi(candles: &[Candle]) -> Vec<Candle> {
+    let mut result: Vec<Candle> = Vec::with_capacity(candles.len());
+    for c in candles {
+        let close = (c.open + c.high + c.low + c.close) / 4.0;
+        let open = match result.last() {
+            Some(prev) => (prev.open + prev.close) / 2.0,
+            None => (c.open + c.close) / 2.0,
+        };
+        result.push(Candle {
+            timestamp: c.timestamp,
+            open,
+            high: c.high.max(open).max(close),
+            low: c.low.min(open).min(close),
+            close,
+            volume: c.volume,
+        });
+    }
+    result
+}
+
+/// Draws the chart into an offscreen buffer of the given size.
+/// Used by the chart windows as well as the headless `--render` mode.
+pub fn render_chart(view: &ChartView, width: u32, height: u32) -> RenderedChart {
+    if !view.compare.is_empty() {
+        return render_comparison(view, width, height);
+    }
+
+    let mut min_size = (0, 0);
+    let mut pixel_buffer = vec![0u8; width as usize * height as usize * 3];
+
+    // A 1D chart spans the trading session, quotes from outside it (e.g. extended hours) are left out
+    let session_range = view.session_range.filter(|_| view.timeframe == "1D");
+    let clipped: Vec<Candle>;
+    let quotes = match session_range {
+        Some((open, close)) => {
+            clipped = view.quotes.iter().filter(|q| q.timestamp >= open && q.timestamp <= close).cloned().collect();
+            if clipped.is_empty() { view.quotes } else { &clipped }
+        },
+        None => view.quotes,
+    };
+
+    if let Some((first_quote, last_quote)) = quotes.first().zip(quotes.last()) {
+        let first_price = first_quote.close;
+        let last_price = last_quote.close;
+        let diff = last_price - first_price;
+        let percent_change = (diff / first_price) * 100.0;
+
+        let color = if diff >= 0.0 { &GREEN } else { &RED };
+        let sign = if diff >= 0.0 { "+" } else { "" };
+
+        let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
+        root.fill(&TRANSPARENT).unwrap(); 
+
+        let font = ("sans-serif", 30).into_font();
+        let padding = 20;
+        let mut current_x = 20;
+
+        // Symbol
+        root.draw_text(view.symbol, &font.clone().color(&WHITE), (current_x, 20)).unwrap();
+        let (w, _) = font.box_size(view.symbol).unwrap();
+        current_x += w as i32 + padding;
+
+        // Price
+        let price_text = currency::format(last_price, view.currency);
+        root.draw_text(&price_text, &font.clone().color(&WHITE), (current_x, 20)).unwrap();
+        let (w, _) = font.box_size(&price_text).unwrap();
+        current_x += w as i32 + padding;
+
+        // Change
+        let change_text = format!("{}{:.2} ({}{:.2}%)", sign, diff, sign, percent_change);
+        root.draw_text(&change_text, &font.clone().color(color), (current_x, 20)).unwrap();
+        let (w, _) = font.box_size(&change_text).unwrap();
+        current_x += w as i32 + padding;
+
+        // Market state badge
+        if let Some(session) = view.session {
+            let (label, badge_color) = match session {
+                Session::Open => (TextId::SessionOpen, GREEN),
+                Session::Pre => (TextId::SessionPre, RGBColor(255, 200, 0)),
+                Session::Post => (TextId::SessionPost, RGBColor(255, 200, 0)),
+                Session::Closed => (TextId::SessionClosed, RGBColor(160, 160, 160)),
+            };
+            let label = get_text(view.language, label);
+            let badge_font = ("sans-serif", 16).into_font();
+            let (w, h) = badge_font.box_size(label).unwrap();
+            let (w, h) = (w as i32 + 12, h as i32 + 6);
+            root.draw(&Rectangle::new([(current_x, 26), (current_x + w, 26 + h)], badge_color.stroke_width(1))).unwrap();
+            root.draw_text(label, &badge_font.color(&badge_color), (current_x + 6, 29)).unwrap();
+            current_x += w + padding;
+        }
+
+        // Volume pane only if enabled and the symbol actually reports volume (indices/FX often don't)
+        let volume_height = match view.volume_pane_height {
+            Some(h) if quotes.iter().any(|q| q.volume > 0) => h,
+            _ => 0,
+        };
+        let oscillators: Vec<Indicator> = view.indicators.iter().copied().filter(|i| i.is_oscillator()).collect();
+
+        // Smallest size that still fits the header and all panes
+        min_size = (current_x as u32, 300 + volume_height + OSCILLATOR_MIN_HEIGHT * oscillators.len() as u32);
+
+        // Chart
+        let (start, end) = match session_range {
+            Some((open, close)) => (open.min(first_quote.timestamp), close.max(last_quote.timestamp)),
+            None => (first_quote.timestamp, last_quote.timestamp),
+        };
+        let start_date = DateTime::from_timestamp(start, 0).unwrap();
+        let end_date = DateTime::from_timestamp(end, 0).unwrap();
+
+        // Heikin-Ashi replaces the candles themselves, everything else draws the raw quotes
+        let ha_candles;
+        let candles: &[Candle] = if view.style == ChartStyle::HeikinAshi {
+            ha_candles = heikin_ashi(quotes);
+            &ha_candles
+        } else {
+            quotes
+        };
+
+        let mut min_price = candles.iter().map(|q| q.low).fold(f64::INFINITY, f64::min);
+        let mut max_price = candles.iter().map(|q| q.high).fold(f64::NEG_INFINITY, f64::max);
+
+        // Bollinger bands can reach outside the price range, keep them visible
+        let closes: Vec<f64> = quotes.iter().map(|q| q.close).collect();
+        for indicator in view.indicators {
+            if let Indicator::Bollinger { period, std_dev } = *indicator {
+                for band in indicators::bollinger(&closes, period, std_dev).into_iter().flatten() {
+                    min_price = min_price.min(band.lower);
+                    max_price = max_price.max(band.upper);
+                }
+            }
+        }
+
+        let range = max_price - min_price;
+        let use_decimals = range < 1.0 || max_price < 2.0;
+
+        let x_labels = (width / 120).max(2) as usize;
+        let y_labels = (height / 60).max(2) as usize;
+
+        let x_label_formatter = |d: &DateTime<chrono::Utc>| {
+            let date = DateTime::from_timestamp(d.timestamp(), 0).unwrap().with_timezone(&Local);
+            let lang = view.language;
+
+            if view.timeframe == "1D" {
+                crate::language::format_time(date)
+            } else if view.timeframe == "1W" {
+                let duration = end_date.signed_duration_since(start_date);
+                if duration.num_days() <= 2 {
+                    crate::language::format_weekday_time(lang, date)
+                } else {
+                    crate::language::format_month_day(lang, date)
+                }
+            } else {
+                crate::language::format_month_day(lang, date)
+            }
+        };
+
+        // Pane layout: price chart on top, then the volume pane and one pane per oscillator.
+        // Only the bottom pane shows the x axis labels.
+        let mut pane_heights = vec![PaneHeight::Flex(3)];
+        if volume_height > 0 {
+            pane_heights.push(PaneHeight::Fixed(volume_height));
+        }
+        pane_heights.extend(oscillators.iter().map(|_| PaneHeight::Flex(1)));
+        let pane_ranges = layout::split_panes(height, HEADER_HEIGHT, AXIS_HEIGHT, &pane_heights);
+        let pane_area = |index: usize| {
+            let r = &pane_ranges[index];
+            root.clone().shrink((0, r.start), (width, r.end - r.start))
+        };
+        let is_last = |index: usize| index + 1 == pane_ranges.len();
+
+        let price_area = pane_area(0);
+        let mut chart = ChartBuilder::on(&price_area)
+            .margin(10)
+            .margin_bottom(if is_last(0) { 10 } else { 0 })
+            .set_label_area_size(LabelAreaPosition::Left, 40)
+            .set_label_area_size(LabelAreaPosition::Bottom, if is_last(0) { 40 } else { 0 })
+            .build_cartesian_2d(start_date..end_date, min_price..max_price)
+            .unwrap();
+
+        chart.configure_mesh()
+            .axis_style(WHITE)
+            .bold_line_style(WHITE.mix(0.3))
+            .light_line_style(TRANSPARENT)
+            .label_style(("sans-serif", 15).into_font().color(&WHITE))
+            .x_labels(x_labels)
+            .y_labels(y_labels)
+            .x_label_formatter(&x_label_formatter)
+            .y_label_formatter(&|y| {
+                if use_decimals {
+                    format!("{:.2}", y)
+                } else {
+                    format!("{:.0}", y)
+                }
+            })
+            .draw().unwrap();
+
+        let to_x = |q: &Candle| DateTime::from_timestamp(q.timestamp, 0).unwrap();
+        let candle_color = |q: &Candle| if q.close >= q.open { GREEN } else { RED };
+        // Bar width in pixels, leaving some space between neighbouring bars
+        let plot_width = chart.plotting_area().dim_in_pixel().0;
+        let bar_width = ((plot_width as f64 / candles.len().max(1) as f64) * 0.7).max(1.0) as u32;
+
+        match view.style {
+            ChartStyle::Line => {
+                chart.draw_series(
+                    LineSeries::new(candles.iter().map(|q| (to_x(q), q.close)), color)
+                ).unwrap();
+            },
+            ChartStyle::Area => {
+                chart.draw_series(
+                    AreaSeries::new(
+                        candles.iter().map(|q| (to_x(q), q.close)),
+                        min_price,
+                        color.mix(0.15).filled(),
+                    )
+                ).unwrap();
+
+                chart.draw_series(
+                    LineSeries::new(candles.iter().map(|q| (to_x(q), q.close)), color)
+                ).unwrap();
+            },
+            ChartStyle::Candlestick | ChartStyle::HeikinAshi => {
+                chart.draw_series(candles.iter().map(|q| {
+                    CandleStick::new(to_x(q), q.open, q.high, q.low, q.close, GREEN.filled(), RED.filled(), bar_width)
+                })).unwrap();
+            },
+            ChartStyle::Ohlc => {
+                let tick = (bar_width / 2).max(1) as i32;
+                for q in candles {
+                    let c = candle_color(q);
+                    // High-Low bar, open tick to the left, close tick to the right
+                    chart.draw_series(std::iter::once(
+                        PathElement::new(vec![(to_x(q), q.low), (to_x(q), q.high)], c)
+                    )).unwrap();
+                    chart.draw_series(std::iter::once(
+                        EmptyElement::at((to_x(q), q.open)) + PathElement::new(vec![(-tick, 0), (0, 0)], c)
+                    )).unwrap();
+                    chart.draw_series(std::iter::once(
+                        EmptyElement::at((to_x(q), q.close)) + PathElement::new(vec![(0, 0), (tick, 0)], c)
+                    )).unwrap();
+                }
+            },
+        }
+
+        // Indicator overlays, always computed from the raw quotes
+        for (i, indicator) in view.indicators.iter().enumerate() {
+            let line_color = INDICATOR_COLORS[i % INDICATOR_COLORS.len()];
+            let line = |values: Vec<Option<f64>>| {
+                LineSeries::new(
+                    quotes.iter().zip(values).filter_map(|(q, v)| v.map(|v| (to_x(q), v))),
+                    line_color.stroke_width(2),
+                )
+            };
+            match *indicator {
+                Indicator::Sma { period } => {
+                    chart.draw_series(line(indicators::sma(&closes, period))).unwrap();
+                },
+                Indicator::Ema { period } => {
+                    chart.draw_series(line(indicators::ema(&closes, period))).unwrap();
+                },
+                Indicator::Vwap => {
+                    chart.draw_series(line(indicators::vwap(quotes))).unwrap();
+                },
+                Indicator::Bollinger { period, std_dev } => {
+                    let bands = indicators::bollinger(&closes, period, std_dev);
+                    chart.draw_series(line(bands.iter().map(|b| b.map(|b| b.middle)).collect())).unwrap();
+                    for edge in [bands.iter().map(|b| b.map(|b| b.upper)).collect::<Vec<_>>(), bands.iter().map(|b| b.map(|b| b.lower)).collect()] {
+                        chart.draw_series(LineSeries::new(
+                            quotes.iter().zip(edge).filter_map(|(q, v)| v.map(|v| (to_x(q), v))),
+                            line_color.mix(0.6),
+                        )).unwrap();
+                    }
+                },
+                // Drawn in their own panes below
+                Indicator::Rsi { .. } | Indicator::Macd { .. } | Indicator::Stochastic { .. } => {},
+            }
+        }
+
+        if volume_height > 0 {
+            let volume_area = pane_area(1);
+            let max_volume = quotes.iter().map(|q| q.volume).max().unwrap_or(1).max(1) as f64;
+            let mut volume_chart = ChartBuilder::on(&volume_area)
+                .margin(10)
+                .margin_top(5)
+                .margin_bottom(if is_last(1) { 10 } else { 0 })
+                .set_label_area_size(LabelAreaPosition::Left, 40)
+                .set_label_area_size(LabelAreaPosition::Bottom, if is_last(1) { 40 } else { 0 })
+                .build_cartesian_2d(start_date..end_date, 0f64..max_volume)
+                .unwrap();
+
+            volume_chart.configure_mesh()
+                .axis_style(WHITE)
+                .bold_line_style(TRANSPARENT)
+                .light_line_style(TRANSPARENT)
+                .label_style(("sans-serif", 15).into_font().color(&WHITE))
+                .x_labels(x_labels)
+                .y_labels(0)
+                .x_label_formatter(&x_label_formatter)
+                .draw().unwrap();
+
+            // Bars take the up/down colour of their (raw) candle
+            volume_chart.draw_series(quotes.iter().map(|q| {
+                let style = candle_color(q).mix(0.6).stroke_width(bar_width);
+                PathElement::new(vec![(to_x(q), 0.0), (to_x(q), q.volume as f64)], style)
+            })).unwrap();
+        }
+
+        // Oscillator panes
+        let first_oscillator_pane = if volume_height > 0 { 2 } else { 1 };
+        for (n, oscillator) in oscillators.iter().enumerate() {
+            let index = first_oscillator_pane + n;
+            let area = pane_area(index);
+            let line_color = INDICATOR_COLORS[n % INDICATOR_COLORS.len()];
+
+            let macd_points = match *oscillator {
+                Indicator::Macd { fast, slow, signal } => indicators::macd(&closes, fast, slow, signal),
+                _ => Vec::new(),
+            };
+            let (y_min, y_max) = if macd_points.is_empty() {
+                (0.0, 100.0)
+            } else {
+                // Symmetric around zero so the histogram baseline stays centered
+                let extent = macd_points.iter().flatten()
+                    .map(|p| p.macd.abs().max(p.signal.abs()).max(p.histogram.abs()))
+                    .fold(0.0, f64::max)
+                    .max(f64::EPSILON);
+                (-extent, extent)
+            };
+
+            let mut osc_chart = ChartBuilder::on(&area)
+                .margin(10)
+                .margin_top(5)
+                .margin_bottom(if is_last(index) { 10 } else { 0 })
+                .set_label_area_size(LabelAreaPosition::Left, 40)
+                .set_label_area_size(LabelAreaPosition::Bottom, if is_last(index) { 40 } else { 0 })
+                .build_cartesian_2d(start_date..end_date, y_min..y_max)
+                .unwrap();
+
+            osc_chart.configure_mesh()
+                .axis_style(WHITE)
+                .bold_line_style(TRANSPARENT)
+                .light_line_style(TRANSPARENT)
+                .label_style(("sans-serif", 12).into_font().color(&WHITE))
+                .x_labels(x_labels)
+                .y_labels(3)
+                .x_label_formatter(&x_label_formatter)
+                .y_label_formatter(&|y| if macd_points.is_empty() { format!("{:.0}", y) } else { format!("{:.2}", y) })
+                .draw().unwrap();
+
+            let guides = |levels: &[f64]| {
+                levels.iter()
+                    .map(|y| PathElement::new(vec![(start_date, *y), (end_date, *y)], WHITE.mix(0.4)))
+                    .collect::<Vec<_>>()
+            };
+            let line = |values: Vec<Option<f64>>, style: ShapeStyle| {
+                LineSeries::new(quotes.iter().zip(values).filter_map(|(q, v)| v.map(|v| (to_x(q), v))), style)
+            };
+
+            match *oscillator {
+                Indicator::Rsi { period } => {
+                    osc_chart.draw_series(guides(&[30.0, 70.0])).unwrap();
+                    osc_chart.draw_series(line(indicators::rsi(&closes, period), line_color.stroke_width(2))).unwrap();
+                },
+                Indicator::Stochastic { k_period, d_period } => {
+                    let values = indicators::stochastic(quotes, k_period, d_period);
+                    osc_chart.draw_series(guides(&[20.0, 80.0])).unwrap();
+                    osc_chart.draw_series(line(values.iter().map(|v| v.map(|(k, _)| k)).collect(), line_color.stroke_width(2))).unwrap();
+                    osc_chart.draw_series(line(values.iter().map(|v| v.map(|(_, d)| d)).collect(), WHITE.mix(0.7).stroke_width(1))).unwrap();
+                },
+                Indicator::Macd { .. } => {
+                    osc_chart.draw_series(guides(&[0.0])).unwrap();
+                    osc_chart.draw_series(quotes.iter().zip(&macd_points).filter_map(|(q, p)| {
+                        let p = (*p)?;
+                        let bar_color = if p.histogram >= 0.0 { GREEN } else { RED };
+                        Some(PathElement::new(vec![(to_x(q), 0.0), (to_x(q), p.histogram)], bar_color.mix(0.6).stroke_width(bar_width)))
+                    })).unwrap();
+                    osc_chart.draw_series(line(macd_points.iter().map(|p| p.map(|p| p.macd)).collect(), line_color.stroke_width(2))).unwrap();
+                    osc_chart.draw_series(line(macd_points.iter().map(|p| p.map(|p| p.signal)).collect(), WHITE.mix(0.7).stroke_width(1))).unwrap();
+                },
+                _ => {},
+            }
+
+            // Pane title
+            area.draw_text(&oscillator.to_string(), &("sans-serif", 13).into_font().color(&WHITE.mix(0.7)), (55, 2)).unwrap();
+        }
+
+        draw_fetched_at(&root, view, width, height);
+    }
+
+    RenderedChart { rgba: to_rgba(&pixel_buffer), min_size }
+}
+
+// Fetch time in the bottom right corner
+fn draw_fetched_at(root: &DrawingArea<BitMapBackend, plotters::coord::Shift>, view: &ChartView, width: u32, height: u32) {
+    let Some((ts, stale)) = view.fetched_at else { return };
+    let mut time_str = format!("{}", ts.format("%Y-%m-%d %H:%M:%S"));
+    // Mark data restored from disk until fresh data arrives
+    let ts_color = if stale {
+        time_str = format!("{} ({})", time_str, crate::language::get_text(view.language, crate::language::TextId::CachedData));
+        RGBColor(255, 165, 0).mix(0.8)
+    } else {
+        WHITE.mix(0.5)
+    };
+    let ts_font = ("sans-serif", 14).into_font();
+    let (tw, th) = ts_font.box_size(&time_str).unwrap();
+    let tx = (width as i32) - (tw as i32) - 10;
+    let ty = (height as i32) - (th as i32) - 5;
+    root.draw_text(&time_str, &ts_font.color(&ts_color), (tx, ty)).unwrap();
+}
+
+/// Comparison mode: every symbol rebased to 0% at its first quote, one line each, with a legend
+/// of the change over the timeframe in place of the price header.
+fn render_comparison(view: &ChartView, width: u32, height: u32) -> RenderedChart {
+    let mut pixel_buffer = vec![0u8; width as usize * height as usize * 3];
+
+    let series: Vec<(&str, Vec<(i64, f64)>)> = std::iter::once((view.symbol, view.quotes))
+        .chain(view.compare.iter().copied())
+        .map(|(symbol, quotes)| {
+            let base = quotes.first().map(|q| q.close).filter(|c| *c != 0.0);
+            let points = base
+                .map(|base| quotes.iter().map(|q| (q.timestamp, (q.close / base - 1.0) * 100.0)).collect())
+                .unwrap_or_default();
+            (symbol, points)
+        })
+        .collect();
+
+    let start = series.iter().filter_map(|(_, p)| p.first()).map(|(t, _)| *t).min();
+    let end = series.iter().filter_map(|(_, p)| p.last()).map(|(t, _)| *t).max();
+    let mut min_size = (0, 0);
+
+    if let (Some(start), Some(end)) = (start, end) {
+        let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
+        root.fill(&TRANSPARENT).unwrap();
+
+        // Legend: "SYMBOL +1.23%" per series next to a swatch of its line colour
+        let font = ("sans-serif", 20).into_font();
+        let mut current_x = 20;
+        for (i, (symbol, points)) in series.iter().enumerate() {
+            let line_color = INDICATOR_COLORS[i % INDICATOR_COLORS.len()];
+            root.draw(&Rectangle::new([(current_x, 24), (current_x + 12, 36)], line_color.filled())).unwrap();
+            current_x += 18;
+
+            root.draw_text(symbol, &font.clone().color(&WHITE), (current_x, 20)).unwrap();
+            current_x += font.box_size(symbol).unwrap().0 as i32 + 6;
+
+            let (change_text, change_color) = match points.last() {
+                Some((_, change)) if *change >= 0.0 => (format!("+{:.2}%", change), GREEN),
+                Some((_, change)) => (format!("{:.2}%", change), RED),
+                None => ("–".to_string(), RGBColor(128, 128, 128)),
+            };
+            root.draw_text(&change_text, &font.clone().color(&change_color), (current_x, 20)).unwrap();
+            current_x += font.box_size(&change_text).unwrap().0 as i32 + 20;
+        }
+        min_size = (current_x as u32, 300);
+
+        let start_date = DateTime::from_timestamp(start, 0).unwrap();
+        let end_date = DateTime::from_timestamp(end.max(start + 1), 0).unwrap();
+        let changes = || series.iter().flat_map(|(_, p)| p.iter().map(|(_, c)| *c));
+        let min_change = changes().fold(0.0, f64::min);
+        let max_change = changes().fold(0.0, f64::max);
+        // Some headroom so the lines don't touch the frame
+        let margin = ((max_change - min_change) * 0.05).max(0.5);
+
+        let x_labels = (width / 120).max(2) as usize;
+        let y_labels = (height / 60).max(2) as usize;
+        let x_label_formatter = |d: &DateTime<chrono::Utc>| {
+            let date = DateTime::from_timestamp(d.timestamp(), 0).unwrap().with_timezone(&Local);
+            if view.timeframe == "1D" {
+                crate::language::format_time(date)
+            } else {
+                crate::language::format_month_day(view.language, date)
+            }
+        };
+
+        let chart_area = root.clone().shrink((0, HEADER_HEIGHT), (width, height.saturating_sub(HEADER_HEIGHT)));
+        let mut chart = ChartBuilder::on(&chart_area)
+            .margin(10)
+            .set_label_area_size(LabelAreaPosition::Left, 50)
+            .set_label_area_size(LabelAreaPosition::Bottom, 40)
+            .build_cartesian_2d(start_date..end_date, (min_change - margin)..(max_change + margin))
+            .unwrap();
+
+        chart.configure_mesh()
+            .axis_style(WHITE)
+            .bold_line_style(WHITE.mix(0.3))
+            .light_line_style(TRANSPARENT)
+            .label_style(("sans-serif", 15).into_font().color(&WHITE))
+            .x_labels(x_labels)
+            .y_labels(y_labels)
+            .x_label_formatter(&x_label_formatter)
+            .y_label_formatter(&|y| format!("{:+.0}%", y))
+            .draw().unwrap();
+
+        // The 0% line all series start from
+        chart.draw_series(std::iter::once(PathElement::new(vec![(start_date, 0.0), (end_date, 0.0)], WHITE.mix(0.5)))).unwrap();
+
+        for (i, (_, points)) in series.iter().enumerate() {
+            let line_color = INDICATOR_COLORS[i % INDICATOR_COLORS.len()];
+            chart.draw_series(LineSeries::new(
+                points.iter().map(|(t, c)| (DateTime::from_timestamp(*t, 0).unwrap(), *c)),
+                line_color.stroke_width(2),
+            )).unwrap();
+        }
+
+        draw_fetched_at(&root, view, width, height);
+    }
+
+    RenderedChart { rgba: to_rgba(&pixel_buffer), min_size }
+}
+
+// Drawing happens on black, which doubles as the transparent background
+fn to_rgba(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks(3)
+        .flat_map(|p| {
+            let alpha = if p == [0, 0, 0] { 0 } else { 255 };
+            [p[0], p[1], p[2], alpha]
+        })
+        .collect()
+}
+
+// Watchlist layout: fixed columns for symbol, price and change, the sparkline takes the remaining width
+const WATCHLIST_ROW_HEIGHT: u32 = 30;
+const WATCHLIST_PADDING: u32 = 10;
+const WATCHLIST_PRICE_X: i32 = 100;
+const WATCHLIST_CHANGE_X: i32 = 200;
+const WATCHLIST_SPARKLINE_X: i32 = 280;
+const WATCHLIST_SPARKLINE_MIN_WIDTH: i32 = 40;
+
+/// One symbol of a watchlist, `quotes` is empty until its data has loaded.
+pub struct WatchlistRow<'a> {
+    pub symbol: &'a str,
+    pub currency: &'a str,
+    pub quotes: &'a [Candle],
+}
+
+/// Height a watchlist with `rows` symbols needs.
+pub fn watchlist_height(rows: usize) -> u32 {
+    WATCHLIST_PADDING * 2 + WATCHLIST_ROW_HEIGHT * rows.max(1) as u32
+}
+
+/// Draws a watchlist as a table with one row per symbol: symbol, last price, change over the
+/// loaded timeframe and a sparkline of the closes.
+pub fn render_watchlist(rows: &[WatchlistRow], width: u32, height: u32) -> RenderedChart {
+    let mut pixel_buffer = vec![0u8; width as usize * height as usize * 3];
+    let min_size = (WATCHLIST_SPARKLINE_X as u32, watchlist_height(rows.len()));
+
+    {
+        let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
+        root.fill(&TRANSPARENT).unwrap();
+
+        let font = ("sans-serif", 18).into_font();
+        let x0 = WATCHLIST_PADDING as i32;
+        let sparkline_end = width as i32 - WATCHLIST_PADDING as i32;
+
+        for (i, row) in rows.iter().enumerate() {
+            let top = (WATCHLIST_PADDING + i as u32 * WATCHLIST_ROW_HEIGHT) as i32;
+            let text_y = top + 5;
+
+            // Separator between rows
+            if i > 0 {
+                root.draw(&PathElement::new(vec![(x0, top), (sparkline_end, top)], WHITE.mix(0.15))).unwrap();
+            }
+
+            root.draw_text(row.symbol, &font.clone().color(&WHITE), (x0, text_y)).unwrap();
+
+            let (Some(first), Some(last)) = (row.quotes.first(), row.quotes.last()) else {
+                root.draw_text("–", &font.clone().color(&WHITE.mix(0.5)), (WATCHLIST_PRICE_X, text_y)).unwrap();
+                continue;
+            };
+
+            let diff = last.close - first.close;
+            let percent_change = if first.close != 0.0 { diff / first.close * 100.0 } else { 0.0 };
+            let color = if diff >= 0.0 { GREEN } else { RED };
+            let sign = if diff >= 0.0 { "+" } else { "" };
+
+            let price_text = currency::format(last.close, row.currency);
+            root.draw_text(&price_text, &font.clone().color(&WHITE), (WATCHLIST_PRICE_X, text_y)).unwrap();
+            let change_text = format!("{}{:.2}%", sign, percent_change);
+            root.draw_text(&change_text, &font.clone().color(&color), (WATCHLIST_CHANGE_X, text_y)).unwrap();
+
+            // Sparkline, scaled to the row; skipped when the window is too narrow for it
+            if sparkline_end - WATCHLIST_SPARKLINE_X < WATCHLIST_SPARKLINE_MIN_WIDTH || row.quotes.len() < 2 {
+                continue;
+            }
+            let min = row.quotes.iter().map(|q| q.close).fold(f64::INFINITY, f64::min);
+            let max = row.quotes.iter().map(|q| q.close).fold(f64::NEG_INFINITY, f64::max);
+            let range = (max - min).max(f64::EPSILON);
+            let line_top = top + 5;
+            let line_height = WATCHLIST_ROW_HEIGHT as i32 - 10;
+            let step = (sparkline_end - WATCHLIST_SPARKLINE_X) as f64 / (row.quotes.len() - 1) as f64;
+            let points: Vec<(i32, i32)> = row.quotes.iter().enumerate()
+                .map(|(n, q)| {
+                    let x = WATCHLIST_SPARKLINE_X + (n as f64 * step) as i32;
+                    let y = line_top + ((max - q.close) / range * line_height as f64) as i32;
+                    (x, y)
+                })
+                .collect();
+            root.draw(&PathElement::new(points, color.stroke_width(1))).unwrap();
+        }
+    }
+
+    RenderedChart { rgba: to_rgba(&pixel_buffer), min_size }
+}
+
+// Portfolio layout: header row, one row per position, then one total row per currency
+const PORTFOLIO_ROW_HEIGHT: u32 = 26;
+const PORTFOLIO_PADDING: u32 = 10;
+// Left edges of Symbol, Qty, Price, Value, P/L, Day, Alloc.
+const PORTFOLIO_COLUMNS: [i32; 7] = [10, 100, 160, 250, 350, 490, 560];
+const PORTFOLIO_MIN_WIDTH: u32 = 620;
+
+/// Height a portfolio with `positions` rows and `totals` currencies needs.
+pub fn portfolio_height(positions: usize, totals: usize) -> u32 {
+    PORTFOLIO_PADDING * 2 + PORTFOLIO_ROW_HEIGHT * (1 + positions.max(1) + totals) as u32
+}
+
+/// Draws the portfolio table: value and unrealized P/L per position and in total, the daily
+/// change and each position's share of its currency's total value. `base_total` adds a last row
+/// with everything converted to the base currency.
+pub fn render_portfolio(rows: &[PositionSummary], totals: &[PortfolioTotal], base_total: Option<&PortfolioTotal>, language: Language, width: u32, height: u32) -> RenderedChart {
+    let mut pixel_buffer = vec![0u8; width as usize * height as usize * 3];
+    let min_size = (PORTFOLIO_MIN_WIDTH, portfolio_height(rows.len(), totals.len() + base_total.is_some() as usize));
+
+    {
+        let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
+        root.fill(&TRANSPARENT).unwrap();
+
+        let font = ("sans-serif", 16).into_font();
+        let muted = RGBColor(160, 160, 160);
+        let row_y = |i: usize| (PORTFOLIO_PADDING + i as u32 * PORTFOLIO_ROW_HEIGHT) as i32;
+        let signed = |v: f64| if v >= 0.0 { format!("+{:.2}", v) } else { format!("{:.2}", v) };
+        let change_color = |v: f64| if v >= 0.0 { GREEN } else { RED };
+        let text = |s: &str, color: &RGBColor, column: usize, y: i32| {
+            root.draw_text(s, &font.clone().color(color), (PORTFOLIO_COLUMNS[column], y + 4)).unwrap();
+        };
+
+        let headers = [TextId::PortfolioSymbol, TextId::PortfolioQuantity, TextId::PortfolioPrice, TextId::PortfolioValue, TextId::PortfolioProfit, TextId::PortfolioDay, TextId::PortfolioAllocation];
+        for (column, id) in headers.into_iter().enumerate() {
+            text(get_text(language, id), &muted, column, row_y(0));
+        }
+
+        for (i, row) in rows.iter().enumerate() {
+            let y = row_y(i + 1);
+            root.draw(&PathElement::new(vec![(PORTFOLIO_COLUMNS[0], y), (width as i32 - PORTFOLIO_PADDING as i32, y)], WHITE.mix(0.15))).unwrap();
+
+            text(&row.symbol, &WHITE, 0, y);
+            text(&format!("{}", row.quantity), &WHITE, 1, y);
+            let (Some(price), Some(value), Some(profit), Some(profit_percent), Some(day)) =
+                (row.price, row.value, row.profit, row.profit_percent, row.day_change_percent) else {
+                text("–", &muted, 2, y);
+                continue;
+            };
+            text(&currency::format(price, &row.currency), &WHITE, 2, y);
+            text(&currency::format_rounded(value, &row.currency, 0), &WHITE, 3, y);
+            text(&format!("{} ({}%)", signed(profit), signed(profit_percent)), &change_color(profit), 4, y);
+            text(&format!("{}%", signed(day)), &change_color(day), 5, y);
+            if let Some(allocation) = row.allocation_percent {
+                text(&format!("{:.1}%", allocation), &WHITE, 6, y);
+            }
+        }
+
+        let total_rows = totals.iter().map(|t| (t, TextId::PortfolioTotal)).chain(base_total.map(|t| (t, TextId::PortfolioTotalConverted)));
+        for (i, (total, label)) in total_rows.enumerate() {
+            let y = row_y(rows.len().max(1) + 1 + i);
+            if i == 0 {
+                root.draw(&PathElement::new(vec![(PORTFOLIO_COLUMNS[0], y), (width as i32 - PORTFOLIO_PADDING as i32, y)], WHITE.mix(0.5))).unwrap();
+            }
+            text(&get_text(language, label).replacen("{}", &total.currency, 1), &WHITE, 0, y);
+            text(&currency::format_rounded(total.value, &total.currency, 0), &WHITE, 3, y);
+            text(&format!("{} ({}%)", signed(total.profit), signed(total.profit_percent)), &change_color(total.profit), 4, y);
+            text(&format!("{}%", signed(total.day_change_percent)), &change_color(total.day_change), 5, y);
+        }
+    }
+
+    RenderedChart { rgba: to_rgba(&pixel_buffer), min_size }
+}
+
+const TICKER_ITEM_GAP: i32 = 40;
+const TICKER_SEGMENT_GAP: i32 = 8;
+
+/// One symbol scrolling through a ticker tape.
+pub struct TickerItem<'a> {
+    pub symbol: &'a str,
+    pub currency: &'a str,
+    pub quotes: &'a [Candle],
+}
+
+/// The ticker's texts laid out once, drawn again for every frame by `render_ticker`.
+pub struct TickerTape {
+    segments: Vec<(String, RGBColor, i32)>, // (text, color, x) relative to the start of the tape
+    width: i32,
+    font_size: f64,
+    /// Smallest size the ticker fits into
+    pub min_size: (u32, u32),
+}
+
+/// Lays out "SYMBOL price +change%" for every item in the font size of a ticker `height` pixels high.
+pub fn layout_ticker(items: &[TickerItem], height: u32) -> TickerTape {
+    let font_size = (height as f64 * 0.5).clamp(12.0, 40.0);
+    let font = ("sans-serif", font_size).into_font();
+
+    let mut segments: Vec<(String, RGBColor, i32)> = Vec::new();
+    let mut tape_width = 0;
+    for item in items {
+        let mut texts = vec![(item.symbol.to_string(), WHITE)];
+        match (item.quotes.first(), item.quotes.last()) {
+            (Some(first), Some(last)) => {
+                let diff = last.close - first.close;
+                let percent_change = if first.close != 0.0 { diff / first.close * 100.0 } else { 0.0 };
+                let color = if diff >= 0.0 { GREEN } else { RED };
+                let sign = if diff >= 0.0 { "+" } else { "" };
+                texts.push((currency::format(last.close, item.currency), WHITE));
+                texts.push((format!("{}{:.2}%", sign, percent_change), color));
+            },
+            _ => texts.push(("–".to_string(), RGBColor(128, 128, 128))),
+        }
+        for (text, color) in texts {
+            let text_width = font.box_size(&text).map(|(w, _)| w as i32).unwrap_or(0);
+            segments.push((text, color, tape_width));
+            tape_width += text_width + TICKER_SEGMENT_GAP;
+        }
+        tape_width += TICKER_ITEM_GAP - TICKER_SEGMENT_GAP;
+    }
+
+    TickerTape { segments, width: tape_width, font_size, min_size: (100, font_size as u32 + 8) }
+}
+
+/// Draws the ticker tape shifted left by `offset` pixels. The items repeat once their
+/// total width is scrolled through, so `offset` may grow without bounds.
+pub fn render_ticker(tape: &TickerTape, width: u32, height: u32, offset: f64) -> RenderedChart {
+    let mut pixel_buffer = vec![0u8; width as usize * height as usize * 3];
+
+    {
+        let root = BitMapBackend::with_buffer(&mut pixel_buffer[..], (width, height)).into_drawing_area();
+        root.fill(&TRANSPARENT).unwrap();
+
+        let font = ("sans-serif", tape.font_size).into_font();
+        if tape.width > 0 {
+            let text_y = (height as i32 - tape.font_size as i32) / 2;
+            // Repeat the tape until the window is covered
+            let mut start = -(offset.rem_euclid(tape.width as f64) as i32);
+            while start < width as i32 {
+                for (text, color, x) in &tape.segments {
+                    let x = start + x;
+                    if x < width as i32 && x > -(tape.width) {
+                        root.draw_text(text, &font.clone().color(color), (x, text_y)).unwrap();
+                    }
+                }
+                start += tape.width;
+            }
+        }
+    }
+
+    RenderedChart { rgba: to_rgba(&pixel_buffer), min_size: tape.min_size }
+}
+
+/// Headless mode: `--render SYMBOL --out file.png [--timeframe 1M] [--style candles] [--size 800x400] [--compare MSFT,SAP.DE]`.
+/// Style, indicators, timeframe and compared symbols default to the first configured chart for the symbol.
+pub fn run_headless(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let value_of = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).map(|s| s.as_str());
+
+    let symbol = value_of("--render").ok_or("missing symbol after --render")?.to_uppercase();
+    let out = value_of("--out").ok_or("missing --out file.png")?;
+
+    // Only read, loading could import a legacy config or back up a broken one behind a running app's back
+    let path = crate::config::AppConfig::path();
+    let config = match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|content| crate::config::AppConfig::parse(&content)) {
+        Ok((config, _)) => config,
+        Err(e) => {
+            if path.exists() {
+                log::warn!("Failed to read config from {:?}, using defaults: {}", path, e);
+            }
+            crate::config::AppConfig::default()
+        },
+    };
+    let chart_config = config.charts.iter().find(|c| c.symbol == symbol);
+
+    let timeframe = value_of("--timeframe")
+        .map(str::to_string)
+        .or_else(|| chart_config.and_then(|c| c.timeframe.clone()))
+        .unwrap_or_else(|| "1M".to_string());
+    let style = match value_of("--style") {
+        Some(name) => ChartStyle::ALL
+            .into_iter()
+            .find(|s| s.to_string().eq_ignore_ascii_case(name) || format!("{:?}", s).eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown style '{}'", name))?,
+        None => chart_config.map(|c| c.style).unwrap_or_default(),
+    };
+    let (width, height) = match value_of("--size") {
+        Some(size) => size
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse::<u32>().ok()?, h.parse::<u32>().ok()?)))
+            .ok_or_else(|| format!("invalid size '{}', expected WIDTHxHEIGHT", size))?,
+        None => chart_config.map(|c| (c.width, c.height)).unwrap_or((800, 400)),
+    };
+    if !crate::provider::TIMEFRAMES.contains(&timeframe.as_str()) {
+        return Err(format!("unknown timeframe '{}', expected one of {}", timeframe, crate::provider::TIMEFRAMES.join(", ")).into());
+    }
+    if !(1..=MAX_RENDER_SIZE).contains(&width) || !(1..=MAX_RENDER_SIZE).contains(&height) {
+        return Err(format!("invalid size {}x{}, width and height have to be between 1 and {}", width, height, MAX_RENDER_SIZE).into());
+    }
+
+    let provider = crate::provider::provider_for(chart_config.and_then(|c| c.provider.as_deref()));
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let series = runtime
+        .block_on(provider.fetch(&symbol, &timeframe))
+        .map_err(|e| crate::language::get_error_text(config.language, &e))?;
+
+    let compare_symbols = match value_of("--compare") {
+        Some(list) => crate::watchlist::parse_symbols(list),
+        None => chart_config.map(|c| c.compare_symbols.clone()).unwrap_or_default(),
+    };
+    let mut compare_series = Vec::new();
+    for compare_symbol in &compare_symbols {
+        let compare = runtime
+            .block_on(provider.fetch(compare_symbol, &timeframe))
+            .map_err(|e| format!("{}: {}", compare_symbol, crate::language::get_error_text(config.language, &e)))?;
+        compare_series.push((compare_symbol.as_str(), compare.candles));
+    }
+    let compare: Vec<(&str, &[Candle])> = compare_series.iter().map(|(s, c)| (*s, c.as_slice())).collect();
+
+    let view = ChartView {
+        symbol: &symbol,
+        currency: &series.currency,
+        quotes: &series.candles,
+        timeframe: &timeframe,
+        style,
+        indicators: chart_config.map(|c| c.indicators.as_slice()).unwrap_or_default(),
+        volume_pane_height: chart_config.and_then(|c| c.volume_pane_height),
+        language: config.language,
+        fetched_at: Some((Local::now(), false)),
+        compare: &compare,
+        session: crate::market_hours::session(&symbol),
+        session_range: series.candles.last().and_then(|q| crate::market_hours::session_range(&symbol, q.timestamp)),
+    };
+    let rendered = render_chart(&view, width, height);
+
+    let file = std::io::BufWriter::new(std::fs::File::create(out)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&rendered.rgba)?;
+
+    log::info!("Rendered {} ({}) to {}", symbol, timeframe, out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 40 daily candles, all closing above their open
+    fn rising_candles() -> Vec<Candle> {
+        (0..40)
+            .map(|i| {
+                let open = 100.0 + (i % 7) as f64;
+                Candle { timestamp: 1_700_000_000 + i * 86_400, open, high: open + 3.0, low: open - 2.0, close: open + 1.0, volume: 1000 }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn heikin_ashi_averages_the_bars() {
+        let candle = |timestamp: i64, open: f64, high: f64, low: f64, close: f64| Candle { timestamp, open, high, low, close, volume: 5 };
+        let candles = [candle(1, 10.0, 12.0, 9.0, 11.0), candle(2, 11.0, 14.0, 10.0, 13.0), candle(3, 9.0, 10.0, 8.0, 9.5)];
+
+        let smoothed = heikin_ashi(&candles);
+        assert_eq!(smoothed, vec![
+            candle(1, 10.5, 12.0, 9.0, 10.5),
+            candle(2, 10.5, 14.0, 10.0, 12.0),
+            // The averaged open lies above the bar, the high is stretched to it
+            candle(3, 11.25, 11.25, 8.0, 9.125),
+        ]);
+        assert!(heikin_ashi(&[]).is_empty());
+    }
+
+    #[test]
+    fn renders_candles_offscreen() {
+        let quotes = rising_candles();
+        let view = ChartView {
+            symbol: "TEST",
+            currency: "USD",
+            quotes: &quotes,
+            timeframe: "3M",
+            style: ChartStyle::Candlestick,
+            indicators: &[],
+            volume_pane_height: None,
+            language: Language::En,
+            fetched_at: None,
+            compare: &[],
+            session: None,
+            session_range: None,
+        };
+        let rendered = render_chart(&view, 320, 200);
+
+        assert_eq!(rendered.rgba.len(), 320 * 200 * 4);
+        let pixels = |color: [u8; 4]| rendered.rgba.chunks(4).filter(|p| *p == color).count();
+        assert!(pixels([0, 255, 0, 255]) > 100);
+        assert_eq!(pixels([255, 0, 0, 255]), 0);
+        assert!(rendered.rgba.chunks(4).any(|p| p[3] == 0));
+        assert_eq!(render_chart(&view, 320, 200).rgba, rendered.rgba);
+    }
+
+    #[test]
+    fn renders_a_series_fetched_from_a_provider() {
+        let provider = crate::provider::provider_for(Some("memory"));
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        assert!(matches!(runtime.block_on(provider.fetch("MISSING", "1M")), Err(crate::language::AppError::NoQuotesFound)));
+        let series = runtime.block_on(provider.fetch("TEST", "1M")).unwrap();
+
+        let view = ChartView {
+            symbol: "TEST",
+            currency: &series.currency,
+            quotes: &series.candles,
+            timeframe: "1M",
+            style: ChartStyle::Line,
+            indicators: &[],
+            volume_pane_height: None,
+            language: Language::En,
+            fetched_at: None,
+            compare: &[],
+            session: None,
+            session_range: None,
+        };
+        let rendered = render_chart(&view, 320, 200);
+
+        // A falling series is drawn in red throughout
+        let pixels = |color: [u8; 4]| rendered.rgba.chunks(4).filter(|p| *p == color).count();
+        assert!(pixels([255, 0, 0, 255]) > 100);
+        assert_eq!(pixels([0, 255, 0, 255]), 0);
+    }
+}
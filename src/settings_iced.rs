@@ -1,15 +1,24 @@
 use iced::widget::{button, checkbox, column, container, pick_list, row, scrollable, text, text_input, vertical_space, horizontal_rule, tooltip, svg};
 use iced::{Element, Length, Theme, Command, Application, Settings, Subscription, Alignment};
-use crate::ipc::{IpcMessage, ChartData, ConfigData, PIPE_NAME};
+use crate::ipc::{Frame, IpcMessage, ChartData, ConfigData, TickerData, WatchlistData};
+use crate::config::ChartStyle;
+use crate::indicators::Indicator;
+use crate::alerts::{AlertCondition, AlertEvent, AlertKind, AlertRule};
+use crate::portfolio::Position;
 use crate::language::{self, TextId};
-use tokio::net::windows::named_pipe::ClientOptions;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::collections::HashMap;
+
+// Parameter choices offered for indicators
+const INDICATOR_PERIODS: [usize; 10] = [5, 9, 10, 12, 14, 20, 26, 50, 100, 200];
+const BOLLINGER_STD_DEVS: [f64; 5] = [1.0, 1.5, 2.0, 2.5, 3.0];
+const SMOOTHING_PERIODS: [usize; 4] = [3, 5, 9, 14];
+const ALERT_COOLDOWNS: [u64; 6] = [5, 15, 30, 60, 240, 1440];
 
 pub fn run() -> iced::Result {
     SettingsApp::run(Settings {
         window: iced::window::Settings {
-            size: iced::Size::new(450.0, 650.0),
-            min_size: Some(iced::Size::new(450.0, 600.0)),
+            size: iced::Size::new(560.0, 650.0),
+            min_size: Some(iced::Size::new(560.0, 600.0)),
             ..iced::window::Settings::default()
         },
         ..Settings::default()
@@ -18,13 +27,30 @@ pub fn run() -> iced::Result {
 
 struct SettingsApp {
     charts: Vec<ChartData>,
+    watchlists: Vec<WatchlistData>,
+    tickers: Vec<TickerData>,
+    positions: Vec<Position>,
     config: Option<ConfigData>,
+    alerts: Vec<AlertRule>,
+    alert_history: Vec<AlertEvent>,
     update_status: Option<crate::common::UpdateStatus>,
     
     // UI State
     input_value: String,
     error_message: Option<String>,
-    sender: Option<tokio::sync::mpsc::Sender<IpcMessage>>,
+    tab: Tab,
+    watchlist_input: String,
+    ticker_input: String,
+    symbol_inputs: HashMap<String, String>, // Symbols text being edited, by chart, watchlist or ticker id
+    alert_inputs: HashMap<u64, String>, // Threshold text being edited, by alert id
+    position_input: String,
+    csv_path: String,
+    portfolio_status: Option<String>, // Outcome of the last CSV import/export
+    base_currency_input: Option<String>, // Base currency text being edited
+    sender: Option<tokio::sync::mpsc::Sender<Frame>>,
+    next_request_id: u64,
+    pending_add: Option<u64>, // AddChart request still waiting for its answer
+    rejected: Option<String>, // The running host refused our protocol version
 }
 
 #[derive(Debug, Clone)]
@@ -34,12 +60,50 @@ enum Message {
     DeletePressed(String),
     LockToggled(String, bool),
     TimeframeChanged(String, String),
+    StyleChanged(String, ChartStyle),
+    IndicatorAdded(String, Indicator),
+    IndicatorChanged(String, usize, Indicator),
+    IndicatorRemoved(String, usize),
+
+    TabSelected(Tab),
+
+    // Watchlists and tickers
+    WatchlistInputChanged(String),
+    WatchlistAddPressed,
+    TickerInputChanged(String),
+    TickerAddPressed,
+    TickerSpeedChanged(String, u32),
+    SymbolsChanged(String, String),
+    SymbolsSubmitted(String),
+    CompareSubmitted(String),
+
+    // Portfolio
+    PortfolioToggled(bool),
+    PositionInputChanged(String),
+    PositionAddPressed,
+    PositionDeleted(String),
+    CsvPathChanged(String),
+    ImportPressed,
+    ExportPressed,
+
+    // Alerts
+    AlertAdded(String),
+    AlertKindChanged(u64, AlertKind),
+    AlertValueChanged(u64, String),
+    AlertValueSubmitted(u64),
+    AlertToggled(u64, bool),
+    AlertCooldownChanged(u64, u64),
+    AlertDeleted(u64),
     
     // Config controls
     LanguageChanged(Language),
     IntervalChanged(u64),
     AutoStartToggled(bool),
     UsePrereleasesToggled(bool),
+    BaseCurrencyChanged(String),
+    BaseCurrencySubmitted,
+    ConvertPricesToggled(bool),
+    RestoreLayout,
     
     // Updates
     CheckUpdates,
@@ -47,11 +111,21 @@ enum Message {
     RestartApp,
 
     // IPC
-    IpcConnected(tokio::sync::mpsc::Sender<IpcMessage>),
-    IpcServerMessage(IpcMessage),
+    IpcConnected(tokio::sync::mpsc::Sender<Frame>),
+    IpcServerFrame(Frame),
+    IpcRejected(String),
     IpcClosed,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Charts,
+    Watchlists,
+    Tickers,
+    Portfolio,
+    Alerts,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Language {
     English,
@@ -67,6 +141,19 @@ impl std::fmt::Display for Language {
     }
 }
 
+/// Entry of the alert condition pick list, labelled in the UI language.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AlertKindOption {
+    kind: AlertKind,
+    lang: language::Language,
+}
+
+impl std::fmt::Display for AlertKindOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind.label(self.lang))
+    }
+}
+
 impl Into<language::Language> for Language {
     fn into(self) -> language::Language {
         match self {
@@ -86,11 +173,28 @@ impl Application for SettingsApp {
         (
             SettingsApp {
                 charts: Vec::new(),
+                watchlists: Vec::new(),
+                tickers: Vec::new(),
+                positions: Vec::new(),
                 config: None,
+                alerts: Vec::new(),
+                alert_history: Vec::new(),
                 update_status: None, // Initial state unknown or idle
                 input_value: String::new(),
                 error_message: None,
+                tab: Tab::Charts,
+                watchlist_input: String::new(),
+                ticker_input: String::new(),
+                symbol_inputs: HashMap::new(),
+                base_currency_input: None,
+                alert_inputs: HashMap::new(),
+                position_input: String::new(),
+                csv_path: String::new(),
+                portfolio_status: None,
                 sender: None,
+                next_request_id: 1,
+                pending_add: None,
+                rejected: None,
             },
             Command::none()
         )
@@ -114,7 +218,7 @@ impl Application for SettingsApp {
             Message::AddPressed => {
                 if !self.input_value.is_empty() {
                      let upper_symbol = self.input_value.to_uppercase();
-                     self.send_ipc(IpcMessage::AddChart(upper_symbol));
+                     self.pending_add = self.send_ipc(IpcMessage::AddChart(upper_symbol));
                      self.input_value.clear();
                 }
                 Command::none()
@@ -131,6 +235,178 @@ impl Application for SettingsApp {
                 self.send_ipc(IpcMessage::SetChartTimeframe(id, tf));
                 Command::none()
             }
+            Message::StyleChanged(id, style) => {
+                self.send_ipc(IpcMessage::SetChartStyle(id, style));
+                Command::none()
+            }
+            Message::IndicatorAdded(id, indicator) => {
+                self.update_indicators(id, |list| list.push(indicator));
+                Command::none()
+            }
+            Message::IndicatorChanged(id, index, indicator) => {
+                self.update_indicators(id, |list| {
+                    if let Some(entry) = list.get_mut(index) {
+                        *entry = indicator;
+                    }
+                });
+                Command::none()
+            }
+            Message::IndicatorRemoved(id, index) => {
+                self.update_indicators(id, |list| {
+                    if index < list.len() {
+                        list.remove(index);
+                    }
+                });
+                Command::none()
+            }
+            Message::TabSelected(tab) => {
+                self.tab = tab;
+                Command::none()
+            }
+            Message::WatchlistInputChanged(value) => {
+                self.watchlist_input = value;
+                Command::none()
+            }
+            Message::WatchlistAddPressed => {
+                let symbols = crate::watchlist::parse_symbols(&self.watchlist_input);
+                if !symbols.is_empty() {
+                    self.send_ipc(IpcMessage::AddWatchlist(symbols));
+                    self.watchlist_input.clear();
+                }
+                Command::none()
+            }
+            Message::TickerInputChanged(value) => {
+                self.ticker_input = value;
+                Command::none()
+            }
+            Message::TickerAddPressed => {
+                let symbols = crate::watchlist::parse_symbols(&self.ticker_input);
+                if !symbols.is_empty() {
+                    self.send_ipc(IpcMessage::AddTicker(symbols));
+                    self.ticker_input.clear();
+                }
+                Command::none()
+            }
+            Message::TickerSpeedChanged(id, speed) => {
+                self.send_ipc(IpcMessage::SetTickerSpeed(id, speed));
+                Command::none()
+            }
+            Message::SymbolsChanged(id, value) => {
+                self.symbol_inputs.insert(id, value);
+                Command::none()
+            }
+            Message::SymbolsSubmitted(id) => {
+                if let Some(value) = self.symbol_inputs.remove(&id) {
+                    let symbols = crate::watchlist::parse_symbols(&value);
+                    if !symbols.is_empty() {
+                        self.send_ipc(IpcMessage::SetSymbols(id, symbols));
+                    }
+                }
+                Command::none()
+            }
+            Message::CompareSubmitted(id) => {
+                // Unlike watchlists an empty list is fine, it switches back to a regular chart
+                if let Some(value) = self.symbol_inputs.remove(&id) {
+                    self.send_ipc(IpcMessage::SetChartCompare(id, crate::watchlist::parse_symbols(&value)));
+                }
+                Command::none()
+            }
+            Message::PortfolioToggled(show) => {
+                if let Some(cfg) = &mut self.config {
+                    cfg.show_portfolio = show;
+                }
+                self.send_ipc(IpcMessage::ShowPortfolio(show));
+                Command::none()
+            }
+            Message::PositionInputChanged(value) => {
+                self.position_input = value;
+                Command::none()
+            }
+            Message::PositionAddPressed => {
+                if let Some(position) = crate::portfolio::parse_position(&self.position_input) {
+                    // Adding a symbol that is already held replaces that position
+                    let mut positions = self.positions.clone();
+                    match positions.iter_mut().find(|p| p.symbol == position.symbol) {
+                        Some(existing) => *existing = position,
+                        None => positions.push(position),
+                    }
+                    self.send_ipc(IpcMessage::SetPositions(positions));
+                    self.position_input.clear();
+                }
+                Command::none()
+            }
+            Message::PositionDeleted(symbol) => {
+                let positions = self.positions.iter().filter(|p| p.symbol != symbol).cloned().collect();
+                self.send_ipc(IpcMessage::SetPositions(positions));
+                Command::none()
+            }
+            Message::CsvPathChanged(value) => {
+                self.csv_path = value;
+                Command::none()
+            }
+            Message::ImportPressed => {
+                let lang = self.language();
+                let result = std::fs::read_to_string(self.csv_path.trim())
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| crate::portfolio::from_csv(&content));
+                self.portfolio_status = Some(match result {
+                    Ok(positions) => {
+                        let status = language::get_text(lang, TextId::PortfolioImported).replacen("{}", &positions.len().to_string(), 1);
+                        self.send_ipc(IpcMessage::SetPositions(positions));
+                        status
+                    },
+                    Err(e) => format!("{} {}", language::get_text(lang, TextId::ErrorPrefix), e),
+                });
+                Command::none()
+            }
+            Message::ExportPressed => {
+                let lang = self.language();
+                let result = crate::portfolio::to_csv(&self.positions)
+                    .and_then(|csv| std::fs::write(self.csv_path.trim(), csv).map_err(|e| e.to_string()));
+                self.portfolio_status = Some(match result {
+                    Ok(()) => language::get_text(lang, TextId::PortfolioExported).replacen("{}", &self.positions.len().to_string(), 1),
+                    Err(e) => format!("{} {}", language::get_text(lang, TextId::ErrorPrefix), e),
+                });
+                Command::none()
+            }
+            Message::AlertAdded(symbol) => {
+                self.send_ipc(IpcMessage::AddAlert(symbol, AlertCondition::PercentChange { percent: 5.0 }));
+                Command::none()
+            }
+            Message::AlertKindChanged(id, kind) => {
+                if let Some(rule) = self.alerts.iter().find(|r| r.id == id) {
+                    let condition = kind.condition(rule.condition.value().unwrap_or(5.0));
+                    self.alert_inputs.remove(&id);
+                    self.send_ipc(IpcMessage::UpdateAlert(id, condition));
+                }
+                Command::none()
+            }
+            Message::AlertValueChanged(id, value) => {
+                self.alert_inputs.insert(id, value);
+                Command::none()
+            }
+            Message::AlertValueSubmitted(id) => {
+                let parsed = self.alert_inputs.get(&id).and_then(|v| v.trim().replace(',', ".").parse::<f64>().ok());
+                if let (Some(value), Some(rule)) = (parsed, self.alerts.iter().find(|r| r.id == id)) {
+                    let condition = rule.condition.kind().condition(value);
+                    self.alert_inputs.remove(&id);
+                    self.send_ipc(IpcMessage::UpdateAlert(id, condition));
+                }
+                Command::none()
+            }
+            Message::AlertToggled(id, enabled) => {
+                self.send_ipc(IpcMessage::SetAlertEnabled(id, enabled));
+                Command::none()
+            }
+            Message::AlertCooldownChanged(id, minutes) => {
+                self.send_ipc(IpcMessage::SetAlertCooldown(id, minutes));
+                Command::none()
+            }
+            Message::AlertDeleted(id) => {
+                self.alert_inputs.remove(&id);
+                self.send_ipc(IpcMessage::DeleteAlert(id));
+                Command::none()
+            }
             Message::LanguageChanged(lang) => {
                 let code = match lang {
                     Language::English => "en",
@@ -163,6 +439,35 @@ impl Application for SettingsApp {
                 self.send_ipc(IpcMessage::SetUsePrereleases(enabled));
                 Command::none()
             }
+            Message::BaseCurrencyChanged(value) => {
+                self.base_currency_input = Some(value.to_uppercase());
+                Command::none()
+            }
+            Message::BaseCurrencySubmitted => {
+                // Empty turns conversion off, anything else has to be an ISO code
+                if let Some(value) = self.base_currency_input.take() {
+                    let value = value.trim().to_string();
+                    if value.is_empty() {
+                        self.send_ipc(IpcMessage::SetBaseCurrency(None));
+                    } else if crate::currency::is_iso_code(&value) {
+                        self.send_ipc(IpcMessage::SetBaseCurrency(Some(value)));
+                    } else {
+                        self.base_currency_input = Some(value);
+                    }
+                }
+                Command::none()
+            }
+            Message::ConvertPricesToggled(enabled) => {
+                if let Some(cfg) = &mut self.config {
+                    cfg.convert_prices = enabled;
+                }
+                self.send_ipc(IpcMessage::SetConvertPrices(enabled));
+                Command::none()
+            }
+            Message::RestoreLayout => {
+                self.send_ipc(IpcMessage::RestorePreviousLayout);
+                Command::none()
+            }
             Message::CheckUpdates => {
                 // Set explicit checking status immediately for UI feedback
                 self.update_status = Some(crate::common::UpdateStatus::Checking("".to_string()));
@@ -182,26 +487,45 @@ impl Application for SettingsApp {
                 iced::window::close(iced::window::Id::MAIN)
             }
             Message::IpcConnected(tx) => {
-                self.sender = Some(tx.clone());
+                self.sender = Some(tx);
                 // Request initial state
-                Command::perform(async move {
-                    let _ = tx.send(IpcMessage::GetCharts).await;
-                    let _ = tx.send(IpcMessage::GetConfig).await;
-                }, |_| Message::InputChanged("".to_string()))
-            }
-            Message::IpcServerMessage(msg) => {
-                match msg {
-                    IpcMessage::Charts(charts) => self.charts = charts,
-                    IpcMessage::Config(cfg) => self.config = Some(cfg),
-                    IpcMessage::UpdateStatus(status) => self.update_status = Some(status),
-                    IpcMessage::Error(err) => self.error_message = Some(err),
-                    IpcMessage::Shutdown => {
-                        return iced::window::close(iced::window::Id::MAIN);
+                self.send_ipc(IpcMessage::GetCharts);
+                self.send_ipc(IpcMessage::GetConfig);
+                self.send_ipc(IpcMessage::GetAlerts);
+                Command::none()
+            }
+            Message::IpcServerFrame(frame) => {
+                match frame {
+                    Frame::Event(msg) => match msg {
+                        IpcMessage::Charts(charts) => self.charts = charts,
+                        IpcMessage::Watchlists(watchlists) => self.watchlists = watchlists,
+                        IpcMessage::Tickers(tickers) => self.tickers = tickers,
+                        IpcMessage::Positions(positions) => self.positions = positions,
+                        IpcMessage::Config(cfg) => self.config = Some(cfg),
+                        IpcMessage::Alerts(alerts) => self.alerts = alerts,
+                        IpcMessage::AlertHistory(history) => self.alert_history = history,
+                        IpcMessage::UpdateStatus(status) => self.update_status = Some(status),
+                        IpcMessage::Shutdown => {
+                            return iced::window::close(iced::window::Id::MAIN);
+                        },
+                        _ => {}
+                    },
+                    Frame::Ack { id } if self.pending_add == Some(id) => self.pending_add = None,
+                    Frame::Failed { id, error } => {
+                        if self.pending_add == Some(id) {
+                            self.pending_add = None;
+                        }
+                        self.error_message = Some(error);
                     },
                     _ => {}
                 }
                 Command::none()
             }
+            Message::IpcRejected(reason) => {
+                log::error!("Widget host rejected the connection: {}", reason);
+                self.rejected = Some(reason);
+                Command::none()
+            }
             Message::IpcClosed => {
                 self.sender = None;
                 iced::window::close(iced::window::Id::MAIN)
@@ -210,11 +534,7 @@ impl Application for SettingsApp {
     }
 
     fn view(&self) -> Element<'_, Message> {
-        let current_lang = if let Some(config) = &self.config {
-            if config.language == "de" { Language::German } else { Language::English }
-        } else {
-            Language::English
-        };
+        let current_lang = self.current_language();
         let lang_enum: language::Language = current_lang.into();
 
         let title_str = language::get_text(lang_enum, TextId::SettingsTitle);
@@ -239,15 +559,34 @@ impl Application for SettingsApp {
 
             let auto_start = checkbox(language::get_text(lang_enum, TextId::AutoStartup), config.auto_start)
                 .on_toggle(Message::AutoStartToggled);
+            let restore_layout = button(language::get_text(lang_enum, TextId::RestoreLayout))
+                .on_press(Message::RestoreLayout);
+
+            let base_currency = self.base_currency_input.clone().or_else(|| config.base_currency.clone()).unwrap_or_default();
+            let base_currency_input = text_input(language::get_text(lang_enum, TextId::BaseCurrencyPlaceholder), &base_currency)
+                .on_input(Message::BaseCurrencyChanged)
+                .on_submit(Message::BaseCurrencySubmitted)
+                .width(Length::Fixed(160.0));
+            // Only meaningful with a base currency to convert to
+            let convert_prices = checkbox(language::get_text(lang_enum, TextId::ConvertPrices), config.convert_prices)
+                .on_toggle_maybe(config.base_currency.is_some().then_some(Message::ConvertPricesToggled));
 
             column![
                 text(language::get_text(lang_enum, TextId::General)).size(18),
                 row![text(language::get_text(lang_enum, TextId::Language)), lang_pick].spacing(10).align_items(Alignment::Center),
                 row![text(language::get_text(lang_enum, TextId::UpdateInterval)), interval_pick].spacing(10).align_items(Alignment::Center),
-                auto_start
+                row![auto_start, restore_layout].spacing(20).align_items(Alignment::Center),
+                row![text(language::get_text(lang_enum, TextId::BaseCurrency)), base_currency_input, convert_prices].spacing(10).align_items(Alignment::Center)
             ].spacing(10)
         } else {
-            column![text("Loading config...")]
+            if let Some(reason) = &self.rejected {
+                column![
+                    text(language::get_text(lang_enum, TextId::IpcVersionMismatch)).style(iced::theme::Text::Color(iced::Color::from_rgb8(255, 100, 100))),
+                    text(reason).size(12),
+                ].spacing(5)
+            } else {
+                column![text("Loading config...")]
+            }
         };
 
         // Charts Section
@@ -257,8 +596,9 @@ impl Application for SettingsApp {
             .padding(10)
             .width(Length::Fill);
 
+        // Disabled until the previous symbol has loaded or failed
         let add_btn = button(language::get_text(lang_enum, TextId::AddButton))
-            .on_press(Message::AddPressed)
+            .on_press_maybe(self.pending_add.is_none().then_some(Message::AddPressed))
             .padding(10);
 
         let controls = row![input, add_btn].spacing(10);
@@ -271,7 +611,7 @@ impl Application for SettingsApp {
         let mut chart_list = column![].spacing(10);
         for chart in &self.charts {
             // Timeframe Picker
-            let timeframe_list = &["1D", "1W", "1M", "3M", "YTD", "1Y"][..];
+            let timeframe_list = &crate::provider::TIMEFRAMES[..];
             // Ensure current timeframe is valid or default (simple check)
             let tf_selected_str = if timeframe_list.contains(&chart.timeframe.as_str()) {
                 Some(chart.timeframe.as_str())
@@ -285,6 +625,13 @@ impl Application for SettingsApp {
                 move |tf| Message::TimeframeChanged(chart.id.clone(), tf.to_string())
             ).width(Length::Fixed(80.0));
 
+            // Render Style Picker
+            let style_pick = pick_list(
+                &ChartStyle::ALL[..],
+                Some(chart.style),
+                move |style| Message::StyleChanged(chart.id.clone(), style)
+            ).width(Length::Fixed(120.0));
+
             // Lock Toggle
             let lock_icon = if chart.locked { crate::icons::lock_icon() } else { crate::icons::unlock_icon() };
             let lock_text_id = if chart.locked { TextId::Locked } else { TextId::Unlocked };
@@ -308,22 +655,130 @@ impl Application for SettingsApp {
             let row = row![
                 text(&chart.symbol).width(Length::Fill).size(18),
                 tf_pick,
+                style_pick,
                 lock_btn,
                 del_btn
             ]
             .spacing(15)
             .align_items(Alignment::Center)
             .padding(10);
+
+            // Indicators
+            let mut indicator_list = column![].spacing(5).padding([0, 10, 10, 10]);
+            for (index, indicator) in chart.indicators.iter().enumerate() {
+                let period_pick = |period: usize, make: fn(usize) -> Indicator| {
+                    pick_list(
+                        &INDICATOR_PERIODS[..],
+                        Some(period),
+                        move |p| Message::IndicatorChanged(chart.id.clone(), index, make(p))
+                    ).width(Length::Fixed(80.0))
+                };
+
+                let params: Element<'_, Message> = match *indicator {
+                    Indicator::Sma { period } => period_pick(period, |p| Indicator::Sma { period: p }).into(),
+                    Indicator::Ema { period } => period_pick(period, |p| Indicator::Ema { period: p }).into(),
+                    Indicator::Bollinger { period, std_dev } => row![
+                        pick_list(
+                            &INDICATOR_PERIODS[..],
+                            Some(period),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Bollinger { period: p, std_dev })
+                        ).width(Length::Fixed(80.0)),
+                        pick_list(
+                            &BOLLINGER_STD_DEVS[..],
+                            Some(std_dev),
+                            move |d| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Bollinger { period, std_dev: d })
+                        ).width(Length::Fixed(70.0)),
+                    ].spacing(5).into(),
+                    Indicator::Vwap => text("").into(),
+                    Indicator::Rsi { period } => period_pick(period, |p| Indicator::Rsi { period: p }).into(),
+                    Indicator::Macd { fast, slow, signal } => row![
+                        pick_list(
+                            &INDICATOR_PERIODS[..],
+                            Some(fast),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Macd { fast: p, slow, signal })
+                        ).width(Length::Fixed(70.0)),
+                        pick_list(
+                            &INDICATOR_PERIODS[..],
+                            Some(slow),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Macd { fast, slow: p, signal })
+                        ).width(Length::Fixed(70.0)),
+                        pick_list(
+                            &SMOOTHING_PERIODS[..],
+                            Some(signal),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Macd { fast, slow, signal: p })
+                        ).width(Length::Fixed(60.0)),
+                    ].spacing(5).into(),
+                    Indicator::Stochastic { k_period, d_period } => row![
+                        pick_list(
+                            &INDICATOR_PERIODS[..],
+                            Some(k_period),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Stochastic { k_period: p, d_period })
+                        ).width(Length::Fixed(80.0)),
+                        pick_list(
+                            &SMOOTHING_PERIODS[..],
+                            Some(d_period),
+                            move |p| Message::IndicatorChanged(chart.id.clone(), index, Indicator::Stochastic { k_period, d_period: p })
+                        ).width(Length::Fixed(60.0)),
+                    ].spacing(5).into(),
+                };
+
+                let remove_btn = tooltip(
+                    button(svg(crate::icons::trash_icon()).width(Length::Fixed(16.0)).height(Length::Fixed(16.0)))
+                        .on_press(Message::IndicatorRemoved(chart.id.clone(), index))
+                        .style(iced::theme::Button::Destructive)
+                        .padding(4),
+                    language::get_text(lang_enum, TextId::DeleteButton),
+                    tooltip::Position::Top
+                );
+
+                indicator_list = indicator_list.push(
+                    row![text(indicator.name()).width(Length::Fixed(50.0)), params, remove_btn]
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                );
+            }
+
+            let add_indicator = pick_list(
+                &Indicator::DEFAULTS[..],
+                None::<Indicator>,
+                move |indicator| Message::IndicatorAdded(chart.id.clone(), indicator)
+            ).placeholder(language::get_text(lang_enum, TextId::AddIndicator));
+            indicator_list = indicator_list.push(add_indicator);
             
             // wrap in container for styling if needed, or just push row
-            chart_list = chart_list.push(container(row).style(iced::theme::Container::Box));
+            // Symbols plotted against this one as percent change
+            let compare_id = chart.id.clone();
+            let compare_text = self.symbol_inputs.get(&chart.id).cloned().unwrap_or_else(|| chart.compare_symbols.join(", "));
+            let compare = container(
+                text_input(language::get_text(lang_enum, TextId::ComparePlaceholder), &compare_text)
+                    .on_input(move |v| Message::SymbolsChanged(compare_id.clone(), v))
+                    .on_submit(Message::CompareSubmitted(chart.id.clone()))
+            ).padding([0, 10]);
+
+            chart_list = chart_list.push(container(column![row, compare, indicator_list]).style(iced::theme::Container::Box));
         }
 
-        let charts_section = column![
-            text(language::get_text(lang_enum, TextId::Charts)).size(18),
-            controls_column.spacing(5),
-            scrollable(chart_list).height(Length::Fill)
-        ].spacing(10).height(Length::Fill);
+        // Charts / Watchlists / Tickers / Portfolio / Alerts tabs
+        let tab = |id: TextId, tab: Tab| {
+            let style = if self.tab == tab { iced::theme::Button::Primary } else { iced::theme::Button::Text };
+            button(text(language::get_text(lang_enum, id)).size(18))
+                .on_press(Message::TabSelected(tab))
+                .style(style)
+                .padding([4, 10])
+        };
+        let tabs = row![tab(TextId::Charts, Tab::Charts), tab(TextId::Watchlists, Tab::Watchlists), tab(TextId::Tickers, Tab::Tickers), tab(TextId::Portfolio, Tab::Portfolio), tab(TextId::Alerts, Tab::Alerts)].spacing(5);
+
+        let charts_section = match self.tab {
+            Tab::Charts => column![
+                tabs,
+                controls_column.spacing(5),
+                scrollable(chart_list).height(Length::Fill)
+            ],
+            Tab::Watchlists => column![tabs, self.watchlists_view(lang_enum)],
+            Tab::Tickers => column![tabs, self.tickers_view(lang_enum)],
+            Tab::Portfolio => column![tabs, self.portfolio_view(lang_enum)],
+            Tab::Alerts => column![tabs, self.alerts_view(lang_enum)],
+        }.spacing(10).height(Length::Fill);
 
         // Updates Section
         let (status_text, show_update, show_restart) = match &self.update_status {
@@ -399,70 +854,325 @@ impl Application for SettingsApp {
 }
 
 impl SettingsApp {
-    fn send_ipc(&self, msg: IpcMessage) {
-        if let Some(tx) = &self.sender {
-            let tx = tx.clone();
-            let _ = tokio::spawn(async move {
-                let _ = tx.send(msg).await;
-            });
+    fn current_language(&self) -> Language {
+        match &self.config {
+            Some(config) if config.language == "de" => Language::German,
+            _ => Language::English,
         }
     }
+
+    fn language(&self) -> language::Language {
+        self.current_language().into()
+    }
+
+    fn watchlists_view(&self, lang_enum: language::Language) -> Element<'_, Message> {
+        let input = text_input(language::get_text(lang_enum, TextId::WatchlistPlaceholder), &self.watchlist_input)
+            .on_input(Message::WatchlistInputChanged)
+            .on_submit(Message::WatchlistAddPressed)
+            .padding(10)
+            .width(Length::Fill);
+        let add_btn = button(language::get_text(lang_enum, TextId::AddButton))
+            .on_press(Message::WatchlistAddPressed)
+            .padding(10);
+
+        let mut watchlist_list = column![].spacing(10);
+        for watchlist in &self.watchlists {
+            watchlist_list = watchlist_list.push(self.symbols_row(lang_enum, &watchlist.id, &watchlist.symbols, watchlist.locked, None));
+        }
+
+        column![
+            row![input, add_btn].spacing(10),
+            scrollable(watchlist_list).height(Length::Fill)
+        ].spacing(10).into()
+    }
+
+    fn tickers_view(&self, lang_enum: language::Language) -> Element<'_, Message> {
+        let input = text_input(language::get_text(lang_enum, TextId::WatchlistPlaceholder), &self.ticker_input)
+            .on_input(Message::TickerInputChanged)
+            .on_submit(Message::TickerAddPressed)
+            .padding(10)
+            .width(Length::Fill);
+        let add_btn = button(language::get_text(lang_enum, TextId::AddButton))
+            .on_press(Message::TickerAddPressed)
+            .padding(10);
+
+        let mut ticker_list = column![].spacing(10);
+        for ticker in &self.tickers {
+            let id = ticker.id.clone();
+            let speed = tooltip(
+                pick_list(
+                    &crate::ticker::SPEEDS[..],
+                    Some(ticker.speed),
+                    move |speed| Message::TickerSpeedChanged(id.clone(), speed)
+                ).width(Length::Fixed(70.0)),
+                language::get_text(lang_enum, TextId::TickerSpeed),
+                tooltip::Position::Top
+            );
+            ticker_list = ticker_list.push(self.symbols_row(lang_enum, &ticker.id, &ticker.symbols, ticker.locked, Some(speed.into())));
+        }
+
+        column![
+            row![input, add_btn].spacing(10),
+            scrollable(ticker_list).height(Length::Fill)
+        ].spacing(10).into()
+    }
+
+    // Editable symbol list of a watchlist or ticker with its lock and delete buttons
+    fn symbols_row<'a>(&'a self, lang_enum: language::Language, id: &str, symbols: &[String], locked: bool, extra: Option<Element<'a, Message>>) -> Element<'a, Message> {
+        let edit_id = id.to_string();
+
+        // Edited text wins until it is submitted
+        let current = self.symbol_inputs.get(id).cloned().unwrap_or_else(|| symbols.join(", "));
+        let symbols = text_input("", &current)
+            .on_input(move |v| Message::SymbolsChanged(edit_id.clone(), v))
+            .on_submit(Message::SymbolsSubmitted(id.to_string()))
+            .width(Length::Fill);
+
+        let lock_icon = if locked { crate::icons::lock_icon() } else { crate::icons::unlock_icon() };
+        let lock_text_id = if locked { TextId::Locked } else { TextId::Unlocked };
+        let lock_btn = tooltip(
+            button(svg(lock_icon).width(Length::Fixed(20.0)).height(Length::Fixed(20.0)))
+                .on_press(Message::LockToggled(id.to_string(), !locked))
+                .padding(5),
+            language::get_text(lang_enum, lock_text_id),
+            tooltip::Position::Top
+        );
+
+        let del_btn = tooltip(
+            button(svg(crate::icons::trash_icon()).width(Length::Fixed(20.0)).height(Length::Fixed(20.0)))
+                .on_press(Message::DeletePressed(id.to_string()))
+                .style(iced::theme::Button::Destructive)
+                .padding(5),
+            language::get_text(lang_enum, TextId::DeleteButton),
+            tooltip::Position::Top
+        );
+
+        let mut row = row![symbols].spacing(15).align_items(Alignment::Center).padding(10);
+        if let Some(extra) = extra {
+            row = row.push(extra);
+        }
+        container(row.push(lock_btn).push(del_btn)).style(iced::theme::Container::Box).into()
+    }
+
+    fn portfolio_view(&self, lang_enum: language::Language) -> Element<'_, Message> {
+        let show = checkbox(language::get_text(lang_enum, TextId::ShowPortfolio), self.config.as_ref().is_some_and(|c| c.show_portfolio))
+            .on_toggle(Message::PortfolioToggled);
+
+        let input = text_input(language::get_text(lang_enum, TextId::PositionPlaceholder), &self.position_input)
+            .on_input(Message::PositionInputChanged)
+            .on_submit(Message::PositionAddPressed)
+            .padding(10)
+            .width(Length::Fill);
+        let add_btn = button(language::get_text(lang_enum, TextId::AddButton))
+            .on_press(Message::PositionAddPressed)
+            .padding(10);
+
+        let mut position_list = column![].spacing(5);
+        for position in &self.positions {
+            let del_btn = tooltip(
+                button(svg(crate::icons::trash_icon()).width(Length::Fixed(16.0)).height(Length::Fixed(16.0)))
+                    .on_press(Message::PositionDeleted(position.symbol.clone()))
+                    .style(iced::theme::Button::Destructive)
+                    .padding(4),
+                language::get_text(lang_enum, TextId::DeleteButton),
+                tooltip::Position::Top
+            );
+            position_list = position_list.push(container(
+                row![
+                    text(&position.symbol).width(Length::Fill),
+                    text(format!("{}", position.quantity)).width(Length::Fixed(80.0)),
+                    text(crate::currency::format(position.average_cost, &position.currency)).width(Length::Fixed(120.0)),
+                    del_btn
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .padding(5)
+            ).style(iced::theme::Container::Box));
+        }
+
+        // CSV import/export goes through a path, there's no file dialog dependency
+        let csv_path = text_input(language::get_text(lang_enum, TextId::CsvPathPlaceholder), &self.csv_path)
+            .on_input(Message::CsvPathChanged)
+            .width(Length::Fill);
+        let has_path = !self.csv_path.trim().is_empty();
+        let import_btn = button(language::get_text(lang_enum, TextId::ImportButton)).on_press_maybe(has_path.then_some(Message::ImportPressed));
+        let export_btn = button(language::get_text(lang_enum, TextId::ExportButton)).on_press_maybe(has_path.then_some(Message::ExportPressed));
+
+        let mut content = column![
+            show,
+            row![input, add_btn].spacing(10),
+            scrollable(position_list).height(Length::Fill),
+            row![csv_path, import_btn, export_btn].spacing(10).align_items(Alignment::Center),
+        ].spacing(10);
+        if let Some(status) = &self.portfolio_status {
+            content = content.push(text(status).size(14));
+        }
+        content.into()
+    }
+
+    fn alerts_view(&self, lang_enum: language::Language) -> Element<'_, Message> {
+        let format_time = |timestamp: i64| {
+            chrono::DateTime::from_timestamp(timestamp, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%d.%m. %H:%M").to_string())
+                .unwrap_or_default()
+        };
+
+        // One block per symbol, charts first, then symbols that only have alerts left
+        let mut symbols: Vec<&str> = Vec::new();
+        for symbol in self.charts.iter().map(|c| c.symbol.as_str()).chain(self.alerts.iter().map(|r| r.symbol.as_str())) {
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+
+        let mut alert_list = column![].spacing(10);
+        for symbol in symbols {
+            let mut rule_list = column![].spacing(8).padding([0, 10, 10, 10]);
+            for rule in self.alerts.iter().filter(|r| r.symbol == symbol) {
+                let id = rule.id;
+
+                let kind_options: Vec<_> = AlertKind::ALL.into_iter().map(|kind| AlertKindOption { kind, lang: lang_enum }).collect();
+                let kind_pick = pick_list(
+                    kind_options,
+                    Some(AlertKindOption { kind: rule.condition.kind(), lang: lang_enum }),
+                    move |option| Message::AlertKindChanged(id, option.kind)
+                ).width(Length::Fixed(140.0));
+
+                // Edited text wins until it is submitted
+                let value: Element<'_, Message> = match rule.condition.value() {
+                    Some(value) => {
+                        let current = self.alert_inputs.get(&id).cloned().unwrap_or_else(|| value.to_string());
+                        text_input("", &current)
+                            .on_input(move |v| Message::AlertValueChanged(id, v))
+                            .on_submit(Message::AlertValueSubmitted(id))
+                            .width(Length::Fixed(80.0))
+                            .into()
+                    },
+                    None => text("").width(Length::Fixed(80.0)).into(),
+                };
+
+                let cooldown_pick = tooltip(
+                    pick_list(
+                        &ALERT_COOLDOWNS[..],
+                        Some(rule.cooldown_minutes),
+                        move |minutes| Message::AlertCooldownChanged(id, minutes)
+                    ).width(Length::Fixed(80.0)),
+                    language::get_text(lang_enum, TextId::AlertCooldown),
+                    tooltip::Position::Top
+                );
+
+                let enabled = checkbox(language::get_text(lang_enum, TextId::AlertEnabled), rule.enabled)
+                    .on_toggle(move |enabled| Message::AlertToggled(id, enabled));
+
+                let del_btn = tooltip(
+                    button(svg(crate::icons::trash_icon()).width(Length::Fixed(16.0)).height(Length::Fixed(16.0)))
+                        .on_press(Message::AlertDeleted(id))
+                        .style(iced::theme::Button::Destructive)
+                        .padding(4),
+                    language::get_text(lang_enum, TextId::DeleteButton),
+                    tooltip::Position::Top
+                );
+
+                let last_fired = match rule.last_fired {
+                    Some(t) => format_time(t),
+                    None => language::get_text(lang_enum, TextId::AlertNever).to_string(),
+                };
+
+                rule_list = rule_list.push(column![
+                    row![kind_pick, value, cooldown_pick, enabled, del_btn].spacing(10).align_items(Alignment::Center),
+                    text(language::get_text(lang_enum, TextId::AlertLastFired).replacen("{}", &last_fired, 1)).size(12),
+                ].spacing(2));
+            }
+
+            rule_list = rule_list.push(
+                button(language::get_text(lang_enum, TextId::AddAlert))
+                    .on_press(Message::AlertAdded(symbol.to_string()))
+                    .padding(5)
+            );
+
+            alert_list = alert_list.push(container(column![
+                container(text(symbol).size(18)).padding(10),
+                rule_list
+            ]).style(iced::theme::Container::Box));
+        }
+
+        if !self.alert_history.is_empty() {
+            let mut history = column![text(language::get_text(lang_enum, TextId::AlertHistory)).size(16)].spacing(3);
+            for event in self.alert_history.iter().take(10) {
+                history = history.push(text(format!("{}  {}", format_time(event.fired_at), event.message(lang_enum))).size(12));
+            }
+            alert_list = alert_list.push(history);
+        }
+
+        scrollable(alert_list).height(Length::Fill).into()
+    }
+
+    // Apply a local edit to a chart's indicator list and push the result to the widget host
+    fn update_indicators(&mut self, id: String, edit: impl FnOnce(&mut Vec<Indicator>)) {
+        if let Some(chart) = self.charts.iter_mut().find(|c| c.id == id) {
+            edit(&mut chart.indicators);
+            let indicators = chart.indicators.clone();
+            self.send_ipc(IpcMessage::SetChartIndicators(id, indicators));
+        }
+    }
+
+    // Send a request to the widget host, returns its id to match the answer against
+    fn send_ipc(&mut self, msg: IpcMessage) -> Option<u64> {
+        let tx = self.sender.clone()?;
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        let _ = tokio::spawn(async move {
+            let _ = tx.send(Frame::Request { id, message: msg }).await;
+        });
+        Some(id)
+    }
 }
 
 // Helper logic
 async fn subscription_logic(mut output: iced::futures::channel::mpsc::Sender<Message>) {
     loop {
         // Connect
-        let client = loop {
-            match ClientOptions::new().open(PIPE_NAME) {
+        let mut client = loop {
+            match crate::ipc::connect().await {
                 Ok(c) => break c,
                 Err(_) => {
                      tokio::time::sleep(std::time::Duration::from_secs(1)).await;
                 }
             }
         };
+
+        match crate::ipc::client_handshake(&mut client, crate::ipc::SETTINGS_CLIENT).await {
+            Ok(()) => {},
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+                // Retrying won't help until the host is restarted with the same version
+                let _ = output.try_send(Message::IpcRejected(e.to_string()));
+                return;
+            },
+            Err(e) => {
+                log::warn!("IPC handshake failed: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        }
         
         let (mut reader, mut writer) = tokio::io::split(client);
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<IpcMessage>(32);
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<Frame>(32);
         
         let _ = output.try_send(Message::IpcConnected(tx));
-        
-        loop {
-            // Helper future for reading
-            let read_fut = async {
-                let mut len_buf = [0u8; 4];
-                if reader.read_exact(&mut len_buf).await.is_err() { return None; }
-                let len = u32::from_le_bytes(len_buf) as usize;
-                let mut buf = vec![0u8; len];
-                if reader.read_exact(&mut buf).await.is_err() { return None; }
-                serde_json::from_slice::<IpcMessage>(&buf).ok()
-            };
 
-            tokio::select! {
-                msg_out = rx.recv() => {
-                    match msg_out {
-                         Some(msg) => {
-                             if let Ok(json) = serde_json::to_string(&msg) {
-                                 let bytes = json.as_bytes();
-                                 let len = bytes.len() as u32;
-                                 if writer.write_all(&len.to_le_bytes()).await.is_err() { break; }
-                                 if writer.write_all(bytes).await.is_err() { break; }
-                             }
-                         }
-                         None => break,
-                    }
-                }
-                msg_in = read_fut => {
-                    match msg_in {
-                        Some(msg) => {
-                            let _ = output.try_send(Message::IpcServerMessage(msg));
-                        }
-                        None => break, // Read error
-                    }
-                }
+        // Writes run in their own task so a pending read is never cancelled halfway through a frame
+        let writer_task = tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                if crate::ipc::write_frame(&mut writer, &frame).await.is_err() { break; }
             }
-        }
+        });
         
+        // Read until the host closes the connection or sends garbage
+        while let Ok(Some(frame)) = crate::ipc::read_frame(&mut reader).await {
+            let _ = output.try_send(Message::IpcServerFrame(frame));
+        }
+
+        writer_task.abort();
         let _ = output.try_send(Message::IpcClosed);
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     }
@@ -0,0 +1,258 @@
+use winit::window::{Window, WindowId};
+use winit::event_loop::ActiveEventLoop;
+use winit::event::{WindowEvent, ElementState, MouseButton};
+use winit::dpi::PhysicalPosition;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use softbuffer::{Context, Surface};
+use std::num::NonZeroU32;
+use crate::chart::{blit_rgba, create_widget_window, draw_unlocked_frame, show_widget};
+use crate::platform;
+use crate::common::WindowHandler;
+use crate::config::{TickerConfig, WidgetConfig, default_ticker_speed};
+use crate::currency::{FxRates, display_prices};
+use crate::fetcher::{FetchService, SymbolData};
+use crate::provider::QuoteSeries;
+use crate::render::{TickerItem, TickerTape, layout_ticker, render_ticker};
+
+const TIMEFRAME: &str = "1D";
+const DEFAULT_SIZE: (u32, u32) = (800, 36);
+// ~30 fps is smooth enough for text moving a few pixels per frame
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Selectable scroll speeds in pixels per second.
+pub const SPEEDS: [u32; 5] = [30, 60, 90, 120, 180];
+pub const MAX_SPEED: u32 = 1000;
+
+/// Thin window scrolling symbols with their last price and daily change. Scrolling pauses
+/// while the mouse is over it so a value can be read.
+pub struct TickerWindow {
+    window: Rc<Window>,
+    surface: Surface<Rc<Window>, Rc<Window>>,
+    _context: Context<Rc<Window>>,
+    data: SymbolData, // 1D series
+    speed: u32,
+    fx: Option<FxRates>, // Set while prices are shown in the base currency
+    locked: bool,
+    cursor_position: PhysicalPosition<f64>,
+    hovered: bool,
+    offset: f64,
+    last_frame: Option<Instant>, // None while not scrolling, so a pause doesn't turn into a jump
+    tape: Option<(u32, TickerTape)>, // Laid out for this height, cleared when the texts change
+    min_size: Option<(u32, u32)>, // Last one passed to the window
+}
+
+impl TickerWindow {
+    pub fn new(event_loop: &ActiveEventLoop, fetcher: FetchService, symbols: Vec<String>, config: Option<TickerConfig>) -> Self {
+        let placement = config.as_ref().map(|c| (c.x, c.y, c.width, c.height));
+        let window = create_widget_window(event_loop, "Ticker", placement);
+        if config.is_none() {
+            let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(DEFAULT_SIZE.0, DEFAULT_SIZE.1));
+        }
+
+        let context = Context::new(window.clone()).unwrap();
+        let mut surface = Surface::new(&context, window.clone()).unwrap();
+
+        let size = window.inner_size();
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+             surface.resize(width, height).unwrap();
+        }
+
+        // Persisted data is shown right away, marked as stale until the network answers
+        let speed = config.as_ref().map(|c| c.speed).unwrap_or(default_ticker_speed());
+        let data = SymbolData::new(fetcher, symbols, TIMEFRAME.to_string(), config.and_then(|c| c.provider));
+
+        let ticker = Self {
+            window,
+            surface,
+            _context: context,
+            data,
+            speed,
+            fx: None,
+            locked: true,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+            hovered: false,
+            offset: 0.0,
+            last_frame: None,
+            tape: None,
+            min_size: None,
+        };
+
+        platform::set_locked(&ticker.window, true);
+        ticker.show_if_loaded();
+        ticker.data.refresh();
+
+        ticker
+    }
+
+    fn show_if_loaded(&self) {
+        if !self.data.is_empty() {
+            show_widget(&self.window);
+        }
+    }
+
+    fn scrolling(&self) -> bool {
+        !self.hovered && self.speed > 0 && !self.data.is_empty()
+    }
+}
+
+impl WindowHandler for TickerWindow {
+    fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        platform::set_locked(&self.window, locked);
+        self.window.request_redraw();
+    }
+
+    fn set_fx_rates(&mut self, rates: Option<&FxRates>, convert_prices: bool) {
+        self.fx = rates.filter(|_| convert_prices).cloned();
+        self.tape = None;
+        self.window.request_redraw();
+    }
+
+    fn set_symbols(&mut self, symbols: Vec<String>) {
+        self.data.set_symbols(symbols);
+        self.data.refresh();
+        self.tape = None;
+        self.show_if_loaded();
+        self.window.request_redraw();
+    }
+
+    fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    fn refresh(&mut self) {
+        self.data.refresh();
+    }
+
+    fn tick(&mut self) {
+        if self.scrolling() && self.last_frame.is_none_or(|t| t.elapsed() >= FRAME_INTERVAL) {
+            self.window.request_redraw();
+        }
+    }
+
+    fn next_wake(&self) -> Option<Instant> {
+        self.scrolling().then(|| self.last_frame.map_or(Instant::now(), |t| t + FRAME_INTERVAL))
+    }
+
+    fn has_data(&self) -> bool {
+        !self.data.is_empty()
+    }
+
+    fn fetch_failed(&mut self, symbol: &str) -> bool {
+        self.data.fetch_failed(symbol)
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.data.symbols().to_vec()
+    }
+
+    fn get_config(&self) -> Option<WidgetConfig> {
+        let size = self.window.inner_size();
+        let pos = self.window.outer_position().unwrap_or(winit::dpi::PhysicalPosition::new(0, 0));
+        Some(WidgetConfig::Ticker(TickerConfig {
+            symbols: self.data.symbols().to_vec(),
+            x: pos.x,
+            y: pos.y,
+            width: size.width,
+            height: size.height,
+            speed: self.speed,
+            provider: self.data.provider().map(str::to_string),
+        }))
+    }
+
+    fn handle_event(&mut self, event: WindowEvent, _event_loop: &ActiveEventLoop) {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.resize(size);
+            },
+            WindowEvent::CursorEntered { .. } => {
+                self.hovered = true;
+                self.last_frame = None;
+            },
+            WindowEvent::CursorLeft { .. } => {
+                self.hovered = false;
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = position;
+                platform::update_cursor(&self.window, position, self.locked);
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if !self.locked => {
+                platform::drag(&self.window, self.cursor_position);
+            },
+            WindowEvent::RedrawRequested => {
+                self.redraw();
+            },
+            _ => (),
+        }
+    }
+
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            self.surface.resize(width, height).unwrap();
+            self.window.request_redraw();
+        }
+    }
+
+    fn update_data(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries) {
+        if self.data.update(symbol, timeframe, series) {
+            self.tape = None;
+            show_widget(&self.window);
+        }
+    }
+
+    fn redraw(&mut self) {
+        // Advance by the time that actually passed, frames are not guaranteed to arrive on schedule
+        if self.scrolling() {
+            let now = Instant::now();
+            if let Some(last_frame) = self.last_frame {
+                self.offset += self.speed as f64 * (now - last_frame).as_secs_f64();
+            }
+            self.last_frame = Some(now);
+        } else {
+            self.last_frame = None;
+        }
+
+        if let Ok(mut buffer) = self.surface.buffer_mut() {
+            buffer.fill(0);
+
+            let width = buffer.width().get();
+            let height = buffer.height().get();
+
+            // Only scrolled between frames, the texts are laid out again when data or height change
+            if self.tape.as_ref().is_none_or(|(tape_height, _)| *tape_height != height) {
+                let prices: Vec<_> = self.data.symbols().iter()
+                    .map(|symbol| match self.data.get(symbol) {
+                        Some(series) => display_prices(self.fx.as_ref(), &series.candles, &series.currency),
+                        None => display_prices(None, &[], ""),
+                    })
+                    .collect();
+                let items: Vec<TickerItem> = self.data.symbols().iter().zip(&prices)
+                    .map(|(symbol, (quotes, currency))| TickerItem { symbol, currency, quotes })
+                    .collect();
+                self.tape = Some((height, layout_ticker(&items, height)));
+            }
+            let Some((_, tape)) = &self.tape else { return };
+            let rendered = render_ticker(tape, width, height, self.offset);
+
+            // A round trip to the window manager on X11, not something to repeat every frame
+            if self.min_size != Some(rendered.min_size) {
+                let (min_width, min_height) = rendered.min_size;
+                self.window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_width as f64, min_height as f64)));
+                self.min_size = Some(rendered.min_size);
+            }
+
+            blit_rgba(&mut buffer, &rendered.rgba);
+
+            if !self.locked {
+                draw_unlocked_frame(&mut buffer, width as i32, height as i32);
+            }
+
+            buffer.present().ok();
+        }
+    }
+}
@@ -75,37 +75,9 @@ pub fn perform_update(use_prereleases: bool) -> Result<String, Box<dyn Error>> {
 
 use winit::event_loop::EventLoopProxy;
 use crate::common::UserEvent;
-use windows::{
-
-    UI::Notifications::{ToastNotification, ToastNotificationManager, ToastTemplateType},
-    core::HSTRING,
-    Foundation::TypedEventHandler,
-};
-
 use crate::language::{Language, TextId, get_text};
 
 pub fn show_update_notification(version: &str, aum_id: &str, proxy: EventLoopProxy<UserEvent>, lang: Language) -> Result<(), Box<dyn Error>> {
-    // Create Toast XML
-    let toast_xml = ToastNotificationManager::GetTemplateContent(ToastTemplateType::ToastText02)?;
-    
-    let text_nodes = toast_xml.GetElementsByTagName(&HSTRING::from("text"))?;
-    text_nodes.Item(0)?.AppendChild(&toast_xml.CreateTextNode(&HSTRING::from(get_text(lang, TextId::UpdateAvailable)))?)?;
     let body_text = get_text(lang, TextId::UpdateBody).replace("{}", version);
-    text_nodes.Item(1)?.AppendChild(&toast_xml.CreateTextNode(&HSTRING::from(body_text))?)?;
-
-    // Create Toast
-    let toast = ToastNotification::CreateToastNotification(&toast_xml)?;
-
-    // Handle Click
-    let proxy_c = proxy.clone();
-    toast.Activated(&TypedEventHandler::new(move |_, _| {
-        let _ = proxy_c.send_event(UserEvent::OpenSettings);
-        Ok(())
-    }))?;
-
-    // Show Toast
-    let notifier = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(aum_id))?;
-    notifier.Show(&toast)?;
-
-    Ok(())
+    crate::platform::show_toast(aum_id, get_text(lang, TextId::UpdateAvailable), &body_text, proxy)
 }
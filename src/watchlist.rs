@@ -0,0 +1,225 @@
+use winit::window::{Window, WindowId};
+use winit::event_loop::ActiveEventLoop;
+use winit::event::{WindowEvent, ElementState, MouseButton};
+use winit::dpi::PhysicalPosition;
+use std::rc::Rc;
+use softbuffer::{Context, Surface};
+use std::num::NonZeroU32;
+use crate::chart::{blit_rgba, create_widget_window, draw_unlocked_frame, show_widget};
+use crate::platform;
+use crate::common::WindowHandler;
+use crate::config::{WatchlistConfig, WidgetConfig};
+use crate::currency::{FxRates, display_prices};
+use crate::fetcher::{FetchService, SymbolData};
+use crate::provider::QuoteSeries;
+use crate::render::{WatchlistRow, render_watchlist, watchlist_height};
+
+const DEFAULT_TIMEFRAME: &str = "1D";
+const DEFAULT_WIDTH: u32 = 420;
+
+/// Splits user input like "msft, aapl sap.de" into upper case symbols, dropping duplicates.
+pub fn parse_symbols(input: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+    for symbol in input.split([',', ' ', ';']).map(|s| s.trim().to_uppercase()).filter(|s| !s.is_empty()) {
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    symbols
+}
+
+/// Compact table widget showing many symbols at once, fed by the same `FetchService` as the charts.
+pub struct WatchlistWindow {
+    window: Rc<Window>,
+    surface: Surface<Rc<Window>, Rc<Window>>,
+    _context: Context<Rc<Window>>,
+    data: SymbolData,
+    fx: Option<FxRates>, // Set while prices are shown in the base currency
+    locked: bool,
+    cursor_position: PhysicalPosition<f64>,
+}
+
+impl WatchlistWindow {
+    pub fn new(event_loop: &ActiveEventLoop, fetcher: FetchService, symbols: Vec<String>, config: Option<WatchlistConfig>) -> Self {
+        // New watchlists get a height that fits all rows, the position is left to the OS
+        let placement = config.as_ref().map(|c| (c.x, c.y, c.width, c.height));
+        let window = create_widget_window(event_loop, "Watchlist", placement);
+        if config.is_none() {
+            let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(DEFAULT_WIDTH, watchlist_height(symbols.len())));
+        }
+
+        let context = Context::new(window.clone()).unwrap();
+        let mut surface = Surface::new(&context, window.clone()).unwrap();
+
+        let size = window.inner_size();
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+             surface.resize(width, height).unwrap();
+        }
+
+        // Persisted data is shown right away, marked as stale until the network answers
+        let timeframe = config.as_ref().and_then(|c| c.timeframe.clone()).unwrap_or(DEFAULT_TIMEFRAME.to_string());
+        let data = SymbolData::new(fetcher, symbols, timeframe, config.and_then(|c| c.provider));
+
+        let watchlist = Self {
+            window,
+            surface,
+            _context: context,
+            data,
+            fx: None,
+            locked: true,
+            cursor_position: PhysicalPosition::new(0.0, 0.0),
+        };
+
+        platform::set_locked(&watchlist.window, true);
+        watchlist.show_if_loaded();
+        watchlist.data.refresh();
+
+        watchlist
+    }
+
+    fn show_if_loaded(&self) {
+        if !self.data.is_empty() {
+            show_widget(&self.window);
+        }
+    }
+}
+
+impl WindowHandler for WatchlistWindow {
+    fn window_id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+        platform::set_locked(&self.window, locked);
+        self.window.request_redraw();
+    }
+
+    fn set_timeframe(&mut self, timeframe: String) {
+        if timeframe == self.data.timeframe() {
+            return;
+        }
+        self.data.set_timeframe(timeframe);
+        self.data.refresh();
+        self.show_if_loaded();
+        self.window.request_redraw();
+    }
+
+    fn set_fx_rates(&mut self, rates: Option<&FxRates>, convert_prices: bool) {
+        self.fx = rates.filter(|_| convert_prices).cloned();
+        self.window.request_redraw();
+    }
+
+    fn set_symbols(&mut self, symbols: Vec<String>) {
+        self.data.set_symbols(symbols);
+        self.data.refresh();
+        self.show_if_loaded();
+        self.window.request_redraw();
+    }
+
+    fn refresh(&mut self) {
+        self.data.refresh();
+    }
+
+    fn has_data(&self) -> bool {
+        !self.data.is_empty()
+    }
+
+    fn fetch_failed(&mut self, symbol: &str) -> bool {
+        self.data.fetch_failed(symbol)
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.data.symbols().to_vec()
+    }
+
+    fn get_config(&self) -> Option<WidgetConfig> {
+        let size = self.window.inner_size();
+        let pos = self.window.outer_position().unwrap_or(winit::dpi::PhysicalPosition::new(0, 0));
+        Some(WidgetConfig::Watchlist(WatchlistConfig {
+            symbols: self.data.symbols().to_vec(),
+            x: pos.x,
+            y: pos.y,
+            width: size.width,
+            height: size.height,
+            timeframe: Some(self.data.timeframe().to_string()),
+            provider: self.data.provider().map(str::to_string),
+        }))
+    }
+
+    fn handle_event(&mut self, event: WindowEvent, _event_loop: &ActiveEventLoop) {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.resize(size);
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = position;
+                platform::update_cursor(&self.window, position, self.locked);
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if !self.locked => {
+                platform::drag(&self.window, self.cursor_position);
+            },
+            WindowEvent::RedrawRequested => {
+                self.redraw();
+            },
+            _ => (),
+        }
+    }
+
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            self.surface.resize(width, height).unwrap();
+            self.window.request_redraw();
+        }
+    }
+
+    fn update_data(&mut self, symbol: &str, timeframe: &str, series: QuoteSeries) {
+        if self.data.update(symbol, timeframe, series) {
+            show_widget(&self.window);
+        }
+    }
+
+    fn redraw(&mut self) {
+        if let Ok(mut buffer) = self.surface.buffer_mut() {
+            buffer.fill(0);
+
+            let width = buffer.width().get();
+            let height = buffer.height().get();
+
+            let prices: Vec<_> = self.data.symbols().iter()
+                .map(|symbol| match self.data.get(symbol) {
+                    Some(series) => display_prices(self.fx.as_ref(), &series.candles, &series.currency),
+                    None => display_prices(None, &[], ""),
+                })
+                .collect();
+            let rows: Vec<WatchlistRow> = self.data.symbols().iter().zip(&prices)
+                .map(|(symbol, (quotes, currency))| WatchlistRow { symbol, currency, quotes })
+                .collect();
+            let rendered = render_watchlist(&rows, width, height);
+
+            let (min_width, min_height) = rendered.min_size;
+            self.window.set_min_inner_size(Some(winit::dpi::LogicalSize::new(min_width as f64, min_height as f64)));
+
+            blit_rgba(&mut buffer, &rendered.rgba);
+
+            if !self.locked {
+                draw_unlocked_frame(&mut buffer, width as i32, height as i32);
+            }
+
+            buffer.present().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_symbol_lists() {
+        assert_eq!(parse_symbols("msft, aapl sap.de"), vec!["MSFT", "AAPL", "SAP.DE"]);
+        assert_eq!(parse_symbols(" BTC-USD;eurusd=X ,, "), vec!["BTC-USD", "EURUSD=X"]);
+        assert_eq!(parse_symbols("aapl AAPL msft aapl"), vec!["AAPL", "MSFT"]);
+        assert!(parse_symbols(" , ; ").is_empty());
+    }
+}